@@ -0,0 +1,127 @@
+use crate::config::rabbitmq::PoolError;
+use crate::model::consumer::ConsumedMessage;
+use crate::model::mqtt::ConfirmStatus;
+use lapin::BasicProperties;
+use rumqttc::QoS;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Boxed so the broker traits can be used as trait objects
+pub type BrokerFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub type BrokerResult<T> = Result<T, BrokerError>;
+
+/// Resolves to the broker's confirm for one published message
+pub type PendingConfirm = BrokerFuture<'static, BrokerResult<ConfirmStatus>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrokerError {
+    /// The broker refused the request as given, e.g. an invalid MQTT topic filter
+    Invalid(String),
+    /// The broker is out of reach or busy, so callers may retry
+    Unavailable(String),
+    Failed(String),
+}
+
+impl fmt::Display for BrokerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrokerError::Invalid(e) | BrokerError::Unavailable(e) | BrokerError::Failed(e) => {
+                f.write_str(e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BrokerError {}
+
+impl From<PoolError> for BrokerError {
+    fn from(e: PoolError) -> Self {
+        match e {
+            PoolError::Channel(_) => BrokerError::Failed(e.to_string()),
+            _ => BrokerError::Unavailable(e.to_string()),
+        }
+    }
+}
+
+/// Where a publisher sends its messages
+#[derive(Debug, Clone, Default)]
+pub struct PublishTarget {
+    /// Empty for the default exchange, where `routing_key` names the queue; AMQP only
+    pub exchange: String,
+    /// The routing key, or the topic over MQTT
+    pub routing_key: String,
+    /// 0 publishes without waiting; over AMQP 1 and 2 both mean publisher confirms
+    pub qos: u8,
+    /// Have the broker keep the last message for new subscribers; MQTT only
+    pub retain: bool,
+    /// Declare the queue before publishing to it through the default exchange. Replies
+    /// skip this, since their queue belongs to the requester.
+    pub declare: bool,
+}
+
+impl PublishTarget {
+    pub fn requires_confirm(&self) -> bool {
+        self.qos > 0
+    }
+
+    pub fn mqtt_qos(&self) -> QoS {
+        rumqttc::qos(self.qos).unwrap_or(QoS::AtMostOnce)
+    }
+}
+
+/// One message to publish. MQTT 3.1.1 has nowhere to put the properties, so only the
+/// payload goes over it.
+#[derive(Debug, Clone)]
+pub struct OutgoingMessage {
+    pub payload: Vec<u8>,
+    pub properties: BasicProperties,
+}
+
+/// Publishing and consuming, whichever protocol carries the messages. The `/mqtt` publish
+/// and consume handlers only go through this, so they run the same against RabbitMQ, an
+/// MQTT broker or the in-memory broker.
+pub trait MessageBroker: Send + Sync {
+    /// Get ready to publish to `target`; producers keep one publisher for all their messages
+    fn open_publisher(
+        &self,
+        target: PublishTarget,
+    ) -> BrokerFuture<'_, BrokerResult<Box<dyn BrokerPublisher>>>;
+
+    /// Start receiving from a queue, or a topic filter over MQTT. `qos` is the MQTT
+    /// subscription's QoS, 0 to 2; other brokers ignore it.
+    fn subscribe<'a>(
+        &'a self,
+        source: &'a str,
+        qos: u8,
+    ) -> BrokerFuture<'a, BrokerResult<Box<dyn BrokerSubscription>>>;
+
+    /// Receive RPC replies on a new exclusive queue, named by the broker and deleted with
+    /// the subscription, along with a publisher to `target` for the request itself
+    fn reply_queue(&self, target: PublishTarget) -> BrokerFuture<'_, BrokerResult<ReplyQueue>>;
+}
+
+/// An RPC's reply queue. Over AMQP the publisher shares the replies' channel, so a request
+/// only ever takes one channel from the pool.
+pub struct ReplyQueue {
+    /// The queue's name, for `reply_to`
+    pub name: String,
+    pub publisher: Box<dyn BrokerPublisher>,
+    pub replies: Box<dyn BrokerSubscription>,
+}
+
+pub trait BrokerPublisher: Send + Sync {
+    /// Hand a message to the broker. The returned confirm resolves once the broker has
+    /// taken it, or right away with `NotRequested` at qos 0.
+    fn send(&self, message: OutgoingMessage) -> BrokerFuture<'_, BrokerResult<PendingConfirm>>;
+}
+
+/// Messages from one source, acknowledged as they are handed out
+pub trait BrokerSubscription: Send {
+    /// The next message, or `None` once the broker has stopped sending
+    fn next(&mut self) -> BrokerFuture<'_, Option<Result<ConsumedMessage, String>>>;
+
+    /// Stop receiving; messages not handed out yet go back to the broker
+    fn close(self: Box<Self>) -> BrokerFuture<'static, ()>;
+}
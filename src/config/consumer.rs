@@ -0,0 +1,727 @@
+use crate::config::broker::MessageBroker;
+use crate::config::db::DynamoDbConfig;
+use crate::config::dead_letter::{
+    dead_letter_properties, declare_dead_letter_queue, declare_delay_queue, republish,
+    retry_properties,
+};
+use crate::config::rabbitmq::{backoff, RabbitMqPool};
+use crate::config::rpc::{RpcHandler, RpcResponders};
+use crate::config::schema::SchemaRegistry;
+use crate::config::transport::Transport;
+use crate::model::consumer::{
+    ConsumedMessage, ConsumerSpec, HandlerSpec, RetryStrategy, WorkerSnapshot, WorkerStatus,
+};
+use crate::model::dead_letter::{retry_count, DEAD_LETTER_EXCHANGE};
+use aws_sdk_dynamodb::{error::DisplayErrorContext, types::AttributeValue};
+use chrono::{DateTime, Utc};
+use futures_lite::StreamExt;
+use hyper::{client::HttpConnector, Body, Client, Method, Request, Uri};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use lapin::{
+    acker::Acker,
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicCancelOptions, BasicConsumeOptions, BasicQosOptions,
+        BasicRejectOptions,
+    },
+    types::FieldTable,
+    BasicProperties,
+};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{watch, Semaphore};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+pub type HandlerFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+/// Processes the messages of one consumer worker. What happens to a message on an error is
+/// up to the worker's failure policy.
+pub trait MessageHandler: Send + Sync {
+    fn handle<'a>(&'a self, message: &'a ConsumedMessage) -> HandlerFuture<'a>;
+}
+
+struct LogHandler {
+    worker_id: String,
+}
+
+impl MessageHandler for LogHandler {
+    fn handle<'a>(&'a self, message: &'a ConsumedMessage) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            println!(
+                "📨 [{}] {} from {}: {}",
+                self.worker_id,
+                message.id,
+                message.source,
+                message.body_value()
+            );
+            Ok(())
+        })
+    }
+}
+
+struct WebhookHandler {
+    url: Uri,
+    client: Client<HttpsConnector<HttpConnector>>,
+}
+
+impl MessageHandler for WebhookHandler {
+    fn handle<'a>(&'a self, message: &'a ConsumedMessage) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(self.url.clone())
+                .header("content-type", "application/json")
+                .body(Body::from(message.to_json().to_string()))
+                .map_err(|e| format!("Failed to build webhook request: {}", e))?;
+
+            let response = tokio::time::timeout(WEBHOOK_TIMEOUT, self.client.request(request))
+                .await
+                .map_err(|_| format!("Webhook {} timed out", self.url))?
+                .map_err(|e| format!("Webhook {} failed: {}", self.url, e))?;
+            if !response.status().is_success() {
+                return Err(format!(
+                    "Webhook {} answered {}",
+                    self.url,
+                    response.status()
+                ));
+            }
+            Ok(())
+        })
+    }
+}
+
+struct DynamoDbHandler {
+    db: DynamoDbConfig,
+    table: String,
+}
+
+impl MessageHandler for DynamoDbHandler {
+    fn handle<'a>(&'a self, message: &'a ConsumedMessage) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            let mut request = self
+                .db
+                .get_client()
+                .put_item()
+                .table_name(&self.table)
+                .item("id", AttributeValue::S(message.id.clone()))
+                .item("source", AttributeValue::S(message.source.clone()))
+                .item(
+                    "body",
+                    AttributeValue::S(String::from_utf8_lossy(&message.body).into_owned()),
+                )
+                .item(
+                    "received_at",
+                    AttributeValue::S(message.received_at.to_rfc3339()),
+                );
+            if let Some(content_type) = &message.content_type {
+                request = request.item("content_type", AttributeValue::S(content_type.clone()));
+            }
+
+            request.send().await.map_err(|e| {
+                format!(
+                    "Failed to store message in {}: {}",
+                    self.table,
+                    DisplayErrorContext(&e)
+                )
+            })?;
+            Ok(())
+        })
+    }
+}
+
+async fn build_handler(
+    worker_id: &str,
+    spec: &HandlerSpec,
+    transport: &Transport,
+    responders: &RpcResponders,
+) -> Result<Arc<dyn MessageHandler>, String> {
+    Ok(match spec {
+        HandlerSpec::Log => Arc::new(LogHandler {
+            worker_id: worker_id.to_string(),
+        }),
+        HandlerSpec::Webhook { url } => {
+            let url: Uri = url
+                .parse()
+                .map_err(|e| format!("Invalid webhook url '{}': {}", url, e))?;
+            let connector = HttpsConnectorBuilder::new()
+                .with_native_roots()
+                .https_or_http()
+                .enable_http1()
+                .build();
+            Arc::new(WebhookHandler {
+                url,
+                client: Client::builder().build(connector),
+            })
+        }
+        HandlerSpec::Dynamodb { table } => Arc::new(DynamoDbHandler {
+            db: DynamoDbConfig::new()
+                .await
+                .map_err(|e| format!("Failed to connect to DynamoDB: {}", e))?,
+            table: table.trim().to_string(),
+        }),
+        HandlerSpec::Rpc { responder } => Arc::new(RpcHandler {
+            responder: responders.get(responder.trim()).ok_or_else(|| {
+                format!(
+                    "Unknown RPC responder '{}'; registered: {}",
+                    responder.trim(),
+                    responders.names().join(", ")
+                )
+            })?,
+            transport: transport.clone(),
+        }),
+    })
+}
+
+#[derive(Default)]
+struct WorkerCounters {
+    received: AtomicU64,
+    processed: AtomicU64,
+    failed: AtomicU64,
+    in_flight: AtomicU64,
+    requeued: AtomicU64,
+    retried: AtomicU64,
+    dead_lettered: AtomicU64,
+    invalid: AtomicU64,
+    reconnects: AtomicU64,
+}
+
+/// A background consumer, running until it is stopped
+pub struct ConsumerWorker {
+    pub id: String,
+    pub spec: ConsumerSpec,
+    transport: &'static str,
+    schemas: SchemaRegistry,
+    started_at: DateTime<Utc>,
+    status: Mutex<WorkerStatus>,
+    counters: WorkerCounters,
+    last_error: Mutex<Option<String>>,
+    stop: watch::Sender<bool>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ConsumerWorker {
+    pub fn snapshot(&self) -> WorkerSnapshot {
+        let counters = &self.counters;
+        WorkerSnapshot {
+            id: self.id.clone(),
+            transport: self.transport,
+            spec: self.spec.clone(),
+            status: self.status(),
+            started_at: self.started_at,
+            received: counters.received.load(Ordering::Relaxed),
+            processed: counters.processed.load(Ordering::Relaxed),
+            failed: counters.failed.load(Ordering::Relaxed),
+            in_flight: counters.in_flight.load(Ordering::Relaxed),
+            requeued: counters.requeued.load(Ordering::Relaxed),
+            retried: counters.retried.load(Ordering::Relaxed),
+            dead_lettered: counters.dead_lettered.load(Ordering::Relaxed),
+            invalid: counters.invalid.load(Ordering::Relaxed),
+            reconnects: counters.reconnects.load(Ordering::Relaxed),
+            last_error: self.last_error.lock().unwrap().clone(),
+        }
+    }
+
+    fn status(&self) -> WorkerStatus {
+        *self.status.lock().unwrap()
+    }
+
+    fn set_status(&self, status: WorkerStatus) {
+        *self.status.lock().unwrap() = status;
+    }
+
+    fn record_error(&self, error: String) {
+        eprintln!("Consumer {}: {}", self.id, error);
+        *self.last_error.lock().unwrap() = Some(error);
+    }
+
+    /// With `validate_schema`, why a message does not match the schema for its queue or
+    /// routing key. It is counted as invalid.
+    fn schema_violation(&self, message: &ConsumedMessage) -> Option<String> {
+        if !self.spec.validate_schema {
+            return None;
+        }
+        let mut subjects = vec![message.source.as_str()];
+        if message.routing_key != message.source {
+            subjects.push(message.routing_key.as_str());
+        }
+        let violation = subjects
+            .into_iter()
+            .find_map(|subject| self.schemas.validate(subject, &message.body).err())?;
+        self.counters.invalid.fetch_add(1, Ordering::Relaxed);
+        self.record_error(format!("Message {}: {}", message.id, violation));
+        Some(violation.to_string())
+    }
+
+    /// Run the handler for one message and count the outcome
+    async fn process(
+        &self,
+        handler: &dyn MessageHandler,
+        message: &ConsumedMessage,
+    ) -> Result<(), String> {
+        let counters = &self.counters;
+        counters.in_flight.fetch_add(1, Ordering::Relaxed);
+        let result = handler.handle(message).await;
+        counters.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        match &result {
+            Ok(()) => counters.processed.fetch_add(1, Ordering::Relaxed),
+            Err(e) => {
+                self.record_error(e.clone());
+                counters.failed.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+        result
+    }
+
+    /// Settle a message the handler failed on, following the failure policy: retry it
+    /// through a delay queue or requeue it while it has attempts left, then dead-letter or
+    /// drop it. A message that cannot be moved is requeued rather than lost.
+    async fn settle_failure(
+        &self,
+        pool: &RabbitMqPool,
+        failure: FailedDelivery,
+        reason: &str,
+    ) -> lapin::Result<()> {
+        let policy = &self.spec.on_failure;
+        let counters = &self.counters;
+        let FailedDelivery {
+            message,
+            acker,
+            properties,
+        } = failure;
+        let queue = message.source.as_str();
+
+        let retries = retry_count(&message.headers);
+        let moved = if policy.strategy == RetryStrategy::Retry && retries + 1 < policy.max_attempts
+        {
+            let delay = policy.retry_delay(retries + 1);
+            let properties = retry_properties(&properties, retries + 1, reason);
+            self.move_message(pool, &message, properties, Some(delay))
+                .await
+                .map(|()| &counters.retried)
+        } else if policy.strategy == RetryStrategy::Requeue && !message.redelivered {
+            counters.requeued.fetch_add(1, Ordering::Relaxed);
+            return acker.reject(BasicRejectOptions { requeue: true }).await;
+        } else if policy.dead_letter {
+            let properties = dead_letter_properties(&properties, queue, reason);
+            self.move_message(pool, &message, properties, None)
+                .await
+                .map(|()| &counters.dead_lettered)
+        } else {
+            return acker.reject(BasicRejectOptions { requeue: false }).await;
+        };
+
+        match moved {
+            Ok(counter) => {
+                counter.fetch_add(1, Ordering::Relaxed);
+                acker.ack(BasicAckOptions::default()).await
+            }
+            Err(e) => {
+                self.record_error(e);
+                counters.requeued.fetch_add(1, Ordering::Relaxed);
+                acker.reject(BasicRejectOptions { requeue: true }).await
+            }
+        }
+    }
+
+    /// Dead-letter a message that failed its schema without handing it to the handler
+    async fn settle_invalid(
+        &self,
+        pool: &RabbitMqPool,
+        failure: FailedDelivery,
+        reason: &str,
+    ) -> lapin::Result<()> {
+        let FailedDelivery {
+            message,
+            acker,
+            properties,
+        } = failure;
+        let properties = dead_letter_properties(&properties, &message.source, reason);
+        match self.move_message(pool, &message, properties, None).await {
+            Ok(()) => {
+                self.counters.dead_lettered.fetch_add(1, Ordering::Relaxed);
+                acker.ack(BasicAckOptions::default()).await
+            }
+            Err(e) => {
+                self.record_error(e);
+                self.counters.requeued.fetch_add(1, Ordering::Relaxed);
+                acker.reject(BasicRejectOptions { requeue: true }).await
+            }
+        }
+    }
+
+    /// Publish a copy of the message to the delay queue for `delay`, or with no delay to
+    /// the dead-letter queue
+    async fn move_message(
+        &self,
+        pool: &RabbitMqPool,
+        message: &ConsumedMessage,
+        properties: BasicProperties,
+        delay: Option<Duration>,
+    ) -> Result<(), String> {
+        let queue = message.source.as_str();
+        let channel = pool.acquire(true).await.map_err(|e| e.to_string())?;
+        match delay {
+            Some(delay) => {
+                let delay_queue = declare_delay_queue(pool, &channel, queue, delay)
+                    .await
+                    .map_err(|e| format!("Failed to declare delay queue: {}", e))?;
+                republish(&channel, "", &delay_queue, &message.body, properties).await
+            }
+            None => {
+                republish(
+                    &channel,
+                    DEAD_LETTER_EXCHANGE,
+                    queue,
+                    &message.body,
+                    properties,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// What settling a failed message needs besides the worker
+struct FailedDelivery {
+    message: ConsumedMessage,
+    acker: Acker,
+    properties: BasicProperties,
+}
+
+/// Consumer workers by id, over the server's transport
+#[derive(Clone)]
+pub struct ConsumerWorkers {
+    transport: Transport,
+    responders: RpcResponders,
+    schemas: SchemaRegistry,
+    workers: Arc<Mutex<BTreeMap<String, Arc<ConsumerWorker>>>>,
+}
+
+impl ConsumerWorkers {
+    pub fn new(transport: Transport, schemas: SchemaRegistry) -> Self {
+        ConsumerWorkers {
+            transport,
+            responders: RpcResponders::new(),
+            schemas,
+            workers: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// Where `rpc` handlers find their responders
+    pub fn responders(&self) -> &RpcResponders {
+        &self.responders
+    }
+
+    pub fn load(path: &Path) -> io::Result<Vec<ConsumerSpec>> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Start the workers listed in the file named by `CONSUMERS_CONFIG`, defaulting to
+    /// `consumers.json`. A missing file means no workers.
+    pub async fn start_from_env(&self) {
+        let path = env::var("CONSUMERS_CONFIG").unwrap_or_else(|_| "consumers.json".to_string());
+        let specs = match Self::load(Path::new(&path)) {
+            Ok(specs) => specs,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+            Err(e) => {
+                eprintln!("Failed to load consumers from {}: {}", path, e);
+                return;
+            }
+        };
+
+        for spec in specs {
+            match self.start(spec).await {
+                Ok(worker) => {
+                    println!("📥 Consumer {} started on {}", worker.id, worker.spec.queue)
+                }
+                Err(e) => eprintln!("Failed to start consumer from {}: {}", path, e),
+            }
+        }
+    }
+
+    pub async fn start(&self, spec: ConsumerSpec) -> Result<Arc<ConsumerWorker>, String> {
+        spec.validate()?;
+        if !matches!(self.transport, Transport::Amqp(_)) && spec.on_failure.uses_failure_queues() {
+            return Err("on_failure retries and dead-lettering need AMQP".to_string());
+        }
+        if matches!(self.transport, Transport::Mqtt(_))
+            && matches!(spec.handler, HandlerSpec::Rpc { .. })
+        {
+            return Err("rpc handlers need AMQP; MQTT 3.1.1 messages have no reply_to".to_string());
+        }
+        let id = Uuid::new_v4().to_string();
+        let handler = build_handler(&id, &spec.handler, &self.transport, &self.responders).await?;
+
+        let (stop, stopped) = watch::channel(false);
+        let worker = Arc::new(ConsumerWorker {
+            id: id.clone(),
+            spec,
+            transport: self.transport.name(),
+            schemas: self.schemas.clone(),
+            started_at: Utc::now(),
+            status: Mutex::new(WorkerStatus::Starting),
+            counters: WorkerCounters::default(),
+            last_error: Mutex::new(None),
+            stop,
+            task: Mutex::new(None),
+        });
+
+        let task = tokio::spawn(run_worker(
+            self.transport.clone(),
+            worker.clone(),
+            handler,
+            stopped,
+        ));
+        *worker.task.lock().unwrap() = Some(task);
+        self.workers.lock().unwrap().insert(id, worker.clone());
+        Ok(worker)
+    }
+
+    /// Every worker, oldest first
+    pub fn list(&self) -> Vec<WorkerSnapshot> {
+        let mut workers: Vec<WorkerSnapshot> = self
+            .workers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|worker| worker.snapshot())
+            .collect();
+        workers.sort_by_key(|worker| worker.started_at);
+        workers
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<ConsumerWorker>> {
+        self.workers.lock().unwrap().get(id).cloned()
+    }
+
+    /// Stop a worker after its in-flight messages are handled, and forget it
+    pub async fn stop(&self, id: &str) -> Option<WorkerSnapshot> {
+        let worker = self.workers.lock().unwrap().remove(id)?;
+        worker.set_status(WorkerStatus::Stopping);
+        let _ = worker.stop.send(true);
+
+        let task = worker.task.lock().unwrap().take();
+        if let Some(task) = task {
+            if tokio::time::timeout(STOP_TIMEOUT, task).await.is_err() {
+                worker.record_error("Timed out waiting for in-flight messages".to_string());
+            }
+        }
+        worker.set_status(WorkerStatus::Stopped);
+        Some(worker.snapshot())
+    }
+}
+
+/// Consume until stopped, starting over with backoff whenever the channel or
+/// subscription is lost
+async fn run_worker(
+    transport: Transport,
+    worker: Arc<ConsumerWorker>,
+    handler: Arc<dyn MessageHandler>,
+    mut stopped: watch::Receiver<bool>,
+) {
+    let permits = Arc::new(Semaphore::new(worker.spec.concurrency));
+    let mut failures = 0;
+
+    while !*stopped.borrow() {
+        let consumer = Consumer {
+            worker: &worker,
+            handler: &handler,
+            permits: &permits,
+        };
+        let result = match &transport {
+            Transport::Amqp(pool) => consumer.consume_queue(pool, &mut stopped).await,
+            other => {
+                consumer
+                    .consume_subscription(other.broker(), &mut stopped)
+                    .await
+            }
+        };
+        let Err(e) = result else {
+            break;
+        };
+
+        if worker.status() == WorkerStatus::Running {
+            failures = 0;
+        }
+        failures += 1;
+        worker.record_error(e);
+        worker.set_status(WorkerStatus::Reconnecting);
+        worker.counters.reconnects.fetch_add(1, Ordering::Relaxed);
+
+        tokio::select! {
+            _ = tokio::time::sleep(backoff(failures, MIN_BACKOFF, MAX_BACKOFF)) => {}
+            _ = stopped.changed() => {}
+        }
+    }
+
+    // Let in-flight handlers finish before reporting the worker as stopped
+    let _ = permits.acquire_many(worker.spec.concurrency as u32).await;
+}
+
+/// One attempt at consuming, until it is stopped (`Ok`) or loses its source (`Err`)
+struct Consumer<'a> {
+    worker: &'a Arc<ConsumerWorker>,
+    handler: &'a Arc<dyn MessageHandler>,
+    permits: &'a Arc<Semaphore>,
+}
+
+impl Consumer<'_> {
+    async fn consume_queue(
+        &self,
+        pool: &RabbitMqPool,
+        stopped: &mut watch::Receiver<bool>,
+    ) -> Result<(), String> {
+        let worker = self.worker;
+        let queue = worker.spec.queue.trim();
+        let channel = pool
+            .acquire_consumer(false)
+            .await
+            .map_err(|e| e.to_string())?;
+        pool.declare_queue(&channel, queue)
+            .await
+            .map_err(|e| format!("Failed to declare queue '{}': {}", queue, e))?;
+        if worker.spec.on_failure.dead_letter || worker.spec.validate_schema {
+            declare_dead_letter_queue(pool, &channel, queue)
+                .await
+                .map_err(|e| format!("Failed to declare dead-letter queue: {}", e))?;
+        }
+        channel
+            .basic_qos(worker.spec.prefetch, BasicQosOptions::default())
+            .await
+            .map_err(|e| format!("Failed to set prefetch: {}", e))?;
+        let mut deliveries = channel
+            .basic_consume(
+                queue,
+                &worker.id,
+                BasicConsumeOptions::default(),
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| format!("Failed to start consumer: {}", e))?;
+        worker.set_status(WorkerStatus::Running);
+
+        loop {
+            tokio::select! {
+                _ = stopped.changed() => break,
+                delivery = deliveries.next() => match delivery {
+                    Some(Ok(delivery)) => self.spawn_delivery(pool, queue, delivery).await,
+                    Some(Err(e)) => return Err(format!("Error receiving message: {}", e)),
+                    None => return Err("Consumer was cancelled by the broker".to_string()),
+                },
+            }
+        }
+
+        // Acks need the channel, so wait for in-flight messages before closing it
+        let _ = channel
+            .basic_cancel(&worker.id, BasicCancelOptions::default())
+            .await;
+        let _ = self
+            .permits
+            .acquire_many(worker.spec.concurrency as u32)
+            .await;
+        channel.close().await;
+        Ok(())
+    }
+
+    async fn spawn_delivery(&self, pool: &RabbitMqPool, queue: &str, delivery: Delivery) {
+        let Ok(permit) = self.permits.clone().acquire_owned().await else {
+            return;
+        };
+        let worker = self.worker.clone();
+        let handler = self.handler.clone();
+        let pool = pool.clone();
+        worker.counters.received.fetch_add(1, Ordering::Relaxed);
+
+        let message = ConsumedMessage::from_delivery(queue, &delivery);
+        let Delivery {
+            acker, properties, ..
+        } = delivery;
+
+        tokio::spawn(async move {
+            let settled = if let Some(reason) = worker.schema_violation(&message) {
+                let failure = FailedDelivery {
+                    message,
+                    acker,
+                    properties,
+                };
+                worker.settle_invalid(&pool, failure, &reason).await
+            } else {
+                match worker.process(handler.as_ref(), &message).await {
+                    Ok(()) => acker.ack(BasicAckOptions::default()).await,
+                    Err(reason) => {
+                        let failure = FailedDelivery {
+                            message,
+                            acker,
+                            properties,
+                        };
+                        worker.settle_failure(&pool, failure, &reason).await
+                    }
+                }
+            };
+            if let Err(e) = settled {
+                worker.record_error(format!("Failed to acknowledge message: {}", e));
+            }
+            drop(permit);
+        });
+    }
+
+    /// Subscriptions acknowledge on receipt, so failed and invalid messages are only counted
+    async fn consume_subscription(
+        &self,
+        broker: &dyn MessageBroker,
+        stopped: &mut watch::Receiver<bool>,
+    ) -> Result<(), String> {
+        let worker = self.worker;
+        let mut subscription = broker
+            .subscribe(worker.spec.queue.trim(), worker.spec.qos)
+            .await
+            .map_err(|e| e.to_string())?;
+        worker.set_status(WorkerStatus::Running);
+
+        loop {
+            tokio::select! {
+                _ = stopped.changed() => break,
+                message = subscription.next() => {
+                    let message = match message {
+                        Some(Ok(message)) => message,
+                        Some(Err(e)) => return Err(e),
+                        None => return Err("Subscription ended".to_string()),
+                    };
+                    let Ok(permit) = self.permits.clone().acquire_owned().await else {
+                        return Ok(());
+                    };
+                    worker.counters.received.fetch_add(1, Ordering::Relaxed);
+                    if worker.schema_violation(&message).is_some() {
+                        continue;
+                    }
+
+                    let worker = worker.clone();
+                    let handler = self.handler.clone();
+                    tokio::spawn(async move {
+                        let _ = worker.process(handler.as_ref(), &message).await;
+                        drop(permit);
+                    });
+                }
+            }
+        }
+
+        subscription.close().await;
+        Ok(())
+    }
+}
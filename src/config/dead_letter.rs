@@ -0,0 +1,211 @@
+use crate::config::rabbitmq::RabbitMqPool;
+use crate::model::dead_letter::{
+    dead_letter_queue, delay_queue, DEAD_LETTER_EXCHANGE, FAILED_AT_HEADER, FAILURE_HEADERS,
+    FAILURE_REASON_HEADER, ORIGINAL_QUEUE_HEADER, RETRY_COUNT_HEADER,
+};
+use chrono::Utc;
+use lapin::{
+    options::{BasicPublishOptions, ExchangeDeclareOptions, QueueBindOptions, QueueDeclareOptions},
+    publisher_confirm::Confirmation,
+    types::{AMQPValue, FieldTable, LongString, ShortString},
+    BasicProperties, Channel, ExchangeKind,
+};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Declare the dead-letter exchange and `queue`'s dead-letter queue, bound to it with the
+/// queue's name
+pub async fn declare_dead_letter_queue(
+    pool: &RabbitMqPool,
+    channel: &Channel,
+    queue: &str,
+) -> lapin::Result<String> {
+    channel
+        .exchange_declare(
+            DEAD_LETTER_EXCHANGE,
+            ExchangeKind::Direct,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+    let dlq = dead_letter_queue(queue);
+    pool.declare_queue_once(channel, &dlq, durable(), FieldTable::default())
+        .await?;
+    channel
+        .queue_bind(
+            &dlq,
+            DEAD_LETTER_EXCHANGE,
+            queue,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+    Ok(dlq)
+}
+
+/// Declare the queue where retries of `queue` wait out `delay`. Nothing consumes it;
+/// messages expire through the default exchange back into `queue`.
+pub async fn declare_delay_queue(
+    pool: &RabbitMqPool,
+    channel: &Channel,
+    queue: &str,
+    delay: Duration,
+) -> lapin::Result<String> {
+    let name = delay_queue(queue, delay);
+    let mut arguments = FieldTable::default();
+    arguments.insert(
+        "x-message-ttl".into(),
+        AMQPValue::LongLongInt(delay.as_millis() as i64),
+    );
+    arguments.insert(
+        "x-dead-letter-exchange".into(),
+        AMQPValue::LongString("".into()),
+    );
+    arguments.insert(
+        "x-dead-letter-routing-key".into(),
+        AMQPValue::LongString(queue.into()),
+    );
+    pool.declare_queue_once(channel, &name, durable(), arguments)
+        .await?;
+    Ok(name)
+}
+
+fn durable() -> QueueDeclareOptions {
+    QueueDeclareOptions {
+        durable: true,
+        ..QueueDeclareOptions::default()
+    }
+}
+
+/// The message's properties with its headers updated for another attempt
+pub fn retry_properties(
+    properties: &BasicProperties,
+    retries: u32,
+    reason: &str,
+) -> BasicProperties {
+    with_headers(properties, |headers| {
+        headers.insert(
+            RETRY_COUNT_HEADER.into(),
+            AMQPValue::LongLongInt(retries as i64),
+        );
+        headers.insert(FAILURE_REASON_HEADER.into(), long_string(reason));
+    })
+}
+
+/// The message's properties with why and where it failed, for the dead-letter queue
+pub fn dead_letter_properties(
+    properties: &BasicProperties,
+    queue: &str,
+    reason: &str,
+) -> BasicProperties {
+    with_headers(properties, |headers| {
+        headers.insert(FAILURE_REASON_HEADER.into(), long_string(reason));
+        headers.insert(
+            FAILED_AT_HEADER.into(),
+            long_string(&Utc::now().to_rfc3339()),
+        );
+        headers.insert(ORIGINAL_QUEUE_HEADER.into(), long_string(queue));
+    })
+}
+
+/// The message's properties without the failure headers, for a replay
+pub fn replay_properties(properties: &BasicProperties) -> BasicProperties {
+    with_headers(properties, |headers| {
+        for name in FAILURE_HEADERS {
+            headers.remove(name);
+        }
+    })
+}
+
+fn with_headers(
+    properties: &BasicProperties,
+    update: impl FnOnce(&mut BTreeMap<ShortString, AMQPValue>),
+) -> BasicProperties {
+    let mut headers = properties
+        .headers()
+        .as_ref()
+        .map(|headers| headers.inner().clone())
+        .unwrap_or_default();
+    update(&mut headers);
+    properties.clone().with_headers(FieldTable::from(headers))
+}
+
+fn long_string(text: &str) -> AMQPValue {
+    AMQPValue::LongString(LongString::from(text.to_string()))
+}
+
+/// Publish on a channel in confirm mode and wait until the broker has taken the message,
+/// so the original can be acked without losing it
+pub async fn republish(
+    channel: &Channel,
+    exchange: &str,
+    routing_key: &str,
+    payload: &[u8],
+    properties: BasicProperties,
+) -> Result<(), String> {
+    let confirmation = channel
+        .basic_publish(
+            exchange,
+            routing_key,
+            BasicPublishOptions {
+                mandatory: true,
+                ..BasicPublishOptions::default()
+            },
+            payload,
+            properties,
+        )
+        .await
+        .map_err(|e| format!("Failed to publish message: {}", e))?
+        .await
+        .map_err(|e| format!("Failed to confirm message delivery: {}", e))?;
+
+    match confirmation {
+        Confirmation::Ack(None) => Ok(()),
+        Confirmation::Ack(Some(_)) => Err(format!(
+            "No queue is bound for '{}' on exchange '{}'",
+            routing_key, exchange
+        )),
+        Confirmation::Nack(_) => Err(format!(
+            "The broker nacked the message for '{}'",
+            routing_key
+        )),
+        Confirmation::NotRequested => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mqtt::headers_json;
+    use serde_json::json;
+
+    #[test]
+    fn test_failure_headers_are_added_and_stripped() {
+        let mut headers = FieldTable::default();
+        headers.insert("source".into(), long_string("k6"));
+        let original = BasicProperties::default()
+            .with_message_id("m-1".into())
+            .with_headers(headers);
+
+        let retried = retry_properties(&original, 2, "timeout");
+        let retried_headers = headers_json(retried.headers().as_ref().unwrap());
+        assert_eq!(retried_headers[RETRY_COUNT_HEADER], json!(2));
+        assert_eq!(retried_headers[FAILURE_REASON_HEADER], json!("timeout"));
+        assert_eq!(retried_headers["source"], json!("k6"));
+        assert_eq!(retried.message_id(), original.message_id());
+
+        let dead = dead_letter_properties(&retried, "orders", "timeout");
+        let dead_headers = headers_json(dead.headers().as_ref().unwrap());
+        assert_eq!(dead_headers[ORIGINAL_QUEUE_HEADER], json!("orders"));
+        assert!(dead_headers.contains_key(FAILED_AT_HEADER));
+
+        let replayed = replay_properties(&dead);
+        assert_eq!(
+            headers_json(replayed.headers().as_ref().unwrap()),
+            BTreeMap::from([("source".to_string(), json!("k6"))])
+        );
+    }
+}
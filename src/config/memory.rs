@@ -0,0 +1,335 @@
+use crate::config::broker::{
+    BrokerFuture, BrokerPublisher, BrokerResult, BrokerSubscription, MessageBroker,
+    OutgoingMessage, PendingConfirm, PublishTarget, ReplyQueue,
+};
+use crate::model::consumer::ConsumedMessage;
+use crate::model::mqtt::ConfirmStatus;
+use bytes::Bytes;
+use lapin::BasicProperties;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// A broker inside this process, selected with `MESSAGE_TRANSPORT=memory`. Every routing
+/// key of the default exchange is a queue that subscribers take turns on, as with AMQP.
+/// There are no named exchanges, and nothing outlives the process.
+#[derive(Clone, Default)]
+pub struct MemoryBroker {
+    inner: Arc<MemoryInner>,
+}
+
+#[derive(Default)]
+struct MemoryInner {
+    queues: Mutex<HashMap<String, VecDeque<StoredMessage>>>,
+    /// Woken on every publish, so waiting subscribers check their queue again
+    arrived: Notify,
+    published: AtomicU64,
+    returned: AtomicU64,
+    delivered: AtomicU64,
+}
+
+struct StoredMessage {
+    routing_key: String,
+    payload: Bytes,
+    properties: BasicProperties,
+}
+
+/// Point-in-time view of the in-memory broker, served by `GET /mqtt/pool`
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryStats {
+    /// Messages waiting in each queue
+    pub queues: BTreeMap<String, usize>,
+    pub published: u64,
+    /// Published to a named exchange, which has no bindings here, or to a missing queue
+    pub returned: u64,
+    pub delivered: u64,
+}
+
+impl MemoryBroker {
+    pub fn new() -> Self {
+        MemoryBroker::default()
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        let inner = &self.inner;
+        let queues = inner
+            .queues
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, messages)| (name.clone(), messages.len()))
+            .collect();
+        MemoryStats {
+            queues,
+            published: inner.published.load(Ordering::Relaxed),
+            returned: inner.returned.load(Ordering::Relaxed),
+            delivered: inner.delivered.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Queue a message, or report it returned when no queue takes it: it was sent to a
+    /// named exchange, or to an undeclared queue such as a reply queue that is gone
+    fn publish(&self, target: &PublishTarget, message: OutgoingMessage) -> ConfirmStatus {
+        let inner = &self.inner;
+        inner.published.fetch_add(1, Ordering::Relaxed);
+        let queued = target.exchange.is_empty() && {
+            let mut queues = inner.queues.lock().unwrap();
+            if target.declare {
+                queues.entry(target.routing_key.clone()).or_default();
+            }
+            match queues.get_mut(&target.routing_key) {
+                Some(queue) => {
+                    queue.push_back(StoredMessage {
+                        routing_key: target.routing_key.clone(),
+                        payload: Bytes::from(message.payload),
+                        properties: message.properties,
+                    });
+                    true
+                }
+                None => false,
+            }
+        };
+
+        if queued {
+            inner.arrived.notify_waiters();
+        } else {
+            inner.returned.fetch_add(1, Ordering::Relaxed);
+        }
+        match (target.requires_confirm(), queued) {
+            (false, _) => ConfirmStatus::NotRequested,
+            (true, true) => ConfirmStatus::Ack,
+            (true, false) => ConfirmStatus::Returned,
+        }
+    }
+
+    fn pop(&self, queue: &str) -> Option<ConsumedMessage> {
+        let stored = self
+            .inner
+            .queues
+            .lock()
+            .unwrap()
+            .get_mut(queue)?
+            .pop_front()?;
+        self.inner.delivered.fetch_add(1, Ordering::Relaxed);
+        Some(ConsumedMessage::from_properties(
+            queue,
+            "",
+            &stored.routing_key,
+            &stored.properties,
+            stored.payload,
+            false,
+        ))
+    }
+}
+
+impl MessageBroker for MemoryBroker {
+    fn open_publisher(
+        &self,
+        target: PublishTarget,
+    ) -> BrokerFuture<'_, BrokerResult<Box<dyn BrokerPublisher>>> {
+        let publisher: Box<dyn BrokerPublisher> = Box::new(MemoryPublisher {
+            broker: self.clone(),
+            target,
+        });
+        Box::pin(async { Ok(publisher) })
+    }
+
+    fn subscribe<'a>(
+        &'a self,
+        source: &'a str,
+        _qos: u8,
+    ) -> BrokerFuture<'a, BrokerResult<Box<dyn BrokerSubscription>>> {
+        self.inner
+            .queues
+            .lock()
+            .unwrap()
+            .entry(source.to_string())
+            .or_default();
+        let subscription: Box<dyn BrokerSubscription> = Box::new(MemorySubscription {
+            broker: self.clone(),
+            queue: source.to_string(),
+            exclusive: false,
+        });
+        Box::pin(async { Ok(subscription) })
+    }
+
+    fn reply_queue(&self, target: PublishTarget) -> BrokerFuture<'_, BrokerResult<ReplyQueue>> {
+        let queue = format!("amq.gen-{}", Uuid::new_v4());
+        self.inner
+            .queues
+            .lock()
+            .unwrap()
+            .insert(queue.clone(), VecDeque::new());
+        let reply_queue = ReplyQueue {
+            name: queue.clone(),
+            publisher: Box::new(MemoryPublisher {
+                broker: self.clone(),
+                target,
+            }),
+            replies: Box::new(MemorySubscription {
+                broker: self.clone(),
+                queue,
+                exclusive: true,
+            }),
+        };
+        Box::pin(async { Ok(reply_queue) })
+    }
+}
+
+struct MemoryPublisher {
+    broker: MemoryBroker,
+    target: PublishTarget,
+}
+
+impl BrokerPublisher for MemoryPublisher {
+    fn send(&self, message: OutgoingMessage) -> BrokerFuture<'_, BrokerResult<PendingConfirm>> {
+        let status = self.broker.publish(&self.target, message);
+        let pending: PendingConfirm = Box::pin(async move { Ok(status) });
+        Box::pin(async { Ok(pending) })
+    }
+}
+
+/// Messages are taken off the queue as they are handed out, so closing leaves the rest
+/// for other subscribers
+struct MemorySubscription {
+    broker: MemoryBroker,
+    queue: String,
+    /// The queue is this subscription's own, and goes away with it
+    exclusive: bool,
+}
+
+impl Drop for MemorySubscription {
+    fn drop(&mut self) {
+        if self.exclusive {
+            self.broker.inner.queues.lock().unwrap().remove(&self.queue);
+        }
+    }
+}
+
+impl BrokerSubscription for MemorySubscription {
+    fn next(&mut self) -> BrokerFuture<'_, Option<Result<ConsumedMessage, String>>> {
+        Box::pin(async move {
+            loop {
+                // Registered before looking, so a publish in between is not missed
+                let arrived = self.broker.inner.arrived.notified();
+                tokio::pin!(arrived);
+                arrived.as_mut().enable();
+                if let Some(message) = self.broker.pop(&self.queue) {
+                    return Some(Ok(message));
+                }
+                arrived.await;
+            }
+        })
+    }
+
+    fn close(self: Box<Self>) -> BrokerFuture<'static, ()> {
+        Box::pin(async {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn target(exchange: &str, routing_key: &str, qos: u8) -> PublishTarget {
+        PublishTarget {
+            exchange: exchange.to_string(),
+            routing_key: routing_key.to_string(),
+            qos,
+            retain: false,
+            declare: true,
+        }
+    }
+
+    fn message(payload: &str) -> OutgoingMessage {
+        OutgoingMessage {
+            payload: payload.as_bytes().to_vec(),
+            properties: BasicProperties::default().with_message_id("m-1".into()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_confirmed_and_consumed_in_order() {
+        let broker = MemoryBroker::new();
+        let publisher = broker
+            .open_publisher(target("", "orders", 1))
+            .await
+            .unwrap();
+        for payload in ["first", "second"] {
+            let confirm = publisher.send(message(payload)).await.unwrap();
+            assert_eq!(confirm.await.unwrap(), ConfirmStatus::Ack);
+        }
+        assert_eq!(broker.stats().queues["orders"], 2);
+
+        let mut subscription = broker.subscribe("orders", 1).await.unwrap();
+        let first = subscription.next().await.unwrap().unwrap();
+        assert_eq!(first.body, Bytes::from("first"));
+        assert_eq!(first.id, "m-1");
+        assert_eq!(first.source, "orders");
+        let second = subscription.next().await.unwrap().unwrap();
+        assert_eq!(second.body, Bytes::from("second"));
+        assert_eq!(broker.stats().delivered, 2);
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_wait_for_messages() {
+        let broker = MemoryBroker::new();
+        let mut subscription = broker.subscribe("orders", 1).await.unwrap();
+        let waiting = tokio::spawn(async move { subscription.next().await });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let publisher = broker
+            .open_publisher(target("", "orders", 0))
+            .await
+            .unwrap();
+        let confirm = publisher.send(message("late")).await.unwrap();
+        assert_eq!(confirm.await.unwrap(), ConfirmStatus::NotRequested);
+
+        let received = waiting.await.unwrap().unwrap().unwrap();
+        assert_eq!(received.body, Bytes::from("late"));
+    }
+
+    #[tokio::test]
+    async fn test_named_exchanges_return_messages() {
+        let broker = MemoryBroker::new();
+        let publisher = broker
+            .open_publisher(target("events", "orders.created", 1))
+            .await
+            .unwrap();
+        let confirm = publisher.send(message("{}")).await.unwrap();
+        assert_eq!(confirm.await.unwrap(), ConfirmStatus::Returned);
+        let stats = broker.stats();
+        assert_eq!(stats.returned, 1);
+        assert!(stats.queues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reply_queues_go_away_with_their_subscription() {
+        let broker = MemoryBroker::new();
+        let ReplyQueue {
+            name: queue,
+            mut replies,
+            ..
+        } = broker.reply_queue(target("", "rpc", 1)).await.unwrap();
+        let reply_target = PublishTarget {
+            declare: false,
+            ..target("", &queue, 1)
+        };
+        let publisher = broker.open_publisher(reply_target).await.unwrap();
+        let confirm = publisher.send(message("pong")).await.unwrap();
+        assert_eq!(confirm.await.unwrap(), ConfirmStatus::Ack);
+        assert_eq!(
+            replies.next().await.unwrap().unwrap().body,
+            Bytes::from("pong")
+        );
+
+        replies.close().await;
+        assert!(!broker.stats().queues.contains_key(&queue));
+        let confirm = publisher.send(message("late")).await.unwrap();
+        assert_eq!(confirm.await.unwrap(), ConfirmStatus::Returned);
+    }
+}
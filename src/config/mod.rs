@@ -1 +1,14 @@
+pub mod broker;
+pub mod consumer;
 pub mod db;
+pub mod dead_letter;
+pub mod memory;
+pub mod mqtt;
+pub mod rabbitmq;
+pub mod report;
+pub mod retention;
+pub mod rpc;
+pub mod scheduler;
+pub mod schema;
+pub mod slo;
+pub mod transport;
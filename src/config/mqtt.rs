@@ -0,0 +1,783 @@
+use crate::config::broker::{
+    BrokerError, BrokerFuture, BrokerPublisher, BrokerResult, BrokerSubscription, MessageBroker,
+    OutgoingMessage, PendingConfirm, PublishTarget, ReplyQueue,
+};
+use crate::config::rabbitmq::backoff;
+use crate::model::consumer::ConsumedMessage;
+use crate::model::mqtt::ConfirmStatus;
+use bytes::Bytes;
+use rumqttc::{
+    AsyncClient, ClientError, Event, EventLoop, LastWill, MqttOptions, Outgoing, Packet, Publish,
+    QoS, Request,
+};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+const DEFAULT_PORT: u16 = 1883;
+const REQUEST_CAPACITY: usize = 1024;
+/// Messages buffered for each subscription. Once one is full, the event loop waits for it
+/// rather than dropping messages the broker already considers delivered.
+const SUBSCRIPTION_CAPACITY: usize = 1024;
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How to reach the MQTT broker, read from the environment
+#[derive(Clone, Debug)]
+pub struct MqttSettings {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub credentials: Option<(String, String)>,
+    pub keep_alive: Duration,
+    /// Published by the broker if this server disappears without disconnecting
+    pub last_will: Option<LastWill>,
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl MqttSettings {
+    /// Reads `MQTT_HOST`, `MQTT_PORT`, `MQTT_CLIENT_ID`, `MQTT_USERNAME`, `MQTT_PASSWORD`,
+    /// `MQTT_KEEP_ALIVE_SECS` and the last will from `MQTT_WILL_TOPIC`, `MQTT_WILL_MESSAGE`,
+    /// `MQTT_WILL_QOS` and `MQTT_WILL_RETAIN`
+    pub fn from_env() -> Self {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+
+        let last_will = var("MQTT_WILL_TOPIC").map(|topic| {
+            let qos = var("MQTT_WILL_QOS")
+                .and_then(|qos| qos.parse().ok())
+                .and_then(|qos| rumqttc::qos(qos).ok())
+                .unwrap_or(QoS::AtLeastOnce);
+            let retain = var("MQTT_WILL_RETAIN").is_some_and(|retain| retain == "true");
+            let message = var("MQTT_WILL_MESSAGE").unwrap_or_else(|| "offline".to_string());
+            LastWill::new(topic, message, qos, retain)
+        });
+
+        MqttSettings {
+            host: var("MQTT_HOST").unwrap_or_else(|| "127.0.0.1".to_string()),
+            port: var("MQTT_PORT")
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(DEFAULT_PORT),
+            client_id: var("MQTT_CLIENT_ID").unwrap_or_else(|| {
+                format!("rust-api-{}", &Uuid::new_v4().simple().to_string()[..8])
+            }),
+            credentials: var("MQTT_USERNAME")
+                .map(|username| (username, var("MQTT_PASSWORD").unwrap_or_default())),
+            keep_alive: Duration::from_secs(
+                var("MQTT_KEEP_ALIVE_SECS")
+                    .and_then(|secs| secs.parse().ok())
+                    .unwrap_or(30),
+            ),
+            last_will,
+            min_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+
+    fn options(&self) -> MqttOptions {
+        let mut options = MqttOptions::new(&self.client_id, &self.host, self.port);
+        options.set_keep_alive(self.keep_alive);
+        // A fresh session on every connect, so unacked publishes fail instead of being
+        // resent behind our back
+        options.set_clean_session(true);
+        if let Some((username, password)) = &self.credentials {
+            options.set_credentials(username, password);
+        }
+        if let Some(will) = &self.last_will {
+            options.set_last_will(will.clone());
+        }
+        options
+    }
+}
+
+/// A message received on a subscribed topic
+#[derive(Debug, Clone)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: Bytes,
+    /// Sent by the broker from its retained store rather than published just now
+    pub retain: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MqttStats {
+    pub connected: bool,
+    pub broker: String,
+    pub client_id: String,
+    pub connects: u64,
+    pub reconnects: u64,
+    pub connection_errors: u64,
+    pub published: u64,
+    pub confirmed: u64,
+    pub failed: u64,
+    pub pending: usize,
+    pub received: u64,
+    pub subscriptions: Vec<String>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct Counters {
+    connects: AtomicU64,
+    connection_errors: AtomicU64,
+    published: AtomicU64,
+    confirmed: AtomicU64,
+    failed: AtomicU64,
+    received: AtomicU64,
+}
+
+type Waiter = oneshot::Sender<Result<ConfirmStatus, String>>;
+
+/// Publishes whose confirm someone waits for. The event loop hands out packet ids in the
+/// order requests were sent, so `queued` lines up with the `Outgoing::Publish` events.
+#[derive(Default)]
+struct ConfirmWaiters {
+    queued: VecDeque<Option<Waiter>>,
+    /// QoS 1/2 publishes sent with this packet id, waiting for PUBACK/PUBCOMP
+    inflight: HashMap<u16, Option<Waiter>>,
+}
+
+/// The subscribers of one topic filter, which is subscribed with the highest QoS any of them
+/// asked for
+struct FilterSubscribers {
+    qos: QoS,
+    senders: HashMap<u64, mpsc::Sender<MqttMessage>>,
+}
+
+struct MqttInner {
+    settings: MqttSettings,
+    /// Held while queueing a waiter and sending its request, to keep both in the same order
+    send_lock: tokio::sync::Mutex<()>,
+    pending: Mutex<ConfirmWaiters>,
+    subscriptions: Mutex<HashMap<String, FilterSubscribers>>,
+    next_subscriber: AtomicU64,
+    connected: AtomicBool,
+    last_error: Mutex<Option<String>>,
+    counters: Counters,
+}
+
+/// MQTT 3.1.1 client shared by every handler. Its event loop runs in the background and
+/// reconnects with exponential backoff; subscriptions are restored after a reconnect.
+#[derive(Clone)]
+pub struct MqttClient {
+    client: AsyncClient,
+    inner: Arc<MqttInner>,
+}
+
+impl MqttClient {
+    /// Create the client and start its event loop
+    pub fn start(settings: MqttSettings) -> Self {
+        let (client, eventloop) = AsyncClient::new(settings.options(), REQUEST_CAPACITY);
+        let mqtt = MqttClient {
+            client,
+            inner: Arc::new(MqttInner {
+                settings,
+                send_lock: tokio::sync::Mutex::new(()),
+                pending: Mutex::new(ConfirmWaiters::default()),
+                subscriptions: Mutex::new(HashMap::new()),
+                next_subscriber: AtomicU64::new(0),
+                connected: AtomicBool::new(false),
+                last_error: Mutex::new(None),
+                counters: Counters::default(),
+            }),
+        };
+
+        tokio::spawn(mqtt.clone().run(eventloop));
+        mqtt
+    }
+
+    pub fn from_env() -> Self {
+        Self::start(MqttSettings::from_env())
+    }
+}
+
+// Helper functions for publishing and subscribing
+impl MqttClient {
+    /// Send a publish and return a handle for its confirm, so several can be in flight
+    pub async fn start_publish(
+        &self,
+        topic: &str,
+        qos: QoS,
+        retain: bool,
+        payload: Vec<u8>,
+    ) -> Result<PendingPublish, String> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_publish(topic, qos, retain, payload, Some(sender))
+            .await?;
+        Ok(PendingPublish(receiver))
+    }
+
+    /// Hand a publish to the event loop without waiting for the broker
+    pub async fn publish_nowait(
+        &self,
+        topic: &str,
+        qos: QoS,
+        payload: Vec<u8>,
+    ) -> Result<(), String> {
+        self.send_publish(topic, qos, false, payload, None).await
+    }
+
+    async fn send_publish(
+        &self,
+        topic: &str,
+        qos: QoS,
+        retain: bool,
+        payload: Vec<u8>,
+        waiter: Option<Waiter>,
+    ) -> Result<(), String> {
+        if !self.inner.connected.load(Ordering::Acquire) {
+            return Err(format!(
+                "MQTT broker {} is not connected",
+                self.broker_address()
+            ));
+        }
+
+        let _order = self.inner.send_lock.lock().await;
+        self.inner.pending.lock().unwrap().queued.push_back(waiter);
+        if let Err(e) = self.client.publish(topic, qos, retain, payload).await {
+            // Nothing was sent, so the waiter we just queued is still the last one
+            self.inner.pending.lock().unwrap().queued.pop_back();
+            self.inner.counters.failed.fetch_add(1, Ordering::Relaxed);
+            return Err(client_error(e));
+        }
+        self.inner
+            .counters
+            .published
+            .fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Subscribe to a topic filter; the subscription ends when the returned handle drops.
+    /// The broker is asked again when `qos` is higher than the filter's current one.
+    pub async fn subscribe(&self, filter: &str, qos: QoS) -> Result<MqttSubscription, String> {
+        if !rumqttc::valid_filter(filter) {
+            return Err(format!("Invalid topic filter '{}'", filter));
+        }
+
+        // Subscribe before registering, so a failure leaves no trace
+        let current = self
+            .inner
+            .subscriptions
+            .lock()
+            .unwrap()
+            .get(filter)
+            .map(|subscribers| subscribers.qos);
+        if current.is_none_or(|current| current < qos) {
+            self.client
+                .subscribe(filter, qos)
+                .await
+                .map_err(client_error)?;
+        }
+
+        let id = self.inner.next_subscriber.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel(SUBSCRIPTION_CAPACITY);
+        let mut subscriptions = self.inner.subscriptions.lock().unwrap();
+        let subscribers =
+            subscriptions
+                .entry(filter.to_string())
+                .or_insert_with(|| FilterSubscribers {
+                    qos,
+                    senders: HashMap::new(),
+                });
+        if qos > subscribers.qos {
+            subscribers.qos = qos;
+        }
+        subscribers.senders.insert(id, sender);
+
+        Ok(MqttSubscription {
+            filter: filter.to_string(),
+            id,
+            receiver,
+            client: self.clone(),
+        })
+    }
+
+    pub fn stats(&self) -> MqttStats {
+        let inner = &self.inner;
+        let counters = &inner.counters;
+        let pending = {
+            let pending = inner.pending.lock().unwrap();
+            pending.queued.len() + pending.inflight.len()
+        };
+        let mut subscriptions: Vec<String> = inner
+            .subscriptions
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        subscriptions.sort();
+        let connects = counters.connects.load(Ordering::Relaxed);
+
+        MqttStats {
+            connected: inner.connected.load(Ordering::Acquire),
+            broker: self.broker_address(),
+            client_id: inner.settings.client_id.clone(),
+            connects,
+            reconnects: connects.saturating_sub(1),
+            connection_errors: counters.connection_errors.load(Ordering::Relaxed),
+            published: counters.published.load(Ordering::Relaxed),
+            confirmed: counters.confirmed.load(Ordering::Relaxed),
+            failed: counters.failed.load(Ordering::Relaxed),
+            pending,
+            received: counters.received.load(Ordering::Relaxed),
+            subscriptions,
+            last_error: inner.last_error.lock().unwrap().clone(),
+        }
+    }
+
+    fn broker_address(&self) -> String {
+        format!("{}:{}", self.inner.settings.host, self.inner.settings.port)
+    }
+}
+
+// Helper functions for the event loop
+impl MqttClient {
+    async fn run(self, mut eventloop: EventLoop) {
+        let mut failures = 0;
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    failures = 0;
+                    self.deliver(publish).await;
+                }
+                Ok(event) => {
+                    failures = 0;
+                    self.handle_event(event);
+                }
+                Err(e) => {
+                    failures += 1;
+                    // A clean session drops whatever was left unsent, so take it here
+                    self.handle_disconnect(e.to_string(), eventloop.pending.drain(..));
+                    let delay = backoff(
+                        failures,
+                        self.inner.settings.min_backoff,
+                        self.inner.settings.max_backoff,
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    fn handle_event(&self, event: Event) {
+        let inner = &self.inner;
+        match event {
+            Event::Incoming(Packet::ConnAck(_)) => {
+                inner.connected.store(true, Ordering::Release);
+                if inner.counters.connects.fetch_add(1, Ordering::Relaxed) > 0 {
+                    println!("📡 Reconnected to MQTT broker {}", self.broker_address());
+                }
+                // A clean session forgets subscriptions, so restore them
+                for (filter, subscribers) in inner.subscriptions.lock().unwrap().iter() {
+                    if let Err(e) = self.client.try_subscribe(filter, subscribers.qos) {
+                        eprintln!("Failed to resubscribe to {}: {}", filter, e);
+                    }
+                }
+            }
+            Event::Incoming(Packet::PubAck(ack)) => self.confirm(ack.pkid),
+            Event::Incoming(Packet::PubComp(comp)) => self.confirm(comp.pkid),
+            Event::Outgoing(Outgoing::Publish(pkid)) => {
+                let mut pending = inner.pending.lock().unwrap();
+                // A resent publish keeps its packet id and is already tracked
+                if pkid != 0 && pending.inflight.contains_key(&pkid) {
+                    return;
+                }
+                let Some(waiter) = pending.queued.pop_front() else {
+                    return;
+                };
+                if pkid == 0 {
+                    if let Some(waiter) = waiter {
+                        let _ = waiter.send(Ok(ConfirmStatus::NotRequested));
+                    }
+                } else {
+                    pending.inflight.insert(pkid, waiter);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Hand a received message to every subscription whose filter matches it. A full
+    /// subscription holds up the event loop until it has room, so a slow consumer slows
+    /// the connection down instead of losing messages.
+    async fn deliver(&self, publish: Publish) {
+        self.inner.counters.received.fetch_add(1, Ordering::Relaxed);
+        let message = MqttMessage {
+            topic: publish.topic,
+            payload: publish.payload,
+            retain: publish.retain,
+        };
+
+        let senders: Vec<mpsc::Sender<MqttMessage>> = self
+            .inner
+            .subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(filter, _)| rumqttc::matches(&message.topic, filter))
+            .flat_map(|(_, subscribers)| subscribers.senders.values().cloned())
+            .collect();
+        for sender in senders {
+            // The subscription may have been dropped meanwhile
+            let _ = sender.send(message.clone()).await;
+        }
+    }
+
+    fn confirm(&self, pkid: u16) {
+        let waiter = self.inner.pending.lock().unwrap().inflight.remove(&pkid);
+        if let Some(waiter) = waiter {
+            self.inner
+                .counters
+                .confirmed
+                .fetch_add(1, Ordering::Relaxed);
+            if let Some(waiter) = waiter {
+                let _ = waiter.send(Ok(ConfirmStatus::Ack));
+            }
+        }
+    }
+
+    /// In-flight publishes are lost with a clean session, so fail them. So are the `unsent`
+    /// requests the event loop had taken off the channel: their publishes still have no
+    /// packet id and are the oldest queued ones, so their waiters are failed as well, which
+    /// keeps `queued` lined up with the publishes still waiting in the channel.
+    fn handle_disconnect(&self, error: String, unsent: impl IntoIterator<Item = Request>) {
+        let inner = &self.inner;
+        if inner.connected.swap(false, Ordering::AcqRel) {
+            eprintln!("MQTT connection lost: {}", error);
+        }
+        inner
+            .counters
+            .connection_errors
+            .fetch_add(1, Ordering::Relaxed);
+
+        let lost = unsent
+            .into_iter()
+            .filter(|request| matches!(request, Request::Publish(publish) if publish.pkid == 0))
+            .count();
+        let failed: Vec<Option<Waiter>> = {
+            let mut pending = inner.pending.lock().unwrap();
+            let lost = lost.min(pending.queued.len());
+            let queued: Vec<_> = pending.queued.drain(..lost).collect();
+            pending
+                .inflight
+                .drain()
+                .map(|(_, waiter)| waiter)
+                .chain(queued)
+                .collect()
+        };
+        inner
+            .counters
+            .failed
+            .fetch_add(failed.len() as u64, Ordering::Relaxed);
+        for waiter in failed.into_iter().flatten() {
+            let _ = waiter.send(Err(format!("MQTT connection lost: {}", error)));
+        }
+        *inner.last_error.lock().unwrap() = Some(error);
+    }
+}
+
+fn client_error(e: ClientError) -> String {
+    format!("Failed to send MQTT request: {}", e)
+}
+
+/// A sent publish whose confirm has not been awaited yet
+pub struct PendingPublish(oneshot::Receiver<Result<ConfirmStatus, String>>);
+
+impl PendingPublish {
+    pub async fn confirmed(self) -> Result<ConfirmStatus, String> {
+        match tokio::time::timeout(CONFIRM_TIMEOUT, self.0).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err("MQTT client stopped".to_string()),
+            Err(_) => Err("Timed out waiting for the broker to acknowledge".to_string()),
+        }
+    }
+}
+
+/// Messages on one topic filter. Unsubscribes when the last handle for the filter drops.
+pub struct MqttSubscription {
+    filter: String,
+    id: u64,
+    receiver: mpsc::Receiver<MqttMessage>,
+    client: MqttClient,
+}
+
+impl MqttSubscription {
+    /// The next message matching the filter, or `None` once the client is gone
+    pub async fn recv(&mut self) -> Option<MqttMessage> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for MqttSubscription {
+    fn drop(&mut self) {
+        let mut subscriptions = self.client.inner.subscriptions.lock().unwrap();
+        let Some(subscribers) = subscriptions.get_mut(&self.filter) else {
+            return;
+        };
+        subscribers.senders.remove(&self.id);
+        if subscribers.senders.is_empty() {
+            subscriptions.remove(&self.filter);
+            if let Err(e) = self.client.client.try_unsubscribe(&self.filter) {
+                eprintln!("Failed to unsubscribe from {}: {}", self.filter, e);
+            }
+        }
+    }
+}
+
+// Publishing and consuming for the `/mqtt` handlers. Every MQTT failure is worth a retry,
+// since the client reconnects in the background.
+impl MessageBroker for MqttClient {
+    fn open_publisher(
+        &self,
+        target: PublishTarget,
+    ) -> BrokerFuture<'_, BrokerResult<Box<dyn BrokerPublisher>>> {
+        let publisher: Box<dyn BrokerPublisher> = Box::new(MqttPublisher {
+            client: self.clone(),
+            qos: target.mqtt_qos(),
+            topic: target.routing_key,
+            retain: target.retain,
+        });
+        Box::pin(async { Ok(publisher) })
+    }
+
+    fn subscribe<'a>(
+        &'a self,
+        source: &'a str,
+        qos: u8,
+    ) -> BrokerFuture<'a, BrokerResult<Box<dyn BrokerSubscription>>> {
+        Box::pin(async move {
+            let qos = rumqttc::qos(qos).map_err(|e| BrokerError::Invalid(e.to_string()))?;
+            match MqttClient::subscribe(self, source, qos).await {
+                Ok(subscription) => {
+                    let subscription: Box<dyn BrokerSubscription> = Box::new(subscription);
+                    Ok(subscription)
+                }
+                Err(e) if e.starts_with("Invalid topic filter") => Err(BrokerError::Invalid(e)),
+                Err(e) => Err(BrokerError::Unavailable(e)),
+            }
+        })
+    }
+
+    fn reply_queue(&self, _target: PublishTarget) -> BrokerFuture<'_, BrokerResult<ReplyQueue>> {
+        Box::pin(async {
+            Err(BrokerError::Invalid(
+                "RPC needs AMQP or the in-memory broker; MQTT 3.1.1 messages have no reply_to"
+                    .to_string(),
+            ))
+        })
+    }
+}
+
+struct MqttPublisher {
+    client: MqttClient,
+    topic: String,
+    qos: QoS,
+    retain: bool,
+}
+
+impl BrokerPublisher for MqttPublisher {
+    /// Fire-and-forget messages skip the confirm bookkeeping entirely
+    fn send(&self, message: OutgoingMessage) -> BrokerFuture<'_, BrokerResult<PendingConfirm>> {
+        Box::pin(async move {
+            if self.qos == QoS::AtMostOnce && !self.retain {
+                self.client
+                    .publish_nowait(&self.topic, QoS::AtMostOnce, message.payload)
+                    .await
+                    .map_err(BrokerError::Unavailable)?;
+                let pending: PendingConfirm = Box::pin(async { Ok(ConfirmStatus::NotRequested) });
+                return Ok(pending);
+            }
+
+            let publish = self
+                .client
+                .start_publish(&self.topic, self.qos, self.retain, message.payload)
+                .await
+                .map_err(BrokerError::Unavailable)?;
+            let pending: PendingConfirm =
+                Box::pin(
+                    async move { publish.confirmed().await.map_err(BrokerError::Unavailable) },
+                );
+            Ok(pending)
+        })
+    }
+}
+
+/// MQTT acknowledges on receipt, so there is nothing to settle
+impl BrokerSubscription for MqttSubscription {
+    fn next(&mut self) -> BrokerFuture<'_, Option<Result<ConsumedMessage, String>>> {
+        Box::pin(async move {
+            self.recv()
+                .await
+                .map(|message| Ok(ConsumedMessage::from_mqtt(message)))
+        })
+    }
+
+    fn close(self: Box<Self>) -> BrokerFuture<'static, ()> {
+        drop(self);
+        Box::pin(async {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rumqttc::{ConnAck, ConnectReturnCode, PubAck, PubComp};
+
+    fn offline_client() -> MqttClient {
+        MqttClient::start(MqttSettings {
+            host: "127.0.0.1".to_string(),
+            port: 1,
+            client_id: "rust-api-test".to_string(),
+            credentials: None,
+            keep_alive: Duration::from_secs(30),
+            last_will: None,
+            min_backoff: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(60),
+        })
+    }
+
+    fn queue_waiter(client: &MqttClient) -> oneshot::Receiver<Result<ConfirmStatus, String>> {
+        let (sender, receiver) = oneshot::channel();
+        client
+            .inner
+            .pending
+            .lock()
+            .unwrap()
+            .queued
+            .push_back(Some(sender));
+        receiver
+    }
+
+    #[tokio::test]
+    async fn test_confirms_follow_packet_ids() {
+        let client = offline_client();
+        let mut qos1 = queue_waiter(&client);
+        client.inner.pending.lock().unwrap().queued.push_back(None);
+        let mut qos0 = queue_waiter(&client);
+        let mut qos2 = queue_waiter(&client);
+
+        client.handle_event(Event::Outgoing(Outgoing::Publish(1)));
+        client.handle_event(Event::Outgoing(Outgoing::Publish(2)));
+        client.handle_event(Event::Outgoing(Outgoing::Publish(0)));
+        assert_eq!(qos0.try_recv().unwrap(), Ok(ConfirmStatus::NotRequested));
+
+        // A retransmit of packet 1 must not consume the next waiter
+        client.handle_event(Event::Outgoing(Outgoing::Publish(1)));
+        client.handle_event(Event::Outgoing(Outgoing::Publish(3)));
+        client.handle_event(Event::Incoming(Packet::PubAck(PubAck::new(1))));
+        assert_eq!(qos1.try_recv().unwrap(), Ok(ConfirmStatus::Ack));
+        assert!(qos2.try_recv().is_err());
+
+        client.handle_event(Event::Incoming(Packet::PubComp(PubComp::new(3))));
+        assert_eq!(qos2.try_recv().unwrap(), Ok(ConfirmStatus::Ack));
+
+        // Packet 2 was never acknowledged, so losing the connection fails it
+        assert_eq!(client.stats().pending, 1);
+        client.handle_disconnect("connection reset".to_string(), []);
+        let stats = client.stats();
+        assert_eq!((stats.pending, stats.confirmed, stats.failed), (0, 2, 1));
+        assert_eq!(stats.last_error.as_deref(), Some("connection reset"));
+    }
+
+    #[tokio::test]
+    async fn test_unsent_publishes_fail_on_disconnect_without_shifting_confirms() {
+        let client = offline_client();
+        let mut sent = queue_waiter(&client);
+        let mut unsent = queue_waiter(&client);
+        let mut later = queue_waiter(&client);
+        client.handle_event(Event::Outgoing(Outgoing::Publish(1)));
+
+        // The event loop had taken the second publish off the channel; the third is still in it
+        let dropped = Request::Publish(Publish::new("sensors/1", QoS::AtLeastOnce, "b"));
+        client.handle_disconnect("connection reset".to_string(), [dropped]);
+        assert!(sent.try_recv().unwrap().is_err());
+        assert!(unsent.try_recv().unwrap().is_err());
+        assert!(later.try_recv().is_err());
+        assert_eq!(client.stats().failed, 2);
+
+        // After reconnecting, the next publish out is the third one
+        client.handle_event(Event::Incoming(Packet::ConnAck(ConnAck::new(
+            ConnectReturnCode::Success,
+            false,
+        ))));
+        client.handle_event(Event::Outgoing(Outgoing::Publish(1)));
+        client.handle_event(Event::Incoming(Packet::PubAck(PubAck::new(1))));
+        assert_eq!(later.try_recv().unwrap(), Ok(ConfirmStatus::Ack));
+        assert_eq!(client.stats().pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_filter_topics_and_unsubscribe_on_drop() {
+        let client = offline_client();
+        let mut sensors = client
+            .subscribe("sensors/+", QoS::AtLeastOnce)
+            .await
+            .unwrap();
+        let other = client
+            .subscribe("sensors/+", QoS::AtLeastOnce)
+            .await
+            .unwrap();
+        assert!(client
+            .subscribe("sensors/#/x", QoS::AtMostOnce)
+            .await
+            .is_err());
+
+        for topic in ["alerts/critical", "sensors/temperature"] {
+            client
+                .deliver(Publish::new(topic, QoS::AtLeastOnce, "23.5"))
+                .await;
+        }
+        let message = sensors.recv().await.unwrap();
+        assert_eq!(message.topic, "sensors/temperature");
+        assert_eq!(message.payload, Bytes::from("23.5"));
+        assert_eq!(client.stats().received, 2);
+
+        drop(other);
+        assert_eq!(client.stats().subscriptions, vec!["sensors/+"]);
+        drop(sensors);
+        assert!(client.stats().subscriptions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_full_subscriptions_hold_delivery_instead_of_dropping() {
+        let client = offline_client();
+        let mut slow = client
+            .subscribe("sensors/+", QoS::AtLeastOnce)
+            .await
+            .unwrap();
+        let upgraded = client
+            .subscribe("sensors/+", QoS::ExactlyOnce)
+            .await
+            .unwrap();
+        assert_eq!(
+            client.inner.subscriptions.lock().unwrap()["sensors/+"].qos,
+            QoS::ExactlyOnce
+        );
+        drop(upgraded);
+
+        let total = SUBSCRIPTION_CAPACITY + 1;
+        let delivering = tokio::spawn({
+            let client = client.clone();
+            async move {
+                for i in 0..total {
+                    let publish = Publish::new("sensors/1", QoS::AtLeastOnce, i.to_string());
+                    client.deliver(publish).await;
+                }
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!delivering.is_finished());
+
+        for i in 0..total {
+            let message = slow.recv().await.unwrap();
+            assert_eq!(message.payload, Bytes::from(i.to_string()));
+        }
+        delivering.await.unwrap();
+        assert_eq!(client.stats().received, total as u64);
+    }
+}
@@ -0,0 +1,800 @@
+use crate::config::retention::RetentionPolicy;
+use crate::config::slo::SloConfig;
+use crate::model::comparison::{Comparison, RegressionThresholds, RunSnapshot};
+use crate::model::coverage::CoverageCalculator;
+use crate::model::k6::{K6Aggregates, K6Aggregator, K6ParseError, K6Reader};
+use crate::model::report::{CoverageData, PerformanceSummary, Report, ReportMetadata};
+use crate::model::retention::RetentionPlan;
+use crate::model::route::RouteInfo;
+use crate::model::threshold::ThresholdReport;
+use crate::model::trend::{TrendRun, Trends};
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use flate2::{write::GzEncoder, Compression};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+pub const PERFORMANCE_DATA_FILE: &str = "performance-data.json";
+pub const COVERAGE_FILE: &str = "endpoint-coverage.json";
+pub const SUMMARY_FILE: &str = "performance-summary.txt";
+pub const RAW_RESULTS_FILE: &str = "raw-results.json";
+pub const METADATA_FILE: &str = "metadata.json";
+/// Directory under the reports folder that holds archived reports
+pub const ARCHIVE_DIR: &str = "archive";
+
+const LATEST_REPORT_ID: &str = "latest";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+#[derive(Clone, Debug)]
+pub struct ReportConfig {
+    pub reports_dir: PathBuf,
+    /// Every route the server registers; coverage is computed against these
+    pub routes: Arc<Vec<RouteInfo>>,
+    pub slos: Arc<SloConfig>,
+    pub retention: Arc<RetentionPolicy>,
+    cache: Arc<ReportCache>,
+}
+
+/// Results derived from a report's files. Stored reports never change, so each is computed
+/// once per report and dropped when the report is removed.
+#[derive(Debug, Default)]
+struct ReportCache {
+    coverage: Mutex<HashMap<String, CoverageData>>,
+    thresholds: Mutex<HashMap<String, ThresholdReport>>,
+}
+
+impl ReportCache {
+    fn remove(&self, id: &str) {
+        self.coverage.lock().unwrap().remove(id);
+        self.thresholds.lock().unwrap().remove(id);
+    }
+}
+
+impl ReportConfig {
+    pub fn new(reports_dir: impl Into<PathBuf>) -> Self {
+        ReportConfig {
+            reports_dir: reports_dir.into(),
+            routes: Arc::new(Vec::new()),
+            slos: Arc::new(SloConfig::default()),
+            retention: Arc::new(RetentionPolicy::default()),
+            cache: Arc::default(),
+        }
+    }
+
+    pub fn with_routes(mut self, mut routes: Vec<RouteInfo>) -> Self {
+        routes.sort_by(|a, b| a.path.cmp(&b.path).then_with(|| a.method.cmp(&b.method)));
+        self.routes = Arc::new(routes);
+        self.cache = Arc::default();
+        self
+    }
+
+    pub fn from_env() -> Self {
+        let reports_dir = env::var("REPORTS_DIR").unwrap_or_else(|_| "reports".to_string());
+        let mut config = Self::new(reports_dir);
+        config.slos = Arc::new(SloConfig::from_env());
+        config.retention = Arc::new(RetentionPolicy::from_env());
+        config
+    }
+}
+
+// Helper functions for reading the reports directory
+impl ReportConfig {
+    /// All report directories, newest first
+    pub fn list_reports(&self) -> io::Result<Vec<Report>> {
+        self.report_ids()?
+            .into_iter()
+            .map(|id| {
+                let dir = self.reports_dir.join(&id);
+                self.load_report(&id, &dir)
+            })
+            .collect()
+    }
+
+    /// Ids of all report directories, newest first, without reading any report files
+    fn report_ids(&self) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.reports_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut ids = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let id = entry.file_name().to_string_lossy().to_string();
+            if is_valid_report_id(&id) {
+                ids.push((parse_report_id(&id).1, id));
+            }
+        }
+
+        ids.sort_by(|a, b| b.cmp(a));
+        Ok(ids.into_iter().map(|(_, id)| id).collect())
+    }
+
+    pub fn get_report(&self, id: &str) -> io::Result<Report> {
+        let (id, dir) = self.resolve_report(id)?;
+        self.load_report(&id, &dir)
+    }
+
+    /// Coverage of the server's routes, computed from the raw k6 stream when there is one.
+    /// Reports without a stream fall back to their `endpoint-coverage.json`.
+    pub fn get_coverage(&self, id: &str) -> io::Result<CoverageData> {
+        let (id, dir) = self.resolve_report(id)?;
+        self.load_coverage(&id, &dir)
+    }
+
+    pub fn get_performance_summary(&self, id: &str) -> io::Result<PerformanceSummary> {
+        let (_, dir) = self.resolve_report(id)?;
+        let data: Value = read_json(&dir.join(PERFORMANCE_DATA_FILE))?;
+        Ok(PerformanceSummary::from_value(&data))
+    }
+
+    /// Pretty-printed `performance-data.json`, or the raw k6 stream when that is all we have
+    pub fn get_raw_data(&self, id: &str) -> io::Result<String> {
+        let (_, dir) = self.resolve_report(id)?;
+        let performance_data = dir.join(PERFORMANCE_DATA_FILE);
+
+        if performance_data.is_file() {
+            let value: Value = read_json(&performance_data)?;
+            return serde_json::to_string_pretty(&value)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+
+        fs::read_to_string(dir.join(RAW_RESULTS_FILE))
+    }
+
+    /// Per-metric and per-endpoint statistics computed from `raw-results.json`, if the
+    /// report has one. Endpoints are also pooled under each endpoint SLO's key.
+    pub fn get_aggregates(&self, id: &str) -> Result<Option<K6Aggregates>, K6ParseError> {
+        let (_, dir) = self.resolve_report(id)?;
+        let raw_results = dir.join(RAW_RESULTS_FILE);
+        if !raw_results.is_file() {
+            return Ok(None);
+        }
+
+        K6Aggregator::new()
+            .with_groups(self.slos.endpoints.keys().cloned())
+            .aggregate_reader(K6Reader::open(&raw_results)?)
+            .map(Some)
+    }
+
+    /// The metrics used to compare runs, preferring the raw k6 stream over the summary
+    pub fn get_snapshot(&self, id: &str) -> io::Result<RunSnapshot> {
+        let (id, _) = self.resolve_report(id)?;
+
+        match self.get_aggregates(&id) {
+            Ok(Some(aggregates)) => return Ok(RunSnapshot::from_aggregates(&id, &aggregates)),
+            Ok(None) => {}
+            Err(e) => eprintln!("Error parsing raw results for {}: {}", id, e),
+        }
+
+        let summary = self.get_performance_summary(&id)?;
+        Ok(RunSnapshot::from_summary(&id, &summary))
+    }
+
+    /// The k6 thresholds and configured SLOs, re-evaluated against the run's metrics. The
+    /// evaluation is cached, so listing reports does not re-parse every raw stream.
+    pub fn get_thresholds(&self, id: &str) -> io::Result<ThresholdReport> {
+        let (id, _) = self.resolve_report(id)?;
+        if let Some(thresholds) = self.cache.thresholds.lock().unwrap().get(&id) {
+            return Ok(thresholds.clone());
+        }
+
+        let summary = self.get_performance_summary(&id).ok();
+        let aggregates = self.get_aggregates(&id).unwrap_or_else(|e| {
+            eprintln!("Error parsing raw results for {}: {}", id, e);
+            None
+        });
+
+        let thresholds =
+            ThresholdReport::evaluate(summary.as_ref(), aggregates.as_ref(), &self.slos);
+        self.cache
+            .thresholds
+            .lock()
+            .unwrap()
+            .insert(id, thresholds.clone());
+        Ok(thresholds)
+    }
+
+    pub fn compare_reports(
+        &self,
+        base: &str,
+        head: &str,
+        thresholds: RegressionThresholds,
+    ) -> io::Result<Comparison> {
+        let base = self.get_snapshot(base)?;
+        let head = self.get_snapshot(head)?;
+        Ok(Comparison::new(&base, &head, thresholds))
+    }
+
+    /// Metrics of every stored run over time, optionally for one test type or endpoint.
+    /// Runs without usable data are skipped.
+    pub fn get_trends(
+        &self,
+        test_type: Option<&str>,
+        endpoint: Option<&str>,
+    ) -> io::Result<Trends> {
+        let runs = self
+            .list_reports()?
+            .into_iter()
+            .filter(|report| test_type.is_none_or(|test_type| report.test_type == test_type))
+            .filter_map(|report| {
+                let mut snapshot = match self.get_snapshot(&report.id) {
+                    Ok(snapshot) => snapshot,
+                    Err(e) => {
+                        eprintln!("Skipping {} in trends: {}", report.id, e);
+                        return None;
+                    }
+                };
+                if let Some(endpoint) = endpoint {
+                    snapshot.endpoints.retain(|name, _| name == endpoint);
+                }
+                let coverage = self
+                    .get_coverage(&report.id)
+                    .ok()
+                    .map(|coverage| coverage.summary.percentage());
+
+                Some(TrendRun {
+                    test_type: report.test_type,
+                    timestamp: report.timestamp,
+                    coverage,
+                    snapshot,
+                })
+            })
+            .collect();
+
+        Ok(Trends::new(runs))
+    }
+
+    /// Map a report id from the URL to its directory, rejecting anything that is not a plain
+    /// directory name. `latest` resolves to the newest report.
+    pub fn resolve_report(&self, id: &str) -> io::Result<(String, PathBuf)> {
+        let id = if id == LATEST_REPORT_ID {
+            self.report_ids()?
+                .into_iter()
+                .next()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No reports found"))?
+        } else {
+            id.to_string()
+        };
+
+        if !is_valid_report_id(&id) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Invalid report id '{}'", id),
+            ));
+        }
+
+        let dir = self.reports_dir.join(&id);
+        if !dir.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Report '{}' not found", id),
+            ));
+        }
+
+        Ok((id, dir))
+    }
+
+    fn load_report(&self, id: &str, dir: &Path) -> io::Result<Report> {
+        let mut files: Vec<String> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+
+        let (test_type, timestamp) = parse_report_id(id);
+        let name = match timestamp {
+            Some(ts) => format!("{} {}", test_type, ts.format("%Y-%m-%d %H:%M:%S")),
+            None => id.to_string(),
+        };
+
+        let has_file = |name: &str| files.iter().any(|file| file == name);
+        let status = if has_file(PERFORMANCE_DATA_FILE) {
+            "completed"
+        } else if has_file(RAW_RESULTS_FILE) {
+            "partial"
+        } else {
+            "failed"
+        };
+
+        let duration = read_json::<Value>(&dir.join(PERFORMANCE_DATA_FILE))
+            .ok()
+            .and_then(|data| {
+                data.pointer("/state/testRunDurationMs")
+                    .and_then(Value::as_f64)
+            })
+            .unwrap_or(0.0) as u64;
+
+        let coverage_percentage = self
+            .load_coverage(id, dir)
+            .map(|coverage| coverage.summary.percentage())
+            .unwrap_or(0.0);
+
+        let summary = fs::read_to_string(dir.join(SUMMARY_FILE))
+            .unwrap_or_else(|_| "No summary available for this report.".to_string());
+
+        let baseline = read_json::<ReportMetadata>(&dir.join(METADATA_FILE))
+            .is_ok_and(|metadata| metadata.baseline);
+
+        Ok(Report {
+            id: id.to_string(),
+            name,
+            test_type,
+            status: status.to_string(),
+            timestamp,
+            duration,
+            coverage_percentage,
+            baseline,
+            files,
+            summary,
+        })
+    }
+
+    fn load_coverage(&self, id: &str, dir: &Path) -> io::Result<CoverageData> {
+        let raw_results = dir.join(RAW_RESULTS_FILE);
+        if self.routes.is_empty() || !raw_results.is_file() {
+            return read_json(&dir.join(COVERAGE_FILE));
+        }
+
+        if let Some(coverage) = self.cache.coverage.lock().unwrap().get(id) {
+            return Ok(coverage.clone());
+        }
+        let coverage = CoverageCalculator::calculate(&self.routes, K6Reader::open(&raw_results)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.cache
+            .coverage
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), coverage.clone());
+        Ok(coverage)
+    }
+}
+
+// Helper functions for storing uploaded reports
+impl ReportConfig {
+    /// A hidden directory to receive upload parts; it is skipped when listing reports
+    pub fn create_staging_dir(&self) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.reports_dir)?;
+        let dir = self.reports_dir.join(format!(".upload-{}", Uuid::new_v4()));
+        fs::create_dir(&dir)?;
+        Ok(dir)
+    }
+
+    /// Validate the files in a staging directory and move it into place under a new
+    /// `<test_type>_<timestamp>` id. `source` records how the report arrived. The staging
+    /// directory is removed on failure.
+    pub fn finalize_upload(
+        &self,
+        staging: &Path,
+        test_type: &str,
+        source: &str,
+    ) -> io::Result<Report> {
+        let result = self.store_upload(staging, test_type, source);
+        if result.is_err() {
+            let _ = fs::remove_dir_all(staging);
+        }
+        result
+    }
+
+    fn store_upload(&self, staging: &Path, test_type: &str, source: &str) -> io::Result<Report> {
+        if !is_valid_test_type(test_type) {
+            return Err(invalid_input(format!("Invalid test type '{}'", test_type)));
+        }
+
+        validate_upload(staging, &self.routes)?;
+
+        let base_id = format!("{}_{}", test_type, Local::now().format(TIMESTAMP_FORMAT));
+        let id = (1..)
+            .map(|n| match n {
+                1 => base_id.clone(),
+                n => format!("{}-{}", base_id, n),
+            })
+            .find(|id| !self.reports_dir.join(id).exists())
+            .unwrap_or(base_id);
+
+        let mut files: Vec<String> = fs::read_dir(staging)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+
+        let metadata = ReportMetadata {
+            id: id.clone(),
+            test_type: test_type.to_string(),
+            created_at: Utc::now(),
+            source: source.to_string(),
+            files,
+            baseline: false,
+        };
+        let metadata = serde_json::to_vec_pretty(&metadata)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(staging.join(METADATA_FILE), metadata)?;
+
+        fs::rename(staging, self.reports_dir.join(&id))?;
+        self.get_report(&id)
+    }
+}
+
+// Helper functions for baselines, retention and removing reports
+impl ReportConfig {
+    /// Pin or unpin a report as a baseline. Reports that predate `metadata.json` get one.
+    pub fn set_baseline(&self, id: &str, baseline: bool) -> io::Result<Report> {
+        let (id, dir) = self.resolve_report(id)?;
+        let path = dir.join(METADATA_FILE);
+
+        let mut metadata = match read_json::<ReportMetadata>(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let report = self.load_report(&id, &dir)?;
+                let created_at: DateTime<Utc> = fs::metadata(&dir)?.modified()?.into();
+                ReportMetadata {
+                    id: id.clone(),
+                    test_type: report.test_type,
+                    created_at,
+                    source: "local".to_string(),
+                    files: report.files,
+                    baseline,
+                }
+            }
+            Err(e) => return Err(e),
+        };
+        metadata.baseline = baseline;
+
+        let metadata = serde_json::to_vec_pretty(&metadata)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, metadata)?;
+        self.load_report(&id, &dir)
+    }
+
+    /// Remove a report directory. Baselines are refused.
+    pub fn delete_report(&self, id: &str) -> io::Result<()> {
+        let (id, dir) = self.resolve_for_removal(id)?;
+        self.cache.remove(&id);
+        fs::remove_dir_all(dir)
+    }
+
+    /// Compress a report directory to `archive/<id>.tar.gz` and remove the directory.
+    /// Baselines are refused. Returns the path of the tarball.
+    pub fn archive_report(&self, id: &str) -> io::Result<PathBuf> {
+        let (id, dir) = self.resolve_for_removal(id)?;
+        let archive_dir = self.reports_dir.join(ARCHIVE_DIR);
+        fs::create_dir_all(&archive_dir)?;
+
+        let tarball = archive_dir.join(format!("{}.tar.gz", id));
+        if tarball.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Archive for '{}' already exists", id),
+            ));
+        }
+
+        // Write under a hidden name first so a failed archive never looks complete
+        let partial = archive_dir.join(format!(".{}.tar.gz.partial", id));
+        let result =
+            write_tarball(&partial, &id, &dir).and_then(|_| fs::rename(&partial, &tarball));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+
+        self.cache.remove(&id);
+        fs::remove_dir_all(dir)?;
+        Ok(tarball)
+    }
+
+    /// What the retention policy would keep and remove right now
+    pub fn retention_plan(&self) -> io::Result<RetentionPlan> {
+        Ok(RetentionPlan::new(
+            &self.list_reports()?,
+            &self.retention,
+            Local::now().naive_local(),
+        ))
+    }
+
+    /// Delete or archive every report the retention policy no longer keeps
+    pub fn apply_retention(&self) -> io::Result<RetentionPlan> {
+        let mut plan = self.retention_plan()?;
+
+        let expired = std::mem::take(&mut plan.expired);
+        for id in expired {
+            let result = if self.retention.archive {
+                self.archive_report(&id).map(|_| ())
+            } else {
+                self.delete_report(&id)
+            };
+            match result {
+                Ok(()) => plan.expired.push(id),
+                Err(e) => {
+                    eprintln!("Error removing expired report {}: {}", id, e);
+                    plan.failed.push(id);
+                }
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Like `resolve_report`, but `latest` is not accepted and baselines are refused
+    fn resolve_for_removal(&self, id: &str) -> io::Result<(String, PathBuf)> {
+        if id == LATEST_REPORT_ID {
+            return Err(invalid_input(
+                "Refusing to remove 'latest'; use the report id".to_string(),
+            ));
+        }
+
+        let (id, dir) = self.resolve_report(id)?;
+        if self.load_report(&id, &dir)?.baseline {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("Report '{}' is pinned as a baseline", id),
+            ));
+        }
+
+        Ok((id, dir))
+    }
+}
+
+fn write_tarball(path: &Path, id: &str, dir: &Path) -> io::Result<()> {
+    let mut builder = tar::Builder::new(GzEncoder::new(
+        fs::File::create(path)?,
+        Compression::default(),
+    ));
+    builder.append_dir_all(id, dir)?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Test types become part of report ids, so keep them to plain characters
+pub fn is_valid_test_type(test_type: &str) -> bool {
+    !test_type.is_empty()
+        && test_type
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Check that uploaded files are what k6 produces before they become a report. Without a
+/// coverage file, one is computed from the raw stream against the server's routes.
+fn validate_upload(dir: &Path, routes: &[RouteInfo]) -> io::Result<()> {
+    let summary = dir.join(PERFORMANCE_DATA_FILE);
+    if !summary.is_file() {
+        return Err(invalid_input(
+            "Missing 'summary' part with the k6 summary export".to_string(),
+        ));
+    }
+    let summary: Value = read_json(&summary)
+        .map_err(|e| invalid_input(format!("Invalid k6 summary export: {}", e)))?;
+    if !summary.get("metrics").is_some_and(Value::is_object) {
+        return Err(invalid_input(
+            "Invalid k6 summary export: missing 'metrics' object".to_string(),
+        ));
+    }
+
+    let coverage = dir.join(COVERAGE_FILE);
+    if coverage.is_file() {
+        read_json::<CoverageData>(&coverage)
+            .map_err(|e| invalid_input(format!("Invalid coverage file: {}", e)))?;
+    }
+
+    let raw_results = dir.join(RAW_RESULTS_FILE);
+    if raw_results.is_file() {
+        let coverage_data = CoverageCalculator::calculate(routes, K6Reader::open(&raw_results)?)
+            .map_err(|e| invalid_input(format!("Invalid raw results: {}", e)))?;
+        if !coverage.is_file() && !routes.is_empty() {
+            let coverage_data = serde_json::to_vec_pretty(&coverage_data)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            fs::write(&coverage, coverage_data)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn is_valid_report_id(id: &str) -> bool {
+    !id.is_empty()
+        && id != ARCHIVE_DIR
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Split `<test_type>_<YYYYMMDD>_<HHMMSS>` into its test type and timestamp
+fn parse_report_id(id: &str) -> (String, Option<NaiveDateTime>) {
+    let mut parts = id.rsplitn(3, '_');
+    let (time, date, test_type) = (parts.next(), parts.next(), parts.next());
+
+    if let (Some(time), Some(date), Some(test_type)) = (time, date, test_type) {
+        // Uploads get a "-N" suffix when two land in the same second
+        let time = time.split('-').next().unwrap_or(time);
+        let stamp = format!("{}_{}", date, time);
+        if let Ok(timestamp) = NaiveDateTime::parse_from_str(&stamp, TIMESTAMP_FORMAT) {
+            return (test_type.to_string(), Some(timestamp));
+        }
+    }
+
+    (id.to_string(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_report_id() {
+        let (test_type, timestamp) = parse_report_id("comprehensive_20250629_214321");
+        assert_eq!(test_type, "comprehensive");
+        assert_eq!(
+            timestamp.unwrap().format("%Y-%m-%d %H:%M:%S").to_string(),
+            "2025-06-29 21:43:21"
+        );
+
+        let (test_type, timestamp) = parse_report_id("adhoc");
+        assert_eq!(test_type, "adhoc");
+        assert!(timestamp.is_none());
+    }
+
+    #[test]
+    fn test_finalize_upload_creates_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ReportConfig::new(dir.path());
+
+        let staging = config.create_staging_dir().unwrap();
+        fs::write(
+            staging.join(PERFORMANCE_DATA_FILE),
+            r#"{"metrics": {}, "state": {"testRunDurationMs": 1000}}"#,
+        )
+        .unwrap();
+        let report = config.finalize_upload(&staging, "ci", "upload").unwrap();
+
+        assert_eq!(report.test_type, "ci");
+        assert!(report.files.contains(&METADATA_FILE.to_string()));
+        assert!(!staging.exists());
+
+        // A second upload in the same second gets its own directory
+        let staging = config.create_staging_dir().unwrap();
+        fs::write(staging.join(PERFORMANCE_DATA_FILE), r#"{"metrics": {}}"#).unwrap();
+        let second = config.finalize_upload(&staging, "ci", "upload").unwrap();
+        assert_ne!(second.id, report.id);
+        assert_eq!(second.test_type, "ci");
+        assert_eq!(config.list_reports().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_finalize_upload_rejects_invalid_raw_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ReportConfig::new(dir.path());
+
+        let staging = config.create_staging_dir().unwrap();
+        fs::write(staging.join(PERFORMANCE_DATA_FILE), r#"{"metrics": {}}"#).unwrap();
+        fs::write(staging.join(RAW_RESULTS_FILE), "not json\n").unwrap();
+
+        let err = config
+            .finalize_upload(&staging, "ci", "upload")
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!staging.exists());
+        assert!(config.list_reports().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_baselines_are_never_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        for id in ["demo_20250629_155400", "demo_20250701_090000"] {
+            fs::create_dir(dir.path().join(id)).unwrap();
+            fs::write(dir.path().join(id).join(PERFORMANCE_DATA_FILE), "{}").unwrap();
+        }
+        let mut config = ReportConfig::new(dir.path());
+        config.retention = Arc::new(RetentionPolicy {
+            keep_last: Some(0),
+            ..RetentionPolicy::default()
+        });
+
+        let pinned = config.set_baseline("demo_20250629_155400", true).unwrap();
+        assert!(pinned.baseline);
+        assert!(pinned.files.contains(&METADATA_FILE.to_string()));
+        let err = config.delete_report("demo_20250629_155400").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        let err = config.archive_report("demo_20250629_155400").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+        let plan = config.apply_retention().unwrap();
+        assert_eq!(plan.expired, vec!["demo_20250701_090000"]);
+        let reports = config.list_reports().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].id, "demo_20250629_155400");
+
+        config.set_baseline("demo_20250629_155400", false).unwrap();
+        let tarball = config.archive_report("demo_20250629_155400").unwrap();
+        assert!(tarball.is_file());
+        assert!(config.list_reports().unwrap().is_empty());
+        assert!(config.resolve_report(ARCHIVE_DIR).is_err());
+    }
+
+    #[test]
+    fn test_rejects_path_traversal() {
+        let config = ReportConfig::new("reports");
+        assert!(config.resolve_report("..").is_err());
+        assert!(config.resolve_report("../src").is_err());
+        assert!(!is_valid_report_id(".hidden"));
+    }
+
+    #[test]
+    fn test_list_reports_reads_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let report_dir = dir.path().join("demo_20250629_155400");
+        fs::create_dir(&report_dir).unwrap();
+        fs::write(
+            report_dir.join(PERFORMANCE_DATA_FILE),
+            r#"{"state": {"testRunDurationMs": 30500}}"#,
+        )
+        .unwrap();
+        fs::write(
+            report_dir.join(COVERAGE_FILE),
+            r#"{"summary": {"totalEndpoints": 8, "testedEndpoints": 7, "coveragePercentage": "87.5%"}}"#,
+        )
+        .unwrap();
+
+        let config = ReportConfig::new(dir.path());
+        let reports = config.list_reports().unwrap();
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].test_type, "demo");
+        assert_eq!(reports[0].status, "completed");
+        assert_eq!(reports[0].duration, 30500);
+        assert_eq!(reports[0].coverage_percentage, 87.5);
+        assert_eq!(config.get_report("latest").unwrap().id, reports[0].id);
+    }
+
+    #[test]
+    fn test_list_reports_uses_computed_coverage() {
+        let dir = tempfile::tempdir().unwrap();
+        let report_dir = dir.path().join("demo_20250629_155400");
+        fs::create_dir(&report_dir).unwrap();
+        fs::write(
+            report_dir.join(COVERAGE_FILE),
+            r#"{"summary": {"totalEndpoints": 8, "testedEndpoints": 7, "coveragePercentage": "87.5%"}}"#,
+        )
+        .unwrap();
+        fs::write(
+            report_dir.join(RAW_RESULTS_FILE),
+            r#"{"metric":"http_reqs","type":"Point","data":{"time":"2025-06-29T21:43:22+07:00","value":1,"tags":{"method":"GET","status":"200","url":"http://127.0.0.1:8000/users/7"}}}
+"#,
+        )
+        .unwrap();
+
+        let config = ReportConfig::new(dir.path()).with_routes(vec![
+            RouteInfo::new("GET", "/users/:id"),
+            RouteInfo::new("POST", "/users"),
+        ]);
+        let reports = config.list_reports().unwrap();
+
+        assert_eq!(reports[0].coverage_percentage, 50.0);
+        assert_eq!(
+            config
+                .get_coverage(&reports[0].id)
+                .unwrap()
+                .summary
+                .percentage(),
+            50.0
+        );
+    }
+}
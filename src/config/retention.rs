@@ -0,0 +1,64 @@
+use serde::Serialize;
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+
+/// Which stored reports survive the scheduled cleanup. A report is kept when any rule
+/// keeps it; reports pinned as a baseline are always kept. With no rule set, nothing is
+/// ever removed.
+#[derive(Clone, Debug, Serialize)]
+pub struct RetentionPolicy {
+    /// Keep the newest N reports of every test type, not counting baselines
+    pub keep_last: Option<usize>,
+    /// Keep reports younger than this many days
+    pub max_age_days: Option<u64>,
+    /// Archive expired reports to a tarball instead of deleting them
+    pub archive: bool,
+    #[serde(skip)]
+    pub interval: Duration,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            keep_last: None,
+            max_age_days: None,
+            archive: false,
+            interval: Duration::from_secs(DEFAULT_INTERVAL_SECS),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Reads `REPORT_RETENTION_KEEP_LAST`, `REPORT_RETENTION_MAX_AGE_DAYS`,
+    /// `REPORT_RETENTION_ARCHIVE` and `REPORT_RETENTION_INTERVAL_SECS`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        RetentionPolicy {
+            keep_last: env_value("REPORT_RETENTION_KEEP_LAST"),
+            max_age_days: env_value("REPORT_RETENTION_MAX_AGE_DAYS"),
+            archive: env_value("REPORT_RETENTION_ARCHIVE").unwrap_or(defaults.archive),
+            interval: env_value("REPORT_RETENTION_INTERVAL_SECS")
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.interval),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.keep_last.is_some() || self.max_age_days.is_some()
+    }
+}
+
+fn env_value<T: FromStr>(name: &str) -> Option<T> {
+    let value = env::var(name).ok()?;
+    match value.trim().parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            eprintln!("Ignoring invalid {}: '{}'", name, value);
+            None
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::config::broker::{BrokerError, OutgoingMessage, PublishTarget};
+use crate::config::consumer::{HandlerFuture, MessageHandler};
+use crate::config::transport::Transport;
+use crate::model::consumer::ConsumedMessage;
+use crate::model::rpc::RPC_ERROR_HEADER;
+use lapin::{
+    types::{AMQPValue, FieldTable},
+    BasicProperties,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+
+pub type ReplyFuture<'a> = Pin<Box<dyn Future<Output = Result<RpcReply, String>> + Send + 'a>>;
+
+/// What a responder answers an RPC request with
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcReply {
+    pub body: Vec<u8>,
+    pub content_type: String,
+}
+
+impl RpcReply {
+    pub fn json(value: &serde_json::Value) -> Self {
+        RpcReply {
+            body: value.to_string().into_bytes(),
+            content_type: "application/json".to_string(),
+        }
+    }
+}
+
+/// Answers the RPC requests of a consumer worker with the `rpc` handler. An error goes
+/// back to the requester as the reply instead of being retried.
+pub trait RpcResponder: Send + Sync {
+    fn respond<'a>(&'a self, request: &'a ConsumedMessage) -> ReplyFuture<'a>;
+}
+
+/// Sends the request back as it came
+struct EchoResponder;
+
+impl RpcResponder for EchoResponder {
+    fn respond<'a>(&'a self, request: &'a ConsumedMessage) -> ReplyFuture<'a> {
+        Box::pin(async move {
+            Ok(RpcReply {
+                body: request.body.to_vec(),
+                content_type: request
+                    .content_type
+                    .clone()
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+            })
+        })
+    }
+}
+
+/// Responders by name, for `{"type": "rpc", "responder": <name>}` consumer handlers. Code
+/// in this service registers its own; `echo` is always there.
+#[derive(Clone)]
+pub struct RpcResponders {
+    responders: Arc<RwLock<BTreeMap<String, Arc<dyn RpcResponder>>>>,
+}
+
+impl Default for RpcResponders {
+    fn default() -> Self {
+        let responders = RpcResponders {
+            responders: Arc::new(RwLock::new(BTreeMap::new())),
+        };
+        responders.register("echo", EchoResponder);
+        responders
+    }
+}
+
+impl RpcResponders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a responder, replacing any registered under the same name. Workers already
+    /// running keep the one they started with.
+    pub fn register(&self, name: &str, responder: impl RpcResponder + 'static) {
+        self.responders
+            .write()
+            .unwrap()
+            .insert(name.to_string(), Arc::new(responder));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn RpcResponder>> {
+        self.responders.read().unwrap().get(name).cloned()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.responders.read().unwrap().keys().cloned().collect()
+    }
+}
+
+/// The consumer handler that runs a responder and publishes its reply to the request's
+/// `reply_to`
+pub struct RpcHandler {
+    pub responder: Arc<dyn RpcResponder>,
+    pub transport: Transport,
+}
+
+impl MessageHandler for RpcHandler {
+    fn handle<'a>(&'a self, message: &'a ConsumedMessage) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            let Some(reply_to) = &message.reply_to else {
+                return Err(format!("RPC request {} has no reply_to", message.id));
+            };
+            let mut properties = BasicProperties::default();
+            if let Some(correlation_id) = &message.correlation_id {
+                properties = properties.with_correlation_id(correlation_id.as_str().into());
+            }
+            let reply = match self.responder.respond(message).await {
+                Ok(reply) => reply,
+                Err(e) => {
+                    let mut headers = FieldTable::default();
+                    headers.insert(
+                        RPC_ERROR_HEADER.into(),
+                        AMQPValue::LongString(e.clone().into()),
+                    );
+                    properties = properties.with_headers(headers);
+                    RpcReply::json(&json!({ "error": e }))
+                }
+            };
+
+            // The reply queue is the requester's, so it is not declared here. A requester
+            // that gave up has taken its queue with it, and the reply is dropped.
+            let target = PublishTarget {
+                exchange: String::new(),
+                routing_key: reply_to.clone(),
+                qos: 0,
+                retain: false,
+                declare: false,
+            };
+            let reply_error = |e: BrokerError| format!("Failed to reply to {}: {}", reply_to, e);
+            let publisher = self
+                .transport
+                .broker()
+                .open_publisher(target)
+                .await
+                .map_err(reply_error)?;
+            let message = OutgoingMessage {
+                payload: reply.body,
+                properties: properties.with_content_type(reply.content_type.as_str().into()),
+            };
+            // Sent without confirms, so the confirm resolves right away
+            publisher
+                .send(message)
+                .await
+                .map_err(reply_error)?
+                .await
+                .map_err(reply_error)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn test_echo_is_registered_and_echoes() {
+        let responders = RpcResponders::new();
+        assert_eq!(responders.names(), vec!["echo"]);
+
+        let request = ConsumedMessage {
+            id: "m-1".to_string(),
+            source: "rpc.echo".to_string(),
+            exchange: String::new(),
+            routing_key: "rpc.echo".to_string(),
+            headers: BTreeMap::new(),
+            body: Bytes::from(r#"{"ping":1}"#),
+            content_type: Some("application/json".to_string()),
+            content_encoding: None,
+            correlation_id: Some("c-1".to_string()),
+            reply_to: Some("amq.gen-1".to_string()),
+            priority: None,
+            expiration: None,
+            timestamp: None,
+            app_id: None,
+            redelivered: false,
+            received_at: Utc::now(),
+        };
+        let reply = responders
+            .get("echo")
+            .unwrap()
+            .respond(&request)
+            .await
+            .unwrap();
+        assert_eq!(reply.body, br#"{"ping":1}"#.to_vec());
+        assert_eq!(reply.content_type, "application/json");
+        assert!(responders.get("prices").is_none());
+    }
+}
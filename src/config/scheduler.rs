@@ -0,0 +1,387 @@
+use crate::config::broker::{BrokerError, BrokerResult, OutgoingMessage, PublishTarget};
+use crate::config::rabbitmq::backoff;
+use crate::config::transport::Transport;
+use crate::model::mqtt::ConfirmStatus;
+use crate::model::schedule::ScheduledMessage;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+const RETRY_MIN_BACKOFF: Duration = Duration::from_millis(500);
+const RETRY_MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Pending messages beyond this are refused; the whole list is rewritten on every change
+const MAX_SCHEDULED: usize = 100_000;
+
+/// Holds publish requests with a `delay_ms` or `deliver_at` and publishes them when due.
+/// Pending messages are written to a file after every change and read back on start, so
+/// they survive a restart; one that fell due while the server was down goes out right away.
+#[derive(Clone)]
+pub struct Scheduler {
+    transport: Transport,
+    inner: Arc<SchedulerInner>,
+}
+
+struct SchedulerInner {
+    /// Unset keeps the messages in memory only
+    path: Option<PathBuf>,
+    pending: Mutex<Pending>,
+    /// Woken when a message is scheduled, so the delivery loop looks at the new due time
+    changed: Notify,
+    /// The version last written to `path`. Whoever holds it writes every change made so
+    /// far, so callers waiting behind a write often find theirs already saved.
+    written: tokio::sync::Mutex<u64>,
+}
+
+#[derive(Default)]
+struct Pending {
+    messages: HashMap<String, ScheduledMessage>,
+    /// The next message due first
+    due: BTreeSet<(DateTime<Utc>, String)>,
+    /// Bumped on every change
+    version: u64,
+}
+
+impl Pending {
+    fn insert(&mut self, message: ScheduledMessage) {
+        if let Some(replaced) = self.messages.remove(&message.id) {
+            self.due.remove(&(replaced.deliver_at, replaced.id));
+        }
+        self.due.insert((message.deliver_at, message.id.clone()));
+        self.messages.insert(message.id.clone(), message);
+        self.version += 1;
+    }
+
+    fn remove(&mut self, id: &str) -> Option<ScheduledMessage> {
+        let message = self.messages.remove(id)?;
+        self.due.remove(&(message.deliver_at, message.id.clone()));
+        self.version += 1;
+        Some(message)
+    }
+
+    fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.due.first().map(|(deliver_at, _)| *deliver_at)
+    }
+
+    /// The first message due by `now`
+    fn first_due(&self, now: DateTime<Utc>) -> Option<&ScheduledMessage> {
+        self.due
+            .first()
+            .filter(|(deliver_at, _)| *deliver_at <= now)
+            .and_then(|(_, id)| self.messages.get(id))
+    }
+
+    fn sorted(&self) -> Vec<ScheduledMessage> {
+        self.due
+            .iter()
+            .filter_map(|(_, id)| self.messages.get(id).cloned())
+            .collect()
+    }
+}
+
+impl Scheduler {
+    pub fn new(transport: Transport, path: Option<PathBuf>) -> Self {
+        Scheduler {
+            transport,
+            inner: Arc::new(SchedulerInner {
+                path,
+                pending: Mutex::new(Pending::default()),
+                changed: Notify::new(),
+                written: tokio::sync::Mutex::new(0),
+            }),
+        }
+    }
+
+    /// Read the pending messages back from `path`; a missing file means none
+    pub fn load(transport: Transport, path: &Path) -> io::Result<Self> {
+        let scheduler = Scheduler::new(transport, Some(path.to_path_buf()));
+        let messages: Vec<ScheduledMessage> = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let mut pending = scheduler.inner.pending.lock().unwrap();
+        for message in messages {
+            pending.insert(message);
+        }
+        *scheduler.inner.written.try_lock().unwrap() = pending.version;
+        drop(pending);
+        Ok(scheduler)
+    }
+
+    /// Keep the messages in the file named by `SCHEDULED_MESSAGES`, defaulting to
+    /// `scheduled.json`. A file that cannot be read leaves scheduling in memory only,
+    /// rather than overwriting it.
+    pub fn from_env(transport: Transport) -> Self {
+        let path = env::var("SCHEDULED_MESSAGES").unwrap_or_else(|_| "scheduled.json".to_string());
+        match Self::load(transport.clone(), Path::new(&path)) {
+            Ok(scheduler) => scheduler,
+            Err(e) => {
+                eprintln!("Failed to load scheduled messages from {}: {}", path, e);
+                Scheduler::new(transport, None)
+            }
+        }
+    }
+
+    /// Start publishing messages as they fall due
+    pub fn start(&self) {
+        let pending = self.inner.pending.lock().unwrap().messages.len();
+        if pending > 0 {
+            println!("⏰ {} scheduled messages pending", pending);
+        }
+        let scheduler = self.clone();
+        tokio::spawn(async move { scheduler.run().await });
+    }
+
+    /// Returns once the message is saved. Fails with `QuotaExceeded` when
+    /// `MAX_SCHEDULED` messages are already pending.
+    pub async fn schedule(&self, message: ScheduledMessage) -> io::Result<()> {
+        let id = message.id.clone();
+        {
+            let mut pending = self.inner.pending.lock().unwrap();
+            if pending.messages.len() >= MAX_SCHEDULED {
+                return Err(io::Error::new(
+                    io::ErrorKind::QuotaExceeded,
+                    format!("{} messages are already scheduled", MAX_SCHEDULED),
+                ));
+            }
+            pending.insert(message);
+        }
+        if let Err(e) = self.save().await {
+            self.inner.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+        self.inner.changed.notify_one();
+        Ok(())
+    }
+
+    /// Pending messages, the next due first
+    pub fn list(&self) -> Vec<ScheduledMessage> {
+        self.inner.pending.lock().unwrap().sorted()
+    }
+
+    pub fn get(&self, id: &str) -> Option<ScheduledMessage> {
+        self.inner.pending.lock().unwrap().messages.get(id).cloned()
+    }
+
+    /// Drop a pending message; `None` when it was already published or never scheduled
+    pub async fn cancel(&self, id: &str) -> io::Result<Option<ScheduledMessage>> {
+        let Some(message) = self.inner.pending.lock().unwrap().remove(id) else {
+            return Ok(None);
+        };
+        if let Err(e) = self.save().await {
+            self.inner.pending.lock().unwrap().insert(message);
+            return Err(e);
+        }
+        Ok(Some(message))
+    }
+
+    /// Write the messages out unless a write since the last change already did. The file
+    /// is written on a blocking thread, to a temporary file first, so a crash mid-write
+    /// leaves the last good list.
+    async fn save(&self) -> io::Result<()> {
+        let Some(path) = self.inner.path.clone() else {
+            return Ok(());
+        };
+        let wanted = self.inner.pending.lock().unwrap().version;
+        let mut written = self.inner.written.lock().await;
+        if *written >= wanted {
+            return Ok(());
+        }
+
+        let (version, messages) = {
+            let pending = self.inner.pending.lock().unwrap();
+            (pending.version, pending.sorted())
+        };
+        tokio::task::spawn_blocking(move || {
+            let content = serde_json::to_vec(&messages)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let staging = path.with_extension("json.tmp");
+            fs::write(&staging, content)?;
+            fs::rename(staging, path)
+        })
+        .await
+        .map_err(io::Error::other)??;
+        *written = version;
+        Ok(())
+    }
+
+    /// Publishes every message that is due, then saves once for all of them
+    async fn run(&self) {
+        let mut failures = 0;
+        loop {
+            // Registered before looking, so a message scheduled in between is not missed
+            let changed = self.inner.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            let now = Utc::now();
+            let due = self.inner.pending.lock().unwrap().first_due(now).cloned();
+            if let Some(message) = due {
+                match self.deliver(&message).await {
+                    Err(BrokerError::Unavailable(e)) => {
+                        failures += 1;
+                        eprintln!("Scheduled message {} delayed: {}", message.id, e);
+                        self.save_delivered().await;
+                        tokio::time::sleep(backoff(failures, RETRY_MIN_BACKOFF, RETRY_MAX_BACKOFF))
+                            .await;
+                        continue;
+                    }
+                    Err(e) => eprintln!("Scheduled message {} dropped: {}", message.id, e),
+                    Ok(ConfirmStatus::Nack | ConfirmStatus::Returned) => eprintln!(
+                        "Scheduled message {} was not routed to {}",
+                        message.id, message.routing_key
+                    ),
+                    Ok(_) => println!(
+                        "⏰ Scheduled message {} published to {}",
+                        message.id, message.routing_key
+                    ),
+                }
+                failures = 0;
+                self.inner.pending.lock().unwrap().remove(&message.id);
+                continue;
+            }
+
+            self.save_delivered().await;
+            let next = self.inner.pending.lock().unwrap().next_due();
+            match next {
+                Some(deliver_at) => {
+                    let wait = (deliver_at - now).to_std().unwrap_or_default();
+                    tokio::select! {
+                        _ = tokio::time::sleep(wait) => {}
+                        _ = changed => {}
+                    }
+                }
+                None => changed.await,
+            }
+        }
+    }
+
+    /// A failed save is retried with the next one; published messages are at worst sent
+    /// again after a restart
+    async fn save_delivered(&self) {
+        if let Err(e) = self.save().await {
+            eprintln!("Failed to save scheduled messages: {}", e);
+        }
+    }
+
+    async fn deliver(&self, message: &ScheduledMessage) -> BrokerResult<ConfirmStatus> {
+        let request = &message.request;
+        let target = PublishTarget {
+            exchange: message.exchange.clone(),
+            routing_key: message.routing_key.clone(),
+            qos: request.qos,
+            retain: request.retain,
+            declare: true,
+        };
+        let (payload, content_type) = request.payload();
+        let outgoing = OutgoingMessage {
+            payload,
+            properties: request.properties(&message.id, content_type),
+        };
+        let publisher = self.transport.broker().open_publisher(target).await?;
+        publisher.send(outgoing).await?.await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::broker::MessageBroker;
+    use crate::config::memory::MemoryBroker;
+    use bytes::Bytes;
+    use serde_json::json;
+
+    fn scheduled(id: &str, delay_ms: i64) -> ScheduledMessage {
+        let now = Utc::now();
+        ScheduledMessage {
+            id: id.to_string(),
+            deliver_at: now + chrono::Duration::milliseconds(delay_ms),
+            created_at: now,
+            exchange: String::new(),
+            routing_key: "reminders".to_string(),
+            request: serde_json::from_value(json!({"topic": "reminders", "message": id, "qos": 1}))
+                .unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pending_messages_survive_a_restart_and_publish_when_due() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scheduled.json");
+        let broker = MemoryBroker::new();
+        let transport = Transport::Memory(broker.clone());
+
+        let scheduler = Scheduler::load(transport.clone(), &path).unwrap();
+        scheduler
+            .schedule(scheduled("later", 3_600_000))
+            .await
+            .unwrap();
+        scheduler.schedule(scheduled("soon", 50)).await.unwrap();
+        scheduler
+            .schedule(scheduled("cancelled", 50))
+            .await
+            .unwrap();
+        assert_eq!(
+            scheduler.cancel("cancelled").await.unwrap().unwrap().id,
+            "cancelled"
+        );
+        assert!(scheduler.cancel("cancelled").await.unwrap().is_none());
+
+        let restarted = Scheduler::load(transport, &path).unwrap();
+        let ids: Vec<_> = restarted.list().into_iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec!["soon", "later"]);
+        restarted.start();
+
+        let mut subscription = broker.subscribe("reminders", 1).await.unwrap();
+        let received = tokio::time::timeout(Duration::from_secs(5), subscription.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.body, Bytes::from("soon"));
+        assert_eq!(received.id, "soon");
+
+        let ids: Vec<_> = restarted.list().into_iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec!["later"]);
+        // The delivered message leaves the file with the next save
+        let mut saved = Scheduler::load(Transport::Memory(broker.clone()), &path).unwrap();
+        for _ in 0..50 {
+            if saved.get("soon").is_none() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            saved = Scheduler::load(Transport::Memory(broker.clone()), &path).unwrap();
+        }
+        assert!(saved.get("soon").is_none());
+        assert!(saved.get("later").is_some());
+        assert_eq!(broker.stats().queues["reminders"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_scheduling_is_capped() {
+        let scheduler = Scheduler::new(Transport::Memory(MemoryBroker::new()), None);
+        let template = scheduled("0", 60_000);
+        {
+            let mut pending = scheduler.inner.pending.lock().unwrap();
+            for i in 0..MAX_SCHEDULED {
+                pending.insert(ScheduledMessage {
+                    id: i.to_string(),
+                    ..template.clone()
+                });
+            }
+        }
+        let err = scheduler
+            .schedule(scheduled("one-too-many", 60_000))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::QuotaExceeded);
+        assert!(scheduler.get("one-too-many").is_none());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::model::schema::{FieldError, RegisteredSchema, SchemaViolation};
+use chrono::Utc;
+use jsonschema::JSONSchema;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// JSON Schemas by queue name or routing key. Publishes to a subject with a schema are
+/// refused unless their payload matches it, and consumers with `validate_schema` set
+/// dead-letter the messages that do not. Saved to a file on every change.
+#[derive(Clone)]
+pub struct SchemaRegistry {
+    inner: Arc<RegistryInner>,
+}
+
+struct RegistryInner {
+    /// Unset keeps the schemas in memory only
+    path: Option<PathBuf>,
+    schemas: RwLock<BTreeMap<String, Entry>>,
+}
+
+struct Entry {
+    registered: RegisteredSchema,
+    compiled: Arc<JSONSchema>,
+}
+
+fn compile(schema: &Value) -> io::Result<JSONSchema> {
+    JSONSchema::compile(schema).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid JSON Schema: {}", e),
+        )
+    })
+}
+
+impl SchemaRegistry {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        SchemaRegistry {
+            inner: Arc::new(RegistryInner {
+                path,
+                schemas: RwLock::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    /// Read the schemas back from `path`; a missing file means none
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let registry = SchemaRegistry::new(Some(path.to_path_buf()));
+        let schemas: Vec<RegisteredSchema> = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let mut entries = registry.inner.schemas.write().unwrap();
+        for registered in schemas {
+            let compiled = compile(&registered.schema).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", registered.subject, e),
+                )
+            })?;
+            entries.insert(
+                registered.subject.clone(),
+                Entry {
+                    registered,
+                    compiled: Arc::new(compiled),
+                },
+            );
+        }
+        drop(entries);
+        Ok(registry)
+    }
+
+    /// Keep the schemas in the file named by `SCHEMA_REGISTRY`, defaulting to
+    /// `schemas.json`. A file that cannot be read leaves the registry in memory only,
+    /// rather than overwriting it.
+    pub fn from_env() -> Self {
+        let path = env::var("SCHEMA_REGISTRY").unwrap_or_else(|_| "schemas.json".to_string());
+        match Self::load(Path::new(&path)) {
+            Ok(registry) => registry,
+            Err(e) => {
+                eprintln!("Failed to load schemas from {}: {}", path, e);
+                SchemaRegistry::new(None)
+            }
+        }
+    }
+
+    /// Add or replace the schema for `subject`. A schema that does not compile is
+    /// refused with `InvalidInput`.
+    pub fn register(&self, subject: &str, schema: Value) -> io::Result<RegisteredSchema> {
+        let compiled = compile(&schema)?;
+        let registered = RegisteredSchema {
+            subject: subject.to_string(),
+            schema,
+            updated_at: Utc::now(),
+        };
+        let mut schemas = self.inner.schemas.write().unwrap();
+        let previous = schemas.insert(
+            subject.to_string(),
+            Entry {
+                registered: registered.clone(),
+                compiled: Arc::new(compiled),
+            },
+        );
+        if let Err(e) = self.persist(&schemas) {
+            match previous {
+                Some(previous) => schemas.insert(subject.to_string(), previous),
+                None => schemas.remove(subject),
+            };
+            return Err(e);
+        }
+        Ok(registered)
+    }
+
+    pub fn list(&self) -> Vec<RegisteredSchema> {
+        self.inner
+            .schemas
+            .read()
+            .unwrap()
+            .values()
+            .map(|entry| entry.registered.clone())
+            .collect()
+    }
+
+    pub fn get(&self, subject: &str) -> Option<RegisteredSchema> {
+        let schemas = self.inner.schemas.read().unwrap();
+        schemas.get(subject).map(|entry| entry.registered.clone())
+    }
+
+    pub fn remove(&self, subject: &str) -> io::Result<Option<RegisteredSchema>> {
+        let mut schemas = self.inner.schemas.write().unwrap();
+        let Some(entry) = schemas.remove(subject) else {
+            return Ok(None);
+        };
+        if let Err(e) = self.persist(&schemas) {
+            schemas.insert(subject.to_string(), entry);
+            return Err(e);
+        }
+        Ok(Some(entry.registered))
+    }
+
+    /// Check a payload against the schema for `subject`; anything goes without one
+    pub fn validate(&self, subject: &str, payload: &[u8]) -> Result<(), SchemaViolation> {
+        let Some(compiled) = self
+            .inner
+            .schemas
+            .read()
+            .unwrap()
+            .get(subject)
+            .map(|entry| entry.compiled.clone())
+        else {
+            return Ok(());
+        };
+        let violation = |errors| SchemaViolation {
+            subject: subject.to_string(),
+            errors,
+        };
+
+        let value: Value = serde_json::from_slice(payload).map_err(|e| {
+            violation(vec![FieldError {
+                path: String::new(),
+                message: format!("is not JSON: {}", e),
+            }])
+        })?;
+        let result = compiled.validate(&value).map_err(|errors| {
+            violation(
+                errors
+                    .map(|error| FieldError {
+                        path: error.instance_path.to_string(),
+                        message: error.to_string(),
+                    })
+                    .collect(),
+            )
+        });
+        result
+    }
+
+    /// Written to a temporary file first, so a crash mid-write leaves the last good list
+    fn persist(&self, schemas: &BTreeMap<String, Entry>) -> io::Result<()> {
+        let Some(path) = &self.inner.path else {
+            return Ok(());
+        };
+        let registered: Vec<_> = schemas.values().map(|entry| &entry.registered).collect();
+        let content = serde_json::to_vec_pretty(&registered)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let staging = path.with_extension("json.tmp");
+        fs::write(&staging, content)?;
+        fs::rename(staging, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn order_schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["id", "amount"],
+            "properties": {
+                "id": {"type": "string"},
+                "amount": {"type": "number", "minimum": 0}
+            }
+        })
+    }
+
+    #[test]
+    fn test_payloads_are_checked_against_their_subject() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schemas.json");
+        let registry = SchemaRegistry::load(&path).unwrap();
+        registry.register("orders", order_schema()).unwrap();
+
+        assert!(registry
+            .validate("orders", br#"{"id": "o-1", "amount": 5}"#)
+            .is_ok());
+        assert!(registry.validate("payments", b"not json").is_ok());
+
+        let violation = registry
+            .validate("orders", br#"{"id": 7, "amount": -1}"#)
+            .unwrap_err();
+        let mut paths: Vec<_> = violation.errors.iter().map(|e| e.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/amount", "/id"]);
+
+        let violation = registry.validate("orders", b"hello").unwrap_err();
+        assert_eq!(violation.errors[0].path, "");
+
+        let error = registry
+            .register("orders", json!({"type": "nonsense"}))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let reloaded = SchemaRegistry::load(&path).unwrap();
+        assert_eq!(reloaded.get("orders").unwrap().schema, order_schema());
+        assert!(reloaded.remove("orders").unwrap().is_some());
+        assert!(SchemaRegistry::load(&path).unwrap().list().is_empty());
+    }
+}
@@ -0,0 +1,47 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Service level objectives checked against every report, on top of the thresholds
+/// declared in the k6 scripts. Expressions use k6 threshold syntax.
+///
+/// ```json
+/// {
+///   "metrics": { "http_req_failed": ["rate<0.05"] },
+///   "endpoints": { "POST /mqtt/pub": { "http_req_duration": ["p(95)<500"] } }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SloConfig {
+    /// Metric name to expressions, evaluated against the whole run
+    #[serde(default)]
+    pub metrics: BTreeMap<String, Vec<String>>,
+    /// `METHOD /path` (a route template such as `GET /user/users/:id` works too) to
+    /// metric name to expressions, evaluated against that endpoint's raw samples
+    #[serde(default)]
+    pub endpoints: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+impl SloConfig {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Reads the file named by `SLO_CONFIG`, defaulting to `slo.json`. A missing file
+    /// means no SLOs.
+    pub fn from_env() -> Self {
+        let path = env::var("SLO_CONFIG").unwrap_or_else(|_| "slo.json".to_string());
+        match Self::load(Path::new(&path)) {
+            Ok(config) => config,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                eprintln!("Failed to load SLO config from {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::config::broker::MessageBroker;
+use crate::config::memory::MemoryBroker;
+use crate::config::mqtt::MqttClient;
+use crate::config::rabbitmq::RabbitMqPool;
+use std::env;
+
+/// The broker protocol behind the `/mqtt` endpoints, chosen with `MESSAGE_TRANSPORT`
+#[derive(Clone)]
+pub enum Transport {
+    /// RabbitMQ over AMQP 0-9-1; topics map to queues
+    Amqp(RabbitMqPool),
+    /// Any MQTT 3.1.1 broker, such as mosquitto
+    Mqtt(MqttClient),
+    /// Queues inside this process, for running without a broker
+    Memory(MemoryBroker),
+}
+
+impl Transport {
+    /// `MESSAGE_TRANSPORT=mqtt` selects MQTT and `memory` the in-memory broker; anything
+    /// else keeps AMQP. The connection is opened in the background.
+    pub fn from_env() -> Self {
+        match env::var("MESSAGE_TRANSPORT")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "mqtt" => Transport::Mqtt(MqttClient::from_env()),
+            "memory" => Transport::Memory(MemoryBroker::new()),
+            other => {
+                if !other.is_empty() && other != "amqp" {
+                    eprintln!("Unknown MESSAGE_TRANSPORT '{}', using amqp", other);
+                }
+                let pool = RabbitMqPool::from_env();
+                pool.start();
+                Transport::Amqp(pool)
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transport::Amqp(_) => "amqp",
+            Transport::Mqtt(_) => "mqtt",
+            Transport::Memory(_) => "memory",
+        }
+    }
+
+    /// Publishing and consuming, whichever protocol this is
+    pub fn broker(&self) -> &dyn MessageBroker {
+        match self {
+            Transport::Amqp(pool) => pool,
+            Transport::Mqtt(client) => client,
+            Transport::Memory(broker) => broker,
+        }
+    }
+}
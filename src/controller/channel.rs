@@ -31,7 +31,7 @@ pub async fn pub_user() {
     for _received_message in rx {
         message_count += 1;
 
-        if message_count % 1_000_000 == 0 {
+        if message_count.is_multiple_of(1_000_000) {
             println!("[Consumer] received total {} messages", message_count);
         }
     }
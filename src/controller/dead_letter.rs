@@ -0,0 +1,208 @@
+use crate::config::dead_letter::{replay_properties, republish};
+use crate::config::transport::Transport;
+use crate::controller::response::{error_response, pool_error_response};
+use crate::controller::topology::{
+    broker_error_response, get_messages, inspect_queue, needs_amqp, requeue_all,
+};
+use crate::model::consumer::ConsumedMessage;
+use crate::model::dead_letter::{
+    dead_letter_queue, ReplayQuery, FAILURE_REASON_HEADER, ORIGINAL_QUEUE_HEADER,
+};
+use crate::model::topology::{validate_queue_name, PeekQuery};
+use axum::{
+    extract::{rejection::QueryRejection, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use lapin::options::{BasicAckOptions, BasicGetOptions, QueuePurgeOptions};
+use serde_json::{json, Value};
+
+// Look at the first messages of a queue's dead-letter queue, leaving them in place
+pub async fn peek_dead_letters(
+    State(transport): State<Transport>,
+    Path(queue): Path<String>,
+    query: Result<Query<PeekQuery>, QueryRejection>,
+) -> Response {
+    let query = match query {
+        Ok(Query(query)) => query,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = query
+        .validate()
+        .and_then(|()| validate_queue_name("queue", &queue))
+    {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Dead-letter queues");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    let dlq = dead_letter_queue(&queue);
+    let count = match inspect_queue(&channel, &dlq).await {
+        Ok(queue) => queue.message_count(),
+        Err(e) => {
+            let context = format!("Failed to inspect dead-letter queue '{}'", dlq);
+            return broker_error_response(&context, e);
+        }
+    };
+    let deliveries = match get_messages(&channel, &dlq, query.n).await {
+        Ok(deliveries) => deliveries,
+        Err(e) => {
+            let context = format!("Failed to read dead-letter queue '{}'", dlq);
+            return broker_error_response(&context, e);
+        }
+    };
+    let messages: Vec<Value> = deliveries
+        .iter()
+        .map(|delivery| {
+            let message = ConsumedMessage::from_delivery(&dlq, delivery);
+            let mut value = message.to_json();
+            value["failure_reason"] = json!(message.headers.get(FAILURE_REASON_HEADER));
+            value
+        })
+        .collect();
+    requeue_all(deliveries).await;
+
+    Json(json!({
+        "success": true,
+        "queue": queue,
+        "dead_letter_queue": dlq,
+        "message_count": count,
+        "messages": messages
+    }))
+    .into_response()
+}
+
+// Send dead-lettered messages back to the queue they failed on, oldest first
+pub async fn replay_dead_letters(
+    State(transport): State<Transport>,
+    Path(queue): Path<String>,
+    query: Result<Query<ReplayQuery>, QueryRejection>,
+) -> Response {
+    let query = match query {
+        Ok(Query(query)) => query,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = query
+        .validate()
+        .and_then(|()| validate_queue_name("queue", &queue))
+    {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Dead-letter queues");
+    };
+    // Replays are confirmed before the dead-lettered copy is acked
+    let channel = match pool.acquire(true).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    let dlq = dead_letter_queue(&queue);
+    if let Err(e) = inspect_queue(&channel, &dlq).await {
+        let context = format!("Failed to inspect dead-letter queue '{}'", dlq);
+        return broker_error_response(&context, e);
+    }
+
+    let mut replayed = 0;
+    let mut error = None;
+    while replayed < query.n && error.is_none() {
+        let delivery = match channel.basic_get(&dlq, BasicGetOptions::default()).await {
+            Ok(Some(message)) => message.delivery,
+            Ok(None) => break,
+            Err(e) => {
+                error = Some(format!("Failed to read dead-letter queue '{}': {}", dlq, e));
+                break;
+            }
+        };
+
+        let message = ConsumedMessage::from_delivery(&dlq, &delivery);
+        let target = message
+            .headers
+            .get(ORIGINAL_QUEUE_HEADER)
+            .and_then(Value::as_str)
+            .unwrap_or(&queue)
+            .to_string();
+        let properties = replay_properties(&delivery.properties);
+        let result = match republish(&channel, "", &target, &delivery.data, properties).await {
+            Ok(()) => delivery
+                .acker
+                .ack(BasicAckOptions::default())
+                .await
+                .map_err(|e| format!("Failed to acknowledge replayed message: {}", e)),
+            Err(e) => {
+                requeue_all(vec![delivery]).await;
+                Err(e)
+            }
+        };
+        match result {
+            Ok(()) => replayed += 1,
+            Err(e) => error = Some(e),
+        }
+    }
+    println!("♻️  Replayed {} messages from {}", replayed, dlq);
+
+    let remaining = inspect_queue(&channel, &dlq)
+        .await
+        .ok()
+        .map(|queue| queue.message_count());
+    let status = if error.is_some() {
+        StatusCode::BAD_GATEWAY
+    } else {
+        StatusCode::OK
+    };
+    (
+        status,
+        Json(json!({
+            "success": error.is_none(),
+            "queue": queue,
+            "dead_letter_queue": dlq,
+            "replayed": replayed,
+            "remaining": remaining,
+            "error": error
+        })),
+    )
+        .into_response()
+}
+
+// Drop every message in a queue's dead-letter queue
+pub async fn purge_dead_letters(
+    State(transport): State<Transport>,
+    Path(queue): Path<String>,
+) -> Response {
+    if let Err(e) = validate_queue_name("queue", &queue) {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Dead-letter queues");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    let dlq = dead_letter_queue(&queue);
+    let purged = match channel
+        .queue_purge(&dlq, QueuePurgeOptions::default())
+        .await
+    {
+        Ok(purged) => purged,
+        Err(e) => {
+            let context = format!("Failed to purge dead-letter queue '{}'", dlq);
+            return broker_error_response(&context, e);
+        }
+    };
+    println!("🗑️  Purged {} messages from {}", purged, dlq);
+
+    Json(json!({
+        "success": true,
+        "queue": queue,
+        "dead_letter_queue": dlq,
+        "purged": purged
+    }))
+    .into_response()
+}
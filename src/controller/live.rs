@@ -0,0 +1,499 @@
+use crate::config::report::{
+    is_valid_test_type, ReportConfig, PERFORMANCE_DATA_FILE, RAW_RESULTS_FILE,
+};
+use crate::controller::response::error_response;
+use crate::model::k6::{K6Aggregates, K6Aggregator, K6Reader, K6Record};
+use crate::model::live::{summary_export, LiveRuns, LiveSnapshot, LiveStatus};
+use crate::model::report::Report;
+use askama::Template;
+use axum::{
+    extract::{BodyStream, Path, Query, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
+};
+use chrono::Utc;
+use futures_lite::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io;
+use std::path::Path as FsPath;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+const DEFAULT_LIVE_TEST_TYPE: &str = "live";
+/// How often viewers get a fresh snapshot while a run is streaming in
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+/// k6 records are a few hundred bytes, so a longer line is not one
+const MAX_LINE_BYTES: usize = 1024 * 1024;
+/// Runs streaming in more than this are refused
+const MAX_LIVE_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+#[derive(Clone)]
+pub struct LiveState {
+    pub config: ReportConfig,
+    pub runs: LiveRuns,
+    pub limits: LiveLimits,
+}
+
+/// Size limits for runs streaming in; exceeding either answers `413`
+#[derive(Debug, Clone, Copy)]
+pub struct LiveLimits {
+    pub max_line_bytes: usize,
+    pub max_run_bytes: u64,
+}
+
+impl Default for LiveLimits {
+    fn default() -> Self {
+        Self {
+            max_line_bytes: MAX_LINE_BYTES,
+            max_run_bytes: MAX_LIVE_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LiveQuery {
+    pub test_type: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "live.html")]
+pub struct LiveTemplate {
+    runs: Vec<LiveSnapshot>,
+    run_id: Option<String>,
+}
+
+// Ingest a running k6 test, e.g. `k6 run -q --out json=- script.js | curl -T - .../api/live`
+pub async fn ingest(
+    State(state): State<LiveState>,
+    Query(query): Query<LiveQuery>,
+    body: BodyStream,
+) -> Response {
+    let test_type = query
+        .test_type
+        .unwrap_or_else(|| DEFAULT_LIVE_TEST_TYPE.to_string());
+    if !is_valid_test_type(&test_type) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid test type '{}'", test_type),
+        );
+    }
+
+    // Run detached so that whatever arrived is still saved if the client goes away
+    match tokio::spawn(run_ingest(state, test_type, body)).await {
+        Ok(response) => response,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn run_ingest(state: LiveState, test_type: String, mut body: BodyStream) -> Response {
+    let staging = match state.config.create_staging_dir() {
+        Ok(staging) => staging,
+        Err(e) => {
+            eprintln!("Error creating live run directory: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let id = Uuid::new_v4().to_string();
+    let sender = state.runs.insert(LiveSnapshot {
+        id: id.clone(),
+        test_type: test_type.clone(),
+        started_at: Utc::now(),
+        status: LiveStatus::Running,
+        records: 0,
+        aggregates: K6Aggregates::default(),
+        report_id: None,
+        error: None,
+    });
+
+    let result = match receive_records(&staging, &mut body, state.limits, &sender).await {
+        Ok(records) if records > 0 => match final_aggregates(&staging).await {
+            Ok(aggregates) => {
+                sender.send_modify(|snapshot| {
+                    snapshot.records = records;
+                    snapshot.aggregates = aggregates.clone();
+                });
+                save_run(&state.config, staging, test_type, &aggregates).await
+            }
+            Err(e) => {
+                eprintln!("Error aggregating live run {}: {}", id, e);
+                let _ = tokio::fs::remove_dir_all(&staging).await;
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to store live run".to_string(),
+                ))
+            }
+        },
+        Ok(_) => {
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+            Err((
+                StatusCode::BAD_REQUEST,
+                "No k6 records received".to_string(),
+            ))
+        }
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+            Err((StatusCode::PAYLOAD_TOO_LARGE, e.to_string()))
+        }
+        Err(e) => {
+            eprintln!("Error receiving live run {}: {}", id, e);
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to store live run".to_string(),
+            ))
+        }
+    };
+
+    // Viewers get the final state before the run disappears from the list
+    let response = match result {
+        Ok(report) => {
+            sender.send_modify(|snapshot| {
+                snapshot.status = LiveStatus::Saved;
+                snapshot.report_id = Some(report.id.clone());
+            });
+            (
+                StatusCode::CREATED,
+                Json(json!({
+                    "success": true,
+                    "report": report
+                })),
+            )
+                .into_response()
+        }
+        Err((status, message)) => {
+            sender.send_modify(|snapshot| {
+                snapshot.status = LiveStatus::Failed;
+                snapshot.error = Some(message.clone());
+            });
+            error_response(status, message)
+        }
+    };
+    state.runs.remove(&id);
+    response
+}
+
+/// Write k6 records to the raw results file as they arrive and keep rolling aggregates.
+/// Lines that are not k6 records, such as a banner on stdout, are skipped. A line or a run
+/// over the size limits fails with `InvalidData`.
+async fn receive_records(
+    staging: &FsPath,
+    body: &mut BodyStream,
+    limits: LiveLimits,
+    sender: &watch::Sender<LiveSnapshot>,
+) -> io::Result<u64> {
+    let mut raw = BufWriter::new(tokio::fs::File::create(staging.join(RAW_RESULTS_FILE)).await?);
+    let mut aggregator = K6Aggregator::streaming();
+    let mut records = 0;
+    let mut received: u64 = 0;
+    let mut pending: Vec<u8> = Vec::new();
+    // Bytes of `pending` already searched for a newline
+    let mut scanned = 0;
+    let mut last_snapshot = Instant::now();
+
+    loop {
+        match body.next().await {
+            Some(Ok(chunk)) => {
+                received += chunk.len() as u64;
+                if received > limits.max_run_bytes {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Live runs are limited to {} bytes", limits.max_run_bytes),
+                    ));
+                }
+                pending.extend_from_slice(&chunk);
+            }
+            Some(Err(e)) => {
+                // k6 was interrupted or the client went away; keep what we have
+                eprintln!("Live run ended early: {}", e);
+                break;
+            }
+            None => break,
+        }
+
+        let mut start = 0;
+        while let Some(offset) = pending[scanned..].iter().position(|byte| *byte == b'\n') {
+            let end = scanned + offset + 1;
+            let line = &pending[start..end];
+            if line.len() > limits.max_line_bytes {
+                return Err(line_too_long(limits));
+            }
+            if let Some(record) = parse_record(line) {
+                raw.write_all(line).await?;
+                aggregator.push(record);
+                records += 1;
+            }
+            start = end;
+            scanned = end;
+        }
+        pending.drain(..start);
+        scanned = pending.len();
+        if pending.len() > limits.max_line_bytes {
+            return Err(line_too_long(limits));
+        }
+
+        if last_snapshot.elapsed() >= SNAPSHOT_INTERVAL {
+            let aggregates = aggregator.snapshot();
+            sender.send_modify(|snapshot| {
+                snapshot.records = records;
+                snapshot.aggregates = aggregates;
+            });
+            last_snapshot = Instant::now();
+        }
+    }
+
+    // The last line may not end in a newline
+    if let Some(record) = parse_record(&pending) {
+        raw.write_all(&pending).await?;
+        raw.write_all(b"\n").await?;
+        aggregator.push(record);
+        records += 1;
+    }
+    raw.flush().await?;
+
+    Ok(records)
+}
+
+fn line_too_long(limits: LiveLimits) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Lines are limited to {} bytes", limits.max_line_bytes),
+    )
+}
+
+/// Exact aggregates for the stored report, read back from the raw results on a blocking
+/// thread; the rolling ones only approximate the percentiles
+async fn final_aggregates(staging: &FsPath) -> io::Result<K6Aggregates> {
+    let raw_results = staging.join(RAW_RESULTS_FILE);
+    tokio::task::spawn_blocking(move || {
+        K6Aggregator::aggregate(K6Reader::open(&raw_results)?)
+            .map_err(|e| io::Error::other(e.to_string()))
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+fn parse_record(line: &[u8]) -> Option<K6Record> {
+    let line = line.trim_ascii();
+    if line.is_empty() {
+        return None;
+    }
+    serde_json::from_slice(line).ok()
+}
+
+/// Store the run as a regular report, with a summary export built from its aggregates
+async fn save_run(
+    config: &ReportConfig,
+    staging: std::path::PathBuf,
+    test_type: String,
+    aggregates: &K6Aggregates,
+) -> Result<Report, (StatusCode, String)> {
+    let summary = summary_export(aggregates);
+    let config = config.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let summary = serde_json::to_vec_pretty(&summary)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Err(e) = std::fs::write(staging.join(PERFORMANCE_DATA_FILE), summary) {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        config.finalize_upload(&staging, &test_type, "live")
+    })
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to store live run".to_string(),
+        )
+    })?;
+
+    match result {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            eprintln!("Error saving live run: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to store live run".to_string(),
+            ))
+        }
+    }
+}
+
+// List runs that are streaming in right now
+pub async fn list_runs(State(state): State<LiveState>) -> Json<Value> {
+    Json(json!({
+        "success": true,
+        "runs": state.runs.list()
+    }))
+}
+
+// Stream snapshots of a live run as server-sent events
+pub async fn run_events(
+    State(state): State<LiveState>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, serde_json::Error>>>, StatusCode> {
+    let receiver = state.runs.subscribe(&id).ok_or(StatusCode::NOT_FOUND)?;
+
+    // Send the current snapshot right away, then one per change until the run ends
+    let stream =
+        futures_lite::stream::unfold((receiver, true), |(mut receiver, first)| async move {
+            if !first && receiver.changed().await.is_err() {
+                return None;
+            }
+            let event = Event::default()
+                .event("snapshot")
+                .json_data(&*receiver.borrow_and_update());
+            Some((event, (receiver, false)))
+        });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+// Live view listing active runs
+pub async fn live_index(State(state): State<LiveState>) -> LiveTemplate {
+    LiveTemplate {
+        runs: state.runs.list(),
+        run_id: None,
+    }
+}
+
+// Live view of a single run
+pub async fn live_run(
+    State(state): State<LiveState>,
+    Path(id): Path<String>,
+) -> Result<LiveTemplate, StatusCode> {
+    state.runs.subscribe(&id).ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(LiveTemplate {
+        runs: state.runs.list(),
+        run_id: Some(id),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::put, Router};
+    use tower::ServiceExt;
+
+    const RECORD: &str = r#"{"metric":"http_req_duration","type":"Point","data":{"time":"2025-06-29T21:43:22+07:00","value":80,"tags":{"method":"GET","status":"200","url":"http://127.0.0.1:8000/user/users/7"}}}"#;
+
+    fn router(dir: &FsPath, limits: LiveLimits) -> Router {
+        let state = LiveState {
+            config: ReportConfig::new(dir),
+            runs: LiveRuns::new(),
+            limits,
+        };
+        Router::new()
+            .route("/api/live", put(ingest))
+            .with_state(state)
+    }
+
+    async fn send(router: Router, chunks: Vec<io::Result<String>>) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method("PUT")
+            .uri("/api/live?test_type=smoke")
+            .body(Body::wrap_stream(futures_lite::stream::iter(chunks)))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn stored_files(dir: &FsPath) -> Vec<String> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_records_split_across_chunks_are_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let (head, tail) = RECORD.split_at(60);
+        let chunks = vec![
+            Ok(format!("k6 banner\n{}", head)),
+            Ok(format!("{}\n{}\n", tail, RECORD)),
+            // The last record has no trailing newline
+            Ok(RECORD.to_string()),
+        ];
+
+        let (status, body) = send(router(dir.path(), LiveLimits::default()), chunks).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let id = body["report"]["id"].as_str().unwrap();
+        assert!(id.starts_with("smoke_"));
+        let report_dir = dir.path().join(id);
+        let raw = std::fs::read_to_string(report_dir.join(RAW_RESULTS_FILE)).unwrap();
+        assert_eq!(raw.lines().collect::<Vec<_>>(), vec![RECORD; 3]);
+        assert!(report_dir.join(PERFORMANCE_DATA_FILE).is_file());
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_run_keeps_what_arrived() {
+        let dir = tempfile::tempdir().unwrap();
+        let chunks = vec![
+            Ok(format!("{}\n", RECORD)),
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "k6 interrupted")),
+        ];
+
+        let (status, body) = send(router(dir.path(), LiveLimits::default()), chunks).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = body["report"]["id"].as_str().unwrap();
+        let raw = std::fs::read_to_string(dir.path().join(id).join(RAW_RESULTS_FILE)).unwrap();
+        assert_eq!(raw.lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_oversize_lines_are_refused() {
+        let limits = LiveLimits {
+            max_line_bytes: RECORD.len() + 1,
+            ..LiveLimits::default()
+        };
+        let long_line = format!("{} {}\n", RECORD, " ".repeat(RECORD.len()));
+
+        // Whether the line is still pending or already complete within one chunk
+        for chunks in [
+            vec![
+                Ok(format!("{}\n", RECORD)),
+                Ok(long_line.trim_end_matches('\n').to_string()),
+            ],
+            vec![Ok(format!("{}\n{}", RECORD, long_line))],
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let (status, body) = send(router(dir.path(), limits), chunks).await;
+            assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+            assert!(body["error"]
+                .as_str()
+                .unwrap()
+                .contains("Lines are limited"));
+            assert!(stored_files(dir.path()).is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversize_runs_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = LiveLimits {
+            max_run_bytes: 2 * (RECORD.len() as u64 + 1),
+            ..LiveLimits::default()
+        };
+        let chunks = (0..3).map(|_| Ok(format!("{}\n", RECORD))).collect();
+
+        let (status, body) = send(router(dir.path(), limits), chunks).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .contains("Live runs are limited"));
+        assert!(stored_files(dir.path()).is_empty());
+    }
+}
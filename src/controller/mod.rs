@@ -1,4 +1,13 @@
 pub mod channel;
+pub mod dead_letter;
 pub mod dynamodb_controller;
+pub mod live;
 pub mod mqtt;
+pub mod report;
+pub mod report_api;
+pub mod response;
+pub mod rpc;
+pub mod schema;
+pub mod stream;
+pub mod topology;
 pub mod user;
@@ -0,0 +1,212 @@
+use crate::config::report::ReportConfig;
+use crate::controller::report_api::{load_comparison, load_trends, CompareQuery, TrendsQuery};
+use crate::model::comparison::Comparison;
+use crate::model::k6::EndpointSummary;
+use crate::model::report::{CoverageData, Report};
+use crate::model::threshold::ThresholdReport;
+use crate::model::trend::Trends;
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use std::collections::BTreeMap;
+use std::io;
+
+#[derive(Template)]
+#[template(path = "index.html")]
+pub struct IndexTemplate {
+    title: String,
+    description: String,
+}
+
+#[derive(Template)]
+#[template(path = "report_list.html")]
+pub struct ReportListTemplate {
+    reports: Vec<Report>,
+    thresholds: BTreeMap<String, ThresholdReport>,
+}
+
+#[derive(Template)]
+#[template(path = "report_detail.html")]
+pub struct ReportDetailTemplate {
+    report: Report,
+    endpoints: BTreeMap<String, EndpointSummary>,
+    thresholds: ThresholdReport,
+}
+
+#[derive(Template)]
+#[template(path = "coverage.html")]
+pub struct CoverageTemplate {
+    title: String,
+    report_id: String,
+    coverage: CoverageData,
+}
+
+#[derive(Template)]
+#[template(path = "raw_data.html")]
+pub struct RawDataTemplate {
+    title: String,
+    report_id: String,
+    raw_data: String,
+}
+
+#[derive(Template)]
+#[template(path = "compare.html")]
+pub struct CompareTemplate {
+    comparison: Comparison,
+}
+
+#[derive(Template)]
+#[template(path = "trends.html")]
+pub struct TrendsTemplate {
+    trends: Trends,
+    test_type: Option<String>,
+}
+
+pub fn error_status(e: io::Error) -> StatusCode {
+    if e.kind() == io::ErrorKind::NotFound {
+        StatusCode::NOT_FOUND
+    } else {
+        eprintln!("Error reading report: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// Run `f` on the blocking pool. Reading reports can mean parsing raw k6 streams of several
+/// megabytes each, so handlers never touch the reports directory on the async runtime.
+pub async fn blocking<T, F>(config: ReportConfig, f: F) -> Result<T, StatusCode>
+where
+    F: FnOnce(ReportConfig) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(move || f(config))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Home page
+pub async fn index() -> IndexTemplate {
+    IndexTemplate {
+        title: "K6 Performance Reports".to_string(),
+        description: "Browse k6 load test runs, endpoint coverage and raw metrics for this API."
+            .to_string(),
+    }
+}
+
+// List all reports
+pub async fn list_reports(
+    State(config): State<ReportConfig>,
+) -> Result<ReportListTemplate, StatusCode> {
+    let (reports, thresholds) = blocking(config, |config| {
+        let reports = config.list_reports()?;
+        let thresholds = reports
+            .iter()
+            .filter_map(|report| {
+                let thresholds = config.get_thresholds(&report.id).ok()?;
+                Some((report.id.clone(), thresholds))
+            })
+            .collect();
+        Ok((reports, thresholds))
+    })
+    .await?
+    .map_err(error_status)?;
+
+    Ok(ReportListTemplate {
+        reports,
+        thresholds,
+    })
+}
+
+// Show a single report
+pub async fn report_detail(
+    State(config): State<ReportConfig>,
+    Path(id): Path<String>,
+) -> Result<ReportDetailTemplate, StatusCode> {
+    let (mut report, aggregates, thresholds) = blocking(config, move |config| {
+        let report = config.get_report(&id)?;
+        let aggregates = config.get_aggregates(&report.id).unwrap_or_else(|e| {
+            eprintln!("Error parsing raw results for {}: {}", report.id, e);
+            None
+        });
+        let thresholds = config.get_thresholds(&report.id)?;
+        Ok((report, aggregates, thresholds))
+    })
+    .await?
+    .map_err(error_status)?;
+
+    let endpoints = match aggregates {
+        Some(aggregates) => {
+            if report.duration == 0 {
+                report.duration = aggregates.duration_ms();
+            }
+            aggregates.endpoints
+        }
+        None => BTreeMap::new(),
+    };
+
+    Ok(ReportDetailTemplate {
+        report,
+        endpoints,
+        thresholds,
+    })
+}
+
+// Show endpoint coverage for a report
+pub async fn report_coverage(
+    State(config): State<ReportConfig>,
+    Path(id): Path<String>,
+) -> Result<CoverageTemplate, StatusCode> {
+    let (report_id, coverage) = blocking(config, move |config| {
+        let (report_id, _) = config.resolve_report(&id)?;
+        let coverage = config.get_coverage(&report_id)?;
+        Ok((report_id, coverage))
+    })
+    .await?
+    .map_err(error_status)?;
+
+    Ok(CoverageTemplate {
+        title: format!("Coverage - {}", report_id),
+        report_id,
+        coverage,
+    })
+}
+
+// Show the raw performance data for a report
+pub async fn report_raw(
+    State(config): State<ReportConfig>,
+    Path(id): Path<String>,
+) -> Result<RawDataTemplate, StatusCode> {
+    let (report_id, raw_data) = blocking(config, move |config| {
+        let (report_id, _) = config.resolve_report(&id)?;
+        let raw_data = config.get_raw_data(&report_id)?;
+        Ok((report_id, raw_data))
+    })
+    .await?
+    .map_err(error_status)?;
+
+    Ok(RawDataTemplate {
+        title: format!("Raw Data - {}", report_id),
+        report_id,
+        raw_data,
+    })
+}
+
+// Compare two reports side by side
+pub async fn compare_reports(
+    State(config): State<ReportConfig>,
+    Query(query): Query<CompareQuery>,
+) -> Result<CompareTemplate, StatusCode> {
+    let comparison = load_comparison(config, query).await?;
+    Ok(CompareTemplate { comparison })
+}
+
+// Show metrics over time across all runs
+pub async fn report_trends(
+    State(config): State<ReportConfig>,
+    Query(query): Query<TrendsQuery>,
+) -> Result<TrendsTemplate, StatusCode> {
+    let test_type = query.test_type.clone();
+    let trends = load_trends(config, query).await?;
+    Ok(TrendsTemplate { trends, test_type })
+}
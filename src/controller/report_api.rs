@@ -0,0 +1,528 @@
+use crate::config::report::{ReportConfig, COVERAGE_FILE, PERFORMANCE_DATA_FILE, RAW_RESULTS_FILE};
+use crate::controller::report::{blocking, error_status};
+use crate::controller::response::error_response;
+use crate::model::comparison::{Comparison, RegressionThresholds};
+use crate::model::report::Report;
+use crate::model::trend::Trends;
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io;
+use tokio::io::AsyncWriteExt;
+
+const DEFAULT_PER_PAGE: usize = 20;
+const MAX_PER_PAGE: usize = 100;
+const DEFAULT_UPLOAD_TEST_TYPE: &str = "upload";
+
+/// Largest accepted upload; raw k6 streams for long runs get big
+pub const MAX_UPLOAD_BYTES: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportSort {
+    #[default]
+    Date,
+    Duration,
+    Coverage,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListReportsQuery {
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    #[serde(default)]
+    pub sort: ReportSort,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub test_type: Option<String>,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompareQuery {
+    pub base: String,
+    pub head: String,
+    pub latency_pct: Option<f64>,
+    pub throughput_pct: Option<f64>,
+    pub error_rate_pct: Option<f64>,
+    pub check_pass_pct: Option<f64>,
+}
+
+impl CompareQuery {
+    pub fn thresholds(&self) -> RegressionThresholds {
+        let defaults = RegressionThresholds::default();
+        RegressionThresholds {
+            latency_pct: self.latency_pct.unwrap_or(defaults.latency_pct),
+            throughput_pct: self.throughput_pct.unwrap_or(defaults.throughput_pct),
+            error_rate_pct: self.error_rate_pct.unwrap_or(defaults.error_rate_pct),
+            check_pass_pct: self.check_pass_pct.unwrap_or(defaults.check_pass_pct),
+        }
+    }
+}
+
+pub async fn load_comparison(
+    config: ReportConfig,
+    query: CompareQuery,
+) -> Result<Comparison, StatusCode> {
+    blocking(config, move |config| {
+        config.compare_reports(&query.base, &query.head, query.thresholds())
+    })
+    .await?
+    .map_err(error_status)
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TrendsQuery {
+    pub test_type: Option<String>,
+    pub endpoint: Option<String>,
+}
+
+pub async fn load_trends(config: ReportConfig, query: TrendsQuery) -> Result<Trends, StatusCode> {
+    blocking(config, move |config| {
+        config.get_trends(query.test_type.as_deref(), query.endpoint.as_deref())
+    })
+    .await?
+    .map_err(error_status)
+}
+
+/// Filter and sort reports in place according to the query
+fn apply_query(reports: &mut Vec<Report>, query: &ListReportsQuery) {
+    reports.retain(|report| {
+        query
+            .test_type
+            .as_ref()
+            .is_none_or(|test_type| &report.test_type == test_type)
+            && query
+                .status
+                .as_ref()
+                .is_none_or(|status| &report.status == status)
+    });
+
+    reports.sort_by(|a, b| {
+        let ordering = match query.sort {
+            ReportSort::Date => a.timestamp.cmp(&b.timestamp),
+            ReportSort::Duration => a.duration.cmp(&b.duration),
+            ReportSort::Coverage => a.coverage_percentage.total_cmp(&b.coverage_percentage),
+        }
+        .then_with(|| a.id.cmp(&b.id));
+
+        match query.order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+}
+
+// List reports with pagination, sorting and filtering
+pub async fn list_reports(
+    State(config): State<ReportConfig>,
+    Query(query): Query<ListReportsQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let mut reports = blocking(config, |config| config.list_reports())
+        .await?
+        .map_err(error_status)?;
+    apply_query(&mut reports, &query);
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let total = reports.len();
+    let reports: Vec<Report> = reports
+        .into_iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "reports": reports,
+        "page": page,
+        "per_page": per_page,
+        "total": total,
+        "total_pages": total.div_ceil(per_page)
+    })))
+}
+
+// Get a single report with its summary, coverage and thresholds
+pub async fn get_report(
+    State(config): State<ReportConfig>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    let (report, summary, coverage, thresholds) = blocking(config, move |config| {
+        let report = config.get_report(&id)?;
+        let summary = config.get_performance_summary(&report.id).ok();
+        let coverage = config.get_coverage(&report.id).ok();
+        let thresholds = config.get_thresholds(&report.id)?;
+        Ok((report, summary, coverage, thresholds))
+    })
+    .await?
+    .map_err(error_status)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "report": report,
+        "summary": summary,
+        "coverage": coverage,
+        "thresholds": thresholds
+    })))
+}
+
+// Evaluate a report's k6 thresholds and SLOs; CI can gate on `passed`
+pub async fn get_thresholds(
+    State(config): State<ReportConfig>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    let (report_id, thresholds) = blocking(config, move |config| {
+        let (report_id, _) = config.resolve_report(&id)?;
+        let thresholds = config.get_thresholds(&report_id)?;
+        Ok((report_id, thresholds))
+    })
+    .await?
+    .map_err(error_status)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "report_id": report_id,
+        "passed": thresholds.is_passing(),
+        "thresholds": thresholds
+    })))
+}
+
+// Compare two reports and flag regressions
+pub async fn compare_reports(
+    State(config): State<ReportConfig>,
+    Query(query): Query<CompareQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let comparison = load_comparison(config, query).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "comparison": comparison
+    })))
+}
+
+// Metrics over time for every stored run, grouped by test type
+pub async fn trends(
+    State(config): State<ReportConfig>,
+    Query(query): Query<TrendsQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let trends = load_trends(config, query).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "trends": trends
+    })))
+}
+
+// List the routes the server serves, which coverage is measured against
+pub async fn list_routes(State(config): State<ReportConfig>) -> Json<Value> {
+    Json(json!({
+        "success": true,
+        "routes": config.routes.as_slice()
+    }))
+}
+
+/// Map errors from removing or pinning a report to a JSON error response
+fn removal_error(e: io::Error) -> Response {
+    match e.kind() {
+        io::ErrorKind::NotFound => error_response(StatusCode::NOT_FOUND, e),
+        io::ErrorKind::InvalidInput => error_response(StatusCode::BAD_REQUEST, e),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::AlreadyExists => {
+            error_response(StatusCode::CONFLICT, e)
+        }
+        _ => {
+            eprintln!("Error updating report: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update report")
+        }
+    }
+}
+
+fn internal_error(e: io::Error) -> Response {
+    eprintln!("Error storing uploaded report: {}", e);
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Failed to store uploaded report",
+    )
+}
+
+/// Stream the multipart fields into the staging directory and return the test type
+async fn receive_upload(
+    staging: &std::path::Path,
+    multipart: &mut Multipart,
+) -> Result<String, Response> {
+    let bad_request =
+        |e: axum::extract::multipart::MultipartError| error_response(StatusCode::BAD_REQUEST, e);
+    let mut test_type = DEFAULT_UPLOAD_TEST_TYPE.to_string();
+
+    while let Some(mut field) = multipart.next_field().await.map_err(bad_request)? {
+        let file_name = match field.name() {
+            Some("summary") => PERFORMANCE_DATA_FILE,
+            Some("raw") => RAW_RESULTS_FILE,
+            Some("coverage") => COVERAGE_FILE,
+            Some("test_type") => {
+                test_type = field.text().await.map_err(bad_request)?.trim().to_string();
+                continue;
+            }
+            other => {
+                return Err(error_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Unexpected field '{}'", other.unwrap_or_default()),
+                ))
+            }
+        };
+
+        let mut file = tokio::fs::File::create(staging.join(file_name))
+            .await
+            .map_err(internal_error)?;
+        while let Some(chunk) = field.chunk().await.map_err(bad_request)? {
+            file.write_all(&chunk).await.map_err(internal_error)?;
+        }
+        file.flush().await.map_err(internal_error)?;
+    }
+
+    Ok(test_type)
+}
+
+// Upload a k6 run as a new report
+pub async fn upload_report(
+    State(config): State<ReportConfig>,
+    mut multipart: Multipart,
+) -> Response {
+    let staging = match config.create_staging_dir() {
+        Ok(staging) => staging,
+        Err(e) => return internal_error(e),
+    };
+
+    let test_type = match receive_upload(&staging, &mut multipart).await {
+        Ok(test_type) => test_type,
+        Err(response) => {
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+            return response;
+        }
+    };
+
+    let result = blocking(config, move |config| {
+        config.finalize_upload(&staging, &test_type, "upload")
+    })
+    .await;
+
+    match result {
+        Ok(Ok(report)) => (
+            StatusCode::CREATED,
+            Json(json!({
+                "success": true,
+                "report": report
+            })),
+        )
+            .into_response(),
+        Ok(Err(e)) if e.kind() == io::ErrorKind::InvalidInput => {
+            error_response(StatusCode::BAD_REQUEST, e)
+        }
+        Ok(Err(e)) => internal_error(e),
+        Err(status) => status.into_response(),
+    }
+}
+
+// Delete a report; baselines are refused with 409
+pub async fn delete_report(State(config): State<ReportConfig>, Path(id): Path<String>) -> Response {
+    let report_id = id.clone();
+    match blocking(config, move |config| config.delete_report(&report_id)).await {
+        Ok(Ok(())) => Json(json!({
+            "success": true,
+            "deleted": id
+        }))
+        .into_response(),
+        Ok(Err(e)) => removal_error(e),
+        Err(status) => status.into_response(),
+    }
+}
+
+// Compress a report to a tarball under the archive directory and remove it from the list
+pub async fn archive_report(
+    State(config): State<ReportConfig>,
+    Path(id): Path<String>,
+) -> Response {
+    let report_id = id.clone();
+    match blocking(config, move |config| config.archive_report(&report_id)).await {
+        Ok(Ok(archive)) => (
+            StatusCode::CREATED,
+            Json(json!({
+                "success": true,
+                "report_id": id,
+                "archive": archive.display().to_string()
+            })),
+        )
+            .into_response(),
+        Ok(Err(e)) => removal_error(e),
+        Err(status) => status.into_response(),
+    }
+}
+
+// Pin a report as a baseline so retention never removes it
+pub async fn pin_baseline(State(config): State<ReportConfig>, Path(id): Path<String>) -> Response {
+    baseline_response(blocking(config, move |config| config.set_baseline(&id, true)).await)
+}
+
+// Unpin a baseline
+pub async fn unpin_baseline(
+    State(config): State<ReportConfig>,
+    Path(id): Path<String>,
+) -> Response {
+    baseline_response(blocking(config, move |config| config.set_baseline(&id, false)).await)
+}
+
+fn baseline_response(result: Result<io::Result<Report>, StatusCode>) -> Response {
+    match result {
+        Ok(Ok(report)) => Json(json!({
+            "success": true,
+            "report": report
+        }))
+        .into_response(),
+        Ok(Err(e)) => removal_error(e),
+        Err(status) => status.into_response(),
+    }
+}
+
+// Show the retention policy and what it would remove right now
+pub async fn retention_plan(State(config): State<ReportConfig>) -> Result<Json<Value>, StatusCode> {
+    let policy = config.retention.clone();
+    let plan = blocking(config, |config| config.retention_plan())
+        .await?
+        .map_err(error_status)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "policy": policy.as_ref(),
+        "plan": plan
+    })))
+}
+
+// Apply the retention policy now instead of waiting for the schedule
+pub async fn apply_retention(
+    State(config): State<ReportConfig>,
+) -> Result<Json<Value>, StatusCode> {
+    let policy = config.retention.clone();
+    let plan = blocking(config, |config| config.apply_retention())
+        .await?
+        .map_err(error_status)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "policy": policy.as_ref(),
+        "plan": plan
+    })))
+}
+
+/// Apply the retention policy on its interval for as long as the server runs. Nothing is
+/// scheduled when no retention rule is configured.
+pub fn spawn_retention(config: ReportConfig) {
+    if !config.retention.is_enabled() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.retention.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let config = config.clone();
+            match tokio::task::spawn_blocking(move || config.apply_retention()).await {
+                Ok(Ok(plan)) if !plan.expired.is_empty() => {
+                    println!(
+                        "🧹 Retention removed {} report(s): {}",
+                        plan.expired.len(),
+                        plan.expired.join(", ")
+                    );
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => eprintln!("Error applying report retention: {}", e),
+                Err(e) => eprintln!("Report retention task failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(id: &str, test_type: &str, duration: u64, coverage: f64) -> Report {
+        let (_, timestamp) = id.split_at(id.len() - 15);
+        Report {
+            id: id.to_string(),
+            name: id.to_string(),
+            test_type: test_type.to_string(),
+            status: "completed".to_string(),
+            timestamp: chrono::NaiveDateTime::parse_from_str(timestamp, "%Y%m%d_%H%M%S").ok(),
+            duration,
+            coverage_percentage: coverage,
+            baseline: false,
+            files: Vec::new(),
+            summary: String::new(),
+        }
+    }
+
+    fn sample_reports() -> Vec<Report> {
+        vec![
+            report("demo_20250629_155400", "demo", 30500, 87.5),
+            report("comprehensive_20250629_214321", "comprehensive", 60752, 0.0),
+            report("basic_20250701_090000", "basic", 12000, 100.0),
+        ]
+    }
+
+    fn ids(reports: &[Report]) -> Vec<&str> {
+        reports.iter().map(|report| report.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_default_sort_is_newest_first() {
+        let mut reports = sample_reports();
+        apply_query(&mut reports, &ListReportsQuery::default());
+        assert_eq!(
+            ids(&reports),
+            vec![
+                "basic_20250701_090000",
+                "comprehensive_20250629_214321",
+                "demo_20250629_155400"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_coverage_ascending() {
+        let mut reports = sample_reports();
+        let query = ListReportsQuery {
+            sort: ReportSort::Coverage,
+            order: SortOrder::Asc,
+            ..ListReportsQuery::default()
+        };
+        apply_query(&mut reports, &query);
+        assert_eq!(reports[0].id, "comprehensive_20250629_214321");
+        assert_eq!(reports[2].id, "basic_20250701_090000");
+    }
+
+    #[test]
+    fn test_filter_by_test_type() {
+        let mut reports = sample_reports();
+        let query = ListReportsQuery {
+            test_type: Some("demo".to_string()),
+            ..ListReportsQuery::default()
+        };
+        apply_query(&mut reports, &query);
+        assert_eq!(ids(&reports), vec!["demo_20250629_155400"]);
+    }
+}
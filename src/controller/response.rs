@@ -0,0 +1,54 @@
+use crate::config::broker::BrokerError;
+use crate::config::rabbitmq::PoolError;
+use crate::model::schema::SchemaViolation;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use std::fmt::Display;
+
+/// `{"success": false, "error": ...}` with `status`, as every JSON endpoint reports errors
+pub fn error_response(status: StatusCode, message: impl Display) -> Response {
+    (
+        status,
+        Json(json!({
+            "success": false,
+            "error": message.to_string()
+        })),
+    )
+        .into_response()
+}
+
+/// Pool errors mean the broker is out of reach or busy, so callers may retry
+pub fn pool_error_response(e: &(dyn std::error::Error + Send + Sync + 'static)) -> Response {
+    let status = match e.downcast_ref::<PoolError>() {
+        Some(PoolError::Channel(_)) | None => StatusCode::INTERNAL_SERVER_ERROR,
+        Some(_) => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    error_response(status, e)
+}
+
+/// Errors worth retrying are `503`, the broker refusing the request `400`
+pub fn broker_failure_response(e: &BrokerError) -> Response {
+    let status = match e {
+        BrokerError::Invalid(_) => StatusCode::BAD_REQUEST,
+        BrokerError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        BrokerError::Failed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    error_response(status, e)
+}
+
+/// A payload that does not match its subject's schema is `422`, with every failing field
+pub fn schema_violation_response(violation: &SchemaViolation) -> Response {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({
+            "success": false,
+            "error": violation.to_string(),
+            "subject": violation.subject,
+            "errors": violation.errors
+        })),
+    )
+        .into_response()
+}
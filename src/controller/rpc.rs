@@ -0,0 +1,159 @@
+use crate::config::broker::{OutgoingMessage, PublishTarget, ReplyQueue};
+use crate::config::schema::SchemaRegistry;
+use crate::config::transport::Transport;
+use crate::controller::mqtt::MqttState;
+use crate::controller::response::{
+    broker_failure_response, error_response, schema_violation_response,
+};
+use crate::model::mqtt::{message_payload, ConfirmStatus};
+use crate::model::rpc::{RpcRequest, RPC_ERROR_HEADER};
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use std::time::Instant;
+use tokio::time::timeout_at;
+use uuid::Uuid;
+
+// Publish a request with `reply_to` set to a fresh exclusive queue and answer with the
+// reply's body, or 504 when none arrives in time
+pub async fn rpc(
+    State(transport): State<Transport>,
+    State(schemas): State<SchemaRegistry>,
+    payload: Result<Json<RpcRequest>, JsonRejection>,
+) -> Response {
+    let request = match payload {
+        Ok(Json(request)) => request,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = request.validate() {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let routing_key = request.queue.trim().to_string();
+    let (payload, _) = message_payload(&request.message);
+    if let Err(violation) = schemas.validate(&routing_key, &payload) {
+        return schema_violation_response(&violation);
+    }
+    let start = Instant::now();
+    let deadline = tokio::time::Instant::now() + request.timeout();
+    let broker = transport.broker();
+
+    let target = PublishTarget {
+        exchange: request.exchange.clone().unwrap_or_default(),
+        routing_key,
+        qos: 1,
+        retain: false,
+        declare: true,
+    };
+    // Listening before publishing, so a fast reply is not missed
+    let ReplyQueue {
+        name: reply_to,
+        publisher,
+        mut replies,
+    } = match broker.reply_queue(target).await {
+        Ok(reply_queue) => reply_queue,
+        Err(e) => return broker_failure_response(&e),
+    };
+    let correlation_id = Uuid::new_v4().to_string();
+    let (payload, properties) =
+        request.message(&Uuid::new_v4().to_string(), &correlation_id, &reply_to);
+    let message = OutgoingMessage {
+        payload,
+        properties,
+    };
+
+    let confirm = match publisher.send(message).await {
+        Ok(pending) => pending.await,
+        Err(e) => Err(e),
+    };
+    drop(publisher);
+    let confirm = match confirm {
+        Ok(confirm) => confirm,
+        Err(e) => {
+            replies.close().await;
+            return broker_failure_response(&e);
+        }
+    };
+    if matches!(confirm, ConfirmStatus::Nack | ConfirmStatus::Returned) {
+        replies.close().await;
+        return error_response(
+            StatusCode::BAD_GATEWAY,
+            format!(
+                "The broker did not route the request to '{}' ({:?})",
+                request.queue, confirm
+            ),
+        );
+    }
+
+    // Replies to earlier requests cannot reach a fresh queue, but a responder may answer
+    // twice
+    let reply = loop {
+        match timeout_at(deadline, replies.next()).await {
+            Ok(Some(Ok(reply))) if reply.correlation_id.as_deref() == Some(&correlation_id) => {
+                break Ok(reply)
+            }
+            Ok(Some(Ok(_))) => {}
+            Ok(Some(Err(e))) => break Err((StatusCode::BAD_GATEWAY, e)),
+            Ok(None) => {
+                let e = "The reply queue was closed".to_string();
+                break Err((StatusCode::BAD_GATEWAY, e));
+            }
+            Err(_) => {
+                let e = format!(
+                    "No reply from '{}' within {}ms",
+                    request.queue, request.timeout_ms
+                );
+                break Err((StatusCode::GATEWAY_TIMEOUT, e));
+            }
+        }
+    };
+    replies.close().await;
+
+    let reply = match reply {
+        Ok(reply) => reply,
+        Err((status, e)) => {
+            eprintln!("RPC to {} failed: {}", request.queue, e);
+            return error_response(status, e);
+        }
+    };
+    if let Some(error) = reply.headers.get(RPC_ERROR_HEADER) {
+        return (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({
+                "success": false,
+                "error": format!("The responder failed: {}", error.as_str().unwrap_or_default()),
+                "correlation_id": correlation_id
+            })),
+        )
+            .into_response();
+    }
+
+    let content_type = reply
+        .content_type
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type),
+            (
+                header::HeaderName::from_static("x-correlation-id"),
+                correlation_id,
+            ),
+            (
+                header::HeaderName::from_static("x-rpc-elapsed-ms"),
+                format!("{:.1}", start.elapsed().as_secs_f64() * 1000.0),
+            ),
+        ],
+        reply.body,
+    )
+        .into_response()
+}
+
+pub async fn list_responders(State(state): State<MqttState>) -> Json<serde_json::Value> {
+    Json(json!({
+        "success": true,
+        "responders": state.consumers.responders().names()
+    }))
+}
@@ -0,0 +1,83 @@
+use crate::config::schema::SchemaRegistry;
+use crate::controller::response::error_response;
+use crate::model::topology::validate_queue_name;
+use axum::{
+    extract::{rejection::JsonRejection, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::{json, Value};
+use std::io;
+
+pub async fn list_schemas(State(schemas): State<SchemaRegistry>) -> Json<Value> {
+    Json(json!({
+        "success": true,
+        "schemas": schemas.list()
+    }))
+}
+
+pub async fn get_schema(
+    State(schemas): State<SchemaRegistry>,
+    Path(subject): Path<String>,
+) -> Response {
+    match schemas.get(&subject) {
+        Some(schema) => Json(json!({
+            "success": true,
+            "schema": schema
+        }))
+        .into_response(),
+        None => error_response(
+            StatusCode::NOT_FOUND,
+            format!("No schema registered for '{}'", subject),
+        ),
+    }
+}
+
+// Register the request body as the JSON Schema for a queue or routing key, replacing any
+// schema it had
+pub async fn put_schema(
+    State(schemas): State<SchemaRegistry>,
+    Path(subject): Path<String>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> Response {
+    let schema = match payload {
+        Ok(Json(schema)) => schema,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = validate_queue_name("subject", &subject) {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+
+    match schemas.register(&subject, schema) {
+        Ok(schema) => Json(json!({
+            "success": true,
+            "schema": schema
+        }))
+        .into_response(),
+        Err(e) if e.kind() == io::ErrorKind::InvalidInput => {
+            error_response(StatusCode::BAD_REQUEST, e)
+        }
+        Err(e) => {
+            eprintln!("Failed to save schema for {}: {}", subject, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, e)
+        }
+    }
+}
+
+pub async fn delete_schema(
+    State(schemas): State<SchemaRegistry>,
+    Path(subject): Path<String>,
+) -> Response {
+    match schemas.remove(&subject) {
+        Ok(Some(schema)) => Json(json!({
+            "success": true,
+            "schema": schema
+        }))
+        .into_response(),
+        Ok(None) => error_response(
+            StatusCode::NOT_FOUND,
+            format!("No schema registered for '{}'", subject),
+        ),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
@@ -0,0 +1,384 @@
+use crate::config::broker::BrokerSubscription;
+use crate::config::rabbitmq::{PooledChannel, RabbitMqPool};
+use crate::config::transport::Transport;
+use crate::controller::response::{broker_failure_response, error_response, pool_error_response};
+use crate::model::consumer::ConsumedMessage;
+use crate::model::stream::{AckMode, ClientAck, StreamQuery};
+use axum::{
+    extract::{
+        rejection::QueryRejection,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+};
+use futures_lite::{stream, StreamExt};
+use lapin::{
+    acker::Acker,
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicCancelOptions, BasicConsumeOptions, BasicNackOptions, BasicQosOptions,
+    },
+    types::{DeliveryTag, FieldTable},
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Messages buffered between the broker and a client. When a slow client lets it fill up,
+/// consuming pauses and the broker stops sending once `prefetch` messages are unacked.
+const STREAM_BUFFER: usize = 16;
+
+/// A message on its way to a client, with what acknowledges it unless that already happened
+struct StreamItem {
+    message: ConsumedMessage,
+    delivery: Option<(DeliveryTag, Acker)>,
+}
+
+impl StreamItem {
+    fn to_json(&self, ack: AckMode) -> Value {
+        let mut value = self.message.to_json();
+        if let (AckMode::Client, Some((tag, _))) = (ack, &self.delivery) {
+            value["delivery_tag"] = json!(tag);
+        }
+        value
+    }
+}
+
+/// A lost channel or subscription ends the stream with its error
+type StreamResult = Result<StreamItem, String>;
+
+// Push each message of a queue or topic as a server-sent `message` event
+pub async fn stream_sse(
+    State(transport): State<Transport>,
+    query: Result<Query<StreamQuery>, QueryRejection>,
+) -> Response {
+    let query = match query {
+        Ok(Query(query)) => query,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if query.ack == AckMode::Client {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "ack=client needs the WebSocket endpoint, /mqtt/stream/ws",
+        );
+    }
+    let ack = query.ack;
+    let receiver = match open_stream(&transport, &query).await {
+        Ok(receiver) => receiver,
+        Err(response) => return response,
+    };
+
+    // The stream only asks for the next message once the previous event has been taken,
+    // so a slow client holds back the receiver. Being asked again is also what shows the
+    // previous event went out, so that is when it is acked.
+    let events = stream::unfold(
+        (Some(receiver), None::<Acker>),
+        move |(receiver, sent)| async move {
+            if let Some(acker) = sent {
+                if let Err(e) = acker.ack(BasicAckOptions::default()).await {
+                    eprintln!("Failed to acknowledge streamed message: {}", e);
+                }
+            }
+            let mut receiver = receiver?;
+            let (event, next) = match receiver.recv().await? {
+                Ok(item) => {
+                    let event = Event::default()
+                        .event("message")
+                        .json_data(item.to_json(ack))
+                        .unwrap_or_default();
+                    let sent = item.delivery.map(|(_, acker)| acker);
+                    (event, (Some(receiver), sent))
+                }
+                Err(e) => {
+                    let event = Event::default()
+                        .event("error")
+                        .json_data(json!({"error": e}))
+                        .unwrap_or_default();
+                    (event, (None, None))
+                }
+            };
+            Some((Ok::<_, Infallible>(event), next))
+        },
+    );
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+// The same messages as `stream_sse` over a WebSocket, which can also ack them itself
+pub async fn stream_ws(
+    State(transport): State<Transport>,
+    query: Result<Query<StreamQuery>, QueryRejection>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let query = match query {
+        Ok(Query(query)) => query,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if query.ack == AckMode::Client && !matches!(transport, Transport::Amqp(_)) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "ack=client needs AMQP; {} acknowledges on receipt",
+                transport.name()
+            ),
+        );
+    }
+    let ack = query.ack;
+    let receiver = match open_stream(&transport, &query).await {
+        Ok(receiver) => receiver,
+        Err(response) => return response,
+    };
+
+    upgrade.on_upgrade(move |socket| run_socket(socket, receiver, ack))
+}
+
+async fn run_socket(
+    mut socket: WebSocket,
+    mut receiver: mpsc::Receiver<StreamResult>,
+    ack: AckMode,
+) {
+    // Dropping these on disconnect leaves the messages unacked, so the broker requeues them
+    let mut unacked: HashMap<DeliveryTag, Acker> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            item = receiver.recv() => {
+                let item = match item {
+                    Some(Ok(item)) => item,
+                    Some(Err(e)) => {
+                        let _ = socket.send(Message::Text(json!({"error": e}).to_string())).await;
+                        break;
+                    }
+                    None => break,
+                };
+                let text = item.to_json(ack).to_string();
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+                match (ack, item.delivery) {
+                    (AckMode::Client, Some((tag, acker))) => {
+                        unacked.insert(tag, acker);
+                    }
+                    (_, Some((_, acker))) => {
+                        if let Err(e) = acker.ack(BasicAckOptions::default()).await {
+                            eprintln!("Failed to acknowledge streamed message: {}", e);
+                        }
+                    }
+                    (_, None) => {}
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) if ack == AckMode::Client => {
+                    if let Err(e) = client_ack(&mut unacked, &text).await {
+                        let error = json!({"error": e}).to_string();
+                        if socket.send(Message::Text(error)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+async fn client_ack(unacked: &mut HashMap<DeliveryTag, Acker>, text: &str) -> Result<(), String> {
+    let command: ClientAck = serde_json::from_str(text).map_err(|_| {
+        r#"Expected {"ack": <delivery_tag>} or {"nack": <delivery_tag>, "requeue": <bool>}"#
+            .to_string()
+    })?;
+    let tag = command.delivery_tag();
+    let acker = unacked
+        .remove(&tag)
+        .ok_or_else(|| format!("No unacked message with delivery tag {}", tag))?;
+
+    let result = match command {
+        ClientAck::Ack { .. } => acker.ack(BasicAckOptions::default()).await,
+        ClientAck::Nack { requeue, .. } => {
+            acker
+                .nack(BasicNackOptions {
+                    requeue,
+                    ..BasicNackOptions::default()
+                })
+                .await
+        }
+    };
+    result.map_err(|e| format!("Failed to acknowledge delivery tag {}: {}", tag, e))
+}
+
+/// Start consuming in the background, handing messages over through a bounded buffer
+async fn open_stream(
+    transport: &Transport,
+    query: &StreamQuery,
+) -> Result<mpsc::Receiver<StreamResult>, Response> {
+    query
+        .validate()
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, e))?;
+    let queue = query.queue.trim().to_string();
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+
+    match transport {
+        Transport::Amqp(pool) => {
+            let channel = pool
+                .acquire_consumer(false)
+                .await
+                .map_err(|e| pool_error_response(&e))?;
+            let consumer_tag = format!("stream-{}", Uuid::new_v4());
+            let deliveries = start_consuming(pool, &channel, &queue, query.prefetch, &consumer_tag)
+                .await
+                .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+            println!("📡 Streaming queue {} ({:?} acks)", queue, query.ack);
+            tokio::spawn(forward_queue(
+                channel,
+                deliveries,
+                queue,
+                consumer_tag,
+                query.ack,
+                sender,
+            ));
+        }
+        other => {
+            let subscription = other
+                .broker()
+                .subscribe(&queue, query.qos)
+                .await
+                .map_err(|e| broker_failure_response(&e))?;
+            println!("📡 Streaming {} from {}", queue, other.name());
+            tokio::spawn(forward_subscription(subscription, sender));
+        }
+    }
+
+    Ok(receiver)
+}
+
+async fn start_consuming(
+    pool: &RabbitMqPool,
+    channel: &PooledChannel,
+    queue: &str,
+    prefetch: u16,
+    consumer_tag: &str,
+) -> Result<lapin::Consumer, String> {
+    channel
+        .basic_qos(prefetch, BasicQosOptions::default())
+        .await
+        .map_err(|e| format!("Failed to set prefetch: {}", e))?;
+    pool.declare_queue(channel, queue)
+        .await
+        .map_err(|e| format!("Failed to declare queue '{}': {}", queue, e))?;
+    channel
+        .basic_consume(
+            queue,
+            consumer_tag,
+            BasicConsumeOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to start consumer: {}", e))
+}
+
+/// Move deliveries to the client until it goes away or the channel is lost
+async fn forward_queue(
+    channel: PooledChannel,
+    mut deliveries: lapin::Consumer,
+    queue: String,
+    consumer_tag: String,
+    ack: AckMode,
+    sender: mpsc::Sender<StreamResult>,
+) {
+    loop {
+        let delivery = tokio::select! {
+            _ = sender.closed() => break,
+            delivery = deliveries.next() => delivery,
+        };
+        let delivery = match delivery {
+            Some(Ok(delivery)) => delivery,
+            Some(Err(e)) => {
+                let _ = sender
+                    .send(Err(format!("Error receiving message: {}", e)))
+                    .await;
+                break;
+            }
+            None => {
+                let _ = sender
+                    .send(Err("Consumer was cancelled by the broker".to_string()))
+                    .await;
+                break;
+            }
+        };
+
+        let message = ConsumedMessage::from_delivery(&queue, &delivery);
+        let Delivery {
+            delivery_tag,
+            acker,
+            ..
+        } = delivery;
+        let delivery = if ack == AckMode::Auto {
+            if let Err(e) = acker.ack(BasicAckOptions::default()).await {
+                let _ = sender
+                    .send(Err(format!("Failed to acknowledge message: {}", e)))
+                    .await;
+                break;
+            }
+            None
+        } else {
+            Some((delivery_tag, acker))
+        };
+
+        // Waits while the buffer is full, which is what holds back a slow client's queue
+        if sender
+            .send(Ok(StreamItem { message, delivery }))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    println!("📡 Stream of queue {} closed", queue);
+    let _ = channel
+        .basic_cancel(&consumer_tag, BasicCancelOptions::default())
+        .await;
+    channel.close().await;
+}
+
+/// Subscriptions acknowledge on receipt, so a client that goes away loses what was buffered
+async fn forward_subscription(
+    mut subscription: Box<dyn BrokerSubscription>,
+    sender: mpsc::Sender<StreamResult>,
+) {
+    loop {
+        let message = tokio::select! {
+            _ = sender.closed() => break,
+            message = subscription.next() => message,
+        };
+        let message = match message {
+            Some(Ok(message)) => message,
+            Some(Err(e)) => {
+                let _ = sender.send(Err(e)).await;
+                break;
+            }
+            None => {
+                let _ = sender.send(Err("Subscription ended".to_string())).await;
+                break;
+            }
+        };
+        let item = StreamItem {
+            message,
+            delivery: None,
+        };
+        if sender.send(Ok(item)).await.is_err() {
+            break;
+        }
+    }
+    subscription.close().await;
+}
@@ -0,0 +1,496 @@
+use crate::config::transport::Transport;
+use crate::controller::response::{error_response, pool_error_response};
+use crate::model::consumer::ConsumedMessage;
+use crate::model::mqtt::field_table;
+use crate::model::topology::{
+    validate_exchange_name, validate_queue_name, BindingSpec, DeleteQuery, ExchangeSpec, PeekQuery,
+    QueueDeclaration, QueueSpec,
+};
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicGetOptions, BasicNackOptions, ExchangeDeleteOptions, QueueBindOptions,
+        QueueDeclareOptions, QueueDeleteOptions, QueuePurgeOptions,
+    },
+    protocol::{AMQPErrorKind, AMQPSoftError},
+    types::FieldTable,
+    Channel, Queue,
+};
+use serde_json::{json, Value};
+
+/// Topology only exists on RabbitMQ; an MQTT broker routes by topic alone
+pub fn needs_amqp(what: &str) -> Response {
+    error_response(
+        StatusCode::BAD_REQUEST,
+        format!("{} need AMQP; MQTT routes by topic", what),
+    )
+}
+
+/// The broker closes the channel with a reply code when it refuses an operation
+pub fn broker_error_response(context: &str, e: lapin::Error) -> Response {
+    let status = match &e {
+        lapin::Error::ProtocolError(error) => match error.kind() {
+            AMQPErrorKind::Soft(AMQPSoftError::NOTFOUND) => StatusCode::NOT_FOUND,
+            AMQPErrorKind::Soft(AMQPSoftError::ACCESSREFUSED) => StatusCode::FORBIDDEN,
+            AMQPErrorKind::Soft(
+                AMQPSoftError::PRECONDITIONFAILED | AMQPSoftError::RESOURCELOCKED,
+            ) => StatusCode::CONFLICT,
+            _ => StatusCode::BAD_GATEWAY,
+        },
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let error_msg = format!("{}: {}", context, e);
+    eprintln!("{}", error_msg);
+    error_response(status, error_msg)
+}
+
+/// Take up to `n` messages off a queue without acking them. They stay with the channel
+/// until they are acked or nacked, or the channel closes.
+pub async fn get_messages(
+    channel: &Channel,
+    queue: &str,
+    n: usize,
+) -> lapin::Result<Vec<Delivery>> {
+    let mut deliveries = Vec::with_capacity(n);
+    while deliveries.len() < n {
+        match channel.basic_get(queue, BasicGetOptions::default()).await? {
+            Some(message) => deliveries.push(message.delivery),
+            None => break,
+        }
+    }
+    Ok(deliveries)
+}
+
+/// Put fetched messages back where they were
+pub async fn requeue_all(deliveries: Vec<Delivery>) {
+    for delivery in deliveries {
+        let requeue = BasicNackOptions {
+            requeue: true,
+            ..BasicNackOptions::default()
+        };
+        if let Err(e) = delivery.acker.nack(requeue).await {
+            eprintln!("Failed to requeue message: {}", e);
+        }
+    }
+}
+
+/// A queue's message and consumer counts, from a passive declare that fails with
+/// NOT_FOUND instead of creating the queue
+pub async fn inspect_queue(channel: &Channel, queue: &str) -> lapin::Result<Queue> {
+    channel
+        .queue_declare(
+            queue,
+            QueueDeclareOptions {
+                passive: true,
+                ..QueueDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await
+}
+
+// Declare an exchange; declaring it again with the same settings is a no-op
+pub async fn declare_exchange(
+    State(transport): State<Transport>,
+    payload: Result<Json<ExchangeSpec>, JsonRejection>,
+) -> Response {
+    let spec = match payload {
+        Ok(Json(spec)) => spec,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = spec.validate() {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Exchanges, queues and bindings");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    if let Err(e) = channel
+        .exchange_declare(
+            &spec.name,
+            spec.kind.into(),
+            spec.options(),
+            spec.arguments(),
+        )
+        .await
+    {
+        let context = format!("Failed to declare exchange '{}'", spec.name);
+        return broker_error_response(&context, e);
+    }
+    println!("🔀 Declared {:?} exchange {}", spec.kind, spec.name);
+
+    (
+        StatusCode::CREATED,
+        Json(json!({
+            "success": true,
+            "exchange": spec
+        })),
+    )
+        .into_response()
+}
+
+pub async fn delete_exchange(
+    State(transport): State<Transport>,
+    Path(name): Path<String>,
+    query: Result<Query<DeleteQuery>, QueryRejection>,
+) -> Response {
+    let query = match query {
+        Ok(Query(query)) => query,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = validate_exchange_name("name", &name) {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Exchanges, queues and bindings");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    let options = ExchangeDeleteOptions {
+        if_unused: query.if_unused,
+        ..ExchangeDeleteOptions::default()
+    };
+    if let Err(e) = channel.exchange_delete(&name, options).await {
+        let context = format!("Failed to delete exchange '{}'", name);
+        return broker_error_response(&context, e);
+    }
+    println!("🔀 Deleted exchange {}", name);
+
+    Json(json!({
+        "success": true,
+        "exchange": name
+    }))
+    .into_response()
+}
+
+// Declare a queue with its arguments; publishers and consumers of it reuse them
+pub async fn declare_queue(
+    State(transport): State<Transport>,
+    payload: Result<Json<QueueSpec>, JsonRejection>,
+) -> Response {
+    let spec = match payload {
+        Ok(Json(spec)) => spec,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = spec.validate() {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Exchanges, queues and bindings");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    let arguments = spec.arguments();
+    let queue = match pool
+        .declare_queue_with(
+            &channel,
+            &spec.name,
+            spec.options(),
+            field_table(&arguments),
+        )
+        .await
+    {
+        Ok(queue) => queue,
+        Err(e) => {
+            let context = format!("Failed to declare queue '{}'", spec.name);
+            return broker_error_response(&context, e);
+        }
+    };
+    println!("📥 Declared queue {}", spec.name);
+
+    (
+        StatusCode::CREATED,
+        Json(json!({
+            "success": true,
+            "queue": spec.name,
+            "durable": spec.durable,
+            "exclusive": spec.exclusive,
+            "auto_delete": spec.auto_delete,
+            "arguments": arguments,
+            "message_count": queue.message_count(),
+            "consumer_count": queue.consumer_count()
+        })),
+    )
+        .into_response()
+}
+
+pub async fn delete_queue(
+    State(transport): State<Transport>,
+    Path(name): Path<String>,
+    query: Result<Query<DeleteQuery>, QueryRejection>,
+) -> Response {
+    let query = match query {
+        Ok(Query(query)) => query,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = validate_queue_name("name", &name) {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Exchanges, queues and bindings");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    let options = QueueDeleteOptions {
+        if_unused: query.if_unused,
+        if_empty: query.if_empty,
+        ..QueueDeleteOptions::default()
+    };
+    let messages_deleted = match channel.queue_delete(&name, options).await {
+        Ok(count) => count,
+        Err(e) => {
+            let context = format!("Failed to delete queue '{}'", name);
+            return broker_error_response(&context, e);
+        }
+    };
+    pool.forget_queue(&name);
+    println!("📥 Deleted queue {} ({} messages)", name, messages_deleted);
+
+    Json(json!({
+        "success": true,
+        "queue": name,
+        "messages_deleted": messages_deleted
+    }))
+    .into_response()
+}
+
+// Message and consumer counts of a queue, and how this server declared it if it did
+pub async fn get_queue(State(transport): State<Transport>, Path(name): Path<String>) -> Response {
+    if let Err(e) = validate_queue_name("name", &name) {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Exchanges, queues and bindings");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    let queue = match inspect_queue(&channel, &name).await {
+        Ok(queue) => queue,
+        Err(e) => {
+            let context = format!("Failed to inspect queue '{}'", name);
+            return broker_error_response(&context, e);
+        }
+    };
+    // The broker does not report arguments, so only what this server declared is known
+    let declared = pool
+        .queue_declaration(&name)
+        .map(|(options, arguments)| QueueDeclaration::new(&options, &arguments));
+
+    Json(json!({
+        "success": true,
+        "queue": name,
+        "message_count": queue.message_count(),
+        "consumer_count": queue.consumer_count(),
+        "declared": declared
+    }))
+    .into_response()
+}
+
+// Drop every ready message in a queue; unacked ones are left alone
+pub async fn purge_queue(State(transport): State<Transport>, Path(name): Path<String>) -> Response {
+    if let Err(e) = validate_queue_name("name", &name) {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Exchanges, queues and bindings");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    let purged = match channel
+        .queue_purge(&name, QueuePurgeOptions::default())
+        .await
+    {
+        Ok(purged) => purged,
+        Err(e) => {
+            let context = format!("Failed to purge queue '{}'", name);
+            return broker_error_response(&context, e);
+        }
+    };
+    println!("🗑️  Purged {} messages from {}", purged, name);
+
+    Json(json!({
+        "success": true,
+        "queue": name,
+        "purged": purged
+    }))
+    .into_response()
+}
+
+// Fetch the first messages of a queue and put them back. They come back flagged as
+// redelivered, and consumers may see them out of order while the peek is running.
+pub async fn peek_queue(
+    State(transport): State<Transport>,
+    Path(name): Path<String>,
+    query: Result<Query<PeekQuery>, QueryRejection>,
+) -> Response {
+    let query = match query {
+        Ok(Query(query)) => query,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = query
+        .validate()
+        .and_then(|()| validate_queue_name("name", &name))
+    {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Exchanges, queues and bindings");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    let queue = match inspect_queue(&channel, &name).await {
+        Ok(queue) => queue,
+        Err(e) => {
+            let context = format!("Failed to inspect queue '{}'", name);
+            return broker_error_response(&context, e);
+        }
+    };
+    let deliveries = match get_messages(&channel, &name, query.n).await {
+        Ok(deliveries) => deliveries,
+        Err(e) => {
+            let context = format!("Failed to read queue '{}'", name);
+            return broker_error_response(&context, e);
+        }
+    };
+    let messages: Vec<Value> = deliveries
+        .iter()
+        .map(|delivery| ConsumedMessage::from_delivery(&name, delivery).to_json())
+        .collect();
+    requeue_all(deliveries).await;
+
+    Json(json!({
+        "success": true,
+        "queue": name,
+        "message_count": queue.message_count(),
+        "messages": messages
+    }))
+    .into_response()
+}
+
+// Bind a queue to an exchange
+pub async fn bind_queue(
+    State(transport): State<Transport>,
+    payload: Result<Json<BindingSpec>, JsonRejection>,
+) -> Response {
+    let binding = match payload {
+        Ok(Json(binding)) => binding,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = binding.validate() {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Exchanges, queues and bindings");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    if let Err(e) = channel
+        .queue_bind(
+            &binding.queue,
+            &binding.exchange,
+            &binding.routing_key,
+            QueueBindOptions::default(),
+            binding.arguments(),
+        )
+        .await
+    {
+        let context = format!(
+            "Failed to bind queue '{}' to exchange '{}'",
+            binding.queue, binding.exchange
+        );
+        return broker_error_response(&context, e);
+    }
+    println!(
+        "🔗 Bound queue {} to {} with '{}'",
+        binding.queue, binding.exchange, binding.routing_key
+    );
+
+    (
+        StatusCode::CREATED,
+        Json(json!({
+            "success": true,
+            "binding": binding
+        })),
+    )
+        .into_response()
+}
+
+// Remove a binding; the body must match the one it was created with
+pub async fn unbind_queue(
+    State(transport): State<Transport>,
+    payload: Result<Json<BindingSpec>, JsonRejection>,
+) -> Response {
+    let binding = match payload {
+        Ok(Json(binding)) => binding,
+        Err(rejection) => return error_response(StatusCode::BAD_REQUEST, rejection.body_text()),
+    };
+    if let Err(e) = binding.validate() {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+    let Transport::Amqp(pool) = &transport else {
+        return needs_amqp("Exchanges, queues and bindings");
+    };
+    let channel = match pool.acquire(false).await {
+        Ok(channel) => channel,
+        Err(e) => return pool_error_response(&e),
+    };
+
+    if let Err(e) = channel
+        .queue_unbind(
+            &binding.queue,
+            &binding.exchange,
+            &binding.routing_key,
+            binding.arguments(),
+        )
+        .await
+    {
+        let context = format!(
+            "Failed to unbind queue '{}' from exchange '{}'",
+            binding.queue, binding.exchange
+        );
+        return broker_error_response(&context, e);
+    }
+    println!(
+        "🔗 Unbound queue {} from {} with '{}'",
+        binding.queue, binding.exchange, binding.routing_key
+    );
+
+    Json(json!({
+        "success": true,
+        "binding": binding
+    }))
+    .into_response()
+}
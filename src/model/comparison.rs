@@ -0,0 +1,361 @@
+use crate::model::k6::K6Aggregates;
+use crate::model::report::PerformanceSummary;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub const OVERALL: &str = "Overall";
+
+/// The numbers we compare between two runs, for the whole run or a single endpoint
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct RunMetrics {
+    pub p95: Option<f64>,
+    pub p99: Option<f64>,
+    /// Requests per second
+    pub throughput: Option<f64>,
+    /// Failed requests as a fraction of all requests
+    pub error_rate: Option<f64>,
+}
+
+/// Everything we know about one run, taken from its raw k6 stream when available and from
+/// `performance-data.json` otherwise
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunSnapshot {
+    pub report_id: String,
+    pub overall: RunMetrics,
+    pub endpoints: BTreeMap<String, RunMetrics>,
+    /// Check name to pass rate
+    pub checks: BTreeMap<String, f64>,
+}
+
+impl RunSnapshot {
+    pub fn from_aggregates(report_id: &str, aggregates: &K6Aggregates) -> Self {
+        let seconds = aggregates.duration_ms() as f64 / 1000.0;
+        let throughput = |requests: u64| (seconds > 0.0).then(|| requests as f64 / seconds);
+
+        let overall = RunMetrics {
+            p95: aggregates
+                .metrics
+                .get("http_req_duration")
+                .map(|m| m.stats.p95),
+            p99: aggregates
+                .metrics
+                .get("http_req_duration")
+                .map(|m| m.stats.p99),
+            throughput: aggregates
+                .metrics
+                .get("http_reqs")
+                .and_then(|m| throughput(m.stats.count)),
+            error_rate: aggregates
+                .metrics
+                .get("http_req_failed")
+                .map(|m| m.stats.rate),
+        };
+
+        let endpoints = aggregates
+            .endpoints
+            .iter()
+            .map(|(endpoint, summary)| {
+                let metrics = RunMetrics {
+                    p95: (summary.duration.count > 0).then_some(summary.duration.p95),
+                    p99: (summary.duration.count > 0).then_some(summary.duration.p99),
+                    throughput: throughput(summary.requests),
+                    error_rate: (summary.requests > 0).then_some(summary.error_rate),
+                };
+                (endpoint.clone(), metrics)
+            })
+            .collect();
+
+        let checks = aggregates
+            .checks
+            .iter()
+            .filter_map(|(name, counts)| {
+                pass_rate(counts.passes, counts.fails).map(|rate| (name.clone(), rate))
+            })
+            .collect();
+
+        Self {
+            report_id: report_id.to_string(),
+            overall,
+            endpoints,
+            checks,
+        }
+    }
+
+    pub fn from_summary(report_id: &str, summary: &PerformanceSummary) -> Self {
+        let value = |metric: &str, key: &str| {
+            summary
+                .metrics
+                .get(metric)
+                .and_then(|m| m.values.get(key))
+                .copied()
+        };
+
+        let overall = RunMetrics {
+            p95: value("http_req_duration", "p(95)"),
+            p99: value("http_req_duration", "p(99)"),
+            throughput: value("http_reqs", "rate"),
+            error_rate: value("http_req_failed", "rate"),
+        };
+
+        let checks = summary
+            .checks
+            .iter()
+            .filter_map(|check| {
+                pass_rate(check.passes, check.fails).map(|rate| (check.name.clone(), rate))
+            })
+            .collect();
+
+        Self {
+            report_id: report_id.to_string(),
+            overall,
+            endpoints: BTreeMap::new(),
+            checks,
+        }
+    }
+}
+
+fn pass_rate(passes: u64, fails: u64) -> Option<f64> {
+    let total = passes + fails;
+    (total > 0).then(|| passes as f64 / total as f64)
+}
+
+/// How much worse a run may get before it counts as a regression
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RegressionThresholds {
+    /// Allowed p95/p99 increase, in percent
+    pub latency_pct: f64,
+    /// Allowed throughput drop, in percent
+    pub throughput_pct: f64,
+    /// Allowed error rate increase, in percentage points
+    pub error_rate_pct: f64,
+    /// Allowed check pass rate drop, in percentage points
+    pub check_pass_pct: f64,
+}
+
+impl Default for RegressionThresholds {
+    fn default() -> Self {
+        Self {
+            latency_pct: 10.0,
+            throughput_pct: 10.0,
+            error_rate_pct: 1.0,
+            check_pass_pct: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricDelta {
+    pub metric: String,
+    pub base: Option<f64>,
+    pub head: Option<f64>,
+    pub change_pct: Option<f64>,
+    pub regression: bool,
+}
+
+impl MetricDelta {
+    fn new(
+        metric: &str,
+        base: Option<f64>,
+        head: Option<f64>,
+        regressed: impl Fn(f64, f64) -> bool,
+    ) -> Self {
+        let change_pct = match (base, head) {
+            (Some(base), Some(head)) if base != 0.0 => Some((head - base) / base * 100.0),
+            _ => None,
+        };
+        let regression = match (base, head) {
+            (Some(base), Some(head)) => regressed(base, head),
+            _ => false,
+        };
+
+        Self {
+            metric: metric.to_string(),
+            base,
+            head,
+            change_pct,
+            regression,
+        }
+    }
+
+    pub fn base_display(&self) -> String {
+        format_value(self.base)
+    }
+
+    pub fn head_display(&self) -> String {
+        format_value(self.head)
+    }
+
+    pub fn change_display(&self) -> String {
+        match self.change_pct {
+            Some(change) => format!("{:+.2}%", change),
+            None => "-".to_string(),
+        }
+    }
+}
+
+fn format_value(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{:.3}", value),
+        None => "-".to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointComparison {
+    pub endpoint: String,
+    pub deltas: Vec<MetricDelta>,
+    pub regression: bool,
+}
+
+impl EndpointComparison {
+    fn new(
+        endpoint: &str,
+        base: RunMetrics,
+        head: RunMetrics,
+        thresholds: &RegressionThresholds,
+    ) -> Self {
+        let latency = |base: f64, head: f64| head > base * (1.0 + thresholds.latency_pct / 100.0);
+        let deltas = vec![
+            MetricDelta::new("p95 (ms)", base.p95, head.p95, latency),
+            MetricDelta::new("p99 (ms)", base.p99, head.p99, latency),
+            MetricDelta::new(
+                "throughput (req/s)",
+                base.throughput,
+                head.throughput,
+                |base, head| head < base * (1.0 - thresholds.throughput_pct / 100.0),
+            ),
+            MetricDelta::new(
+                "error rate",
+                base.error_rate,
+                head.error_rate,
+                |base, head| (head - base) * 100.0 > thresholds.error_rate_pct,
+            ),
+        ];
+
+        Self {
+            endpoint: endpoint.to_string(),
+            regression: deltas.iter().any(|delta| delta.regression),
+            deltas,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub base: String,
+    pub head: String,
+    pub thresholds: RegressionThresholds,
+    pub overall: EndpointComparison,
+    pub endpoints: Vec<EndpointComparison>,
+    pub checks: Vec<MetricDelta>,
+    pub regressions: usize,
+}
+
+impl Comparison {
+    pub fn new(base: &RunSnapshot, head: &RunSnapshot, thresholds: RegressionThresholds) -> Self {
+        let overall = EndpointComparison::new(OVERALL, base.overall, head.overall, &thresholds);
+
+        let mut names: Vec<&String> = base.endpoints.keys().chain(head.endpoints.keys()).collect();
+        names.sort();
+        names.dedup();
+        let endpoints: Vec<EndpointComparison> = names
+            .into_iter()
+            .map(|endpoint| {
+                EndpointComparison::new(
+                    endpoint,
+                    base.endpoints.get(endpoint).copied().unwrap_or_default(),
+                    head.endpoints.get(endpoint).copied().unwrap_or_default(),
+                    &thresholds,
+                )
+            })
+            .collect();
+
+        let mut check_names: Vec<&String> = base.checks.keys().chain(head.checks.keys()).collect();
+        check_names.sort();
+        check_names.dedup();
+        let checks: Vec<MetricDelta> = check_names
+            .into_iter()
+            .map(|check| {
+                MetricDelta::new(
+                    check,
+                    base.checks.get(check).copied(),
+                    head.checks.get(check).copied(),
+                    |base, head| (base - head) * 100.0 > thresholds.check_pass_pct,
+                )
+            })
+            .collect();
+
+        let regressions = overall.deltas.iter().filter(|d| d.regression).count()
+            + endpoints
+                .iter()
+                .flat_map(|e| &e.deltas)
+                .filter(|d| d.regression)
+                .count()
+            + checks.iter().filter(|d| d.regression).count();
+
+        Self {
+            base: base.report_id.clone(),
+            head: head.report_id.clone(),
+            thresholds,
+            overall,
+            endpoints,
+            checks,
+            regressions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(id: &str, p95: f64, throughput: f64, error_rate: f64, check: f64) -> RunSnapshot {
+        let metrics = RunMetrics {
+            p95: Some(p95),
+            p99: None,
+            throughput: Some(throughput),
+            error_rate: Some(error_rate),
+        };
+        RunSnapshot {
+            report_id: id.to_string(),
+            overall: metrics,
+            endpoints: BTreeMap::from([("POST /mqtt/pub".to_string(), metrics)]),
+            checks: BTreeMap::from([("status is 200".to_string(), check)]),
+        }
+    }
+
+    #[test]
+    fn test_no_regression_within_thresholds() {
+        let base = snapshot("base", 100.0, 50.0, 0.01, 0.99);
+        let head = snapshot("head", 105.0, 48.0, 0.015, 0.985);
+
+        let comparison = Comparison::new(&base, &head, RegressionThresholds::default());
+        assert_eq!(comparison.regressions, 0);
+        assert_eq!(comparison.overall.deltas[0].change_pct, Some(5.0));
+    }
+
+    #[test]
+    fn test_flags_regressions_past_thresholds() {
+        let base = snapshot("base", 100.0, 50.0, 0.01, 0.99);
+        let head = snapshot("head", 150.0, 40.0, 0.05, 0.90);
+
+        let comparison = Comparison::new(&base, &head, RegressionThresholds::default());
+        // p95, throughput and error rate for overall and the endpoint, plus the check
+        assert_eq!(comparison.regressions, 7);
+        assert!(comparison.endpoints[0].regression);
+        assert!(comparison.checks[0].regression);
+        assert!(!comparison.overall.deltas[1].regression);
+    }
+
+    #[test]
+    fn test_custom_thresholds() {
+        let base = snapshot("base", 100.0, 50.0, 0.01, 0.99);
+        let head = snapshot("head", 150.0, 50.0, 0.01, 0.99);
+        let thresholds = RegressionThresholds {
+            latency_pct: 60.0,
+            ..RegressionThresholds::default()
+        };
+
+        assert_eq!(Comparison::new(&base, &head, thresholds).regressions, 0);
+    }
+}
@@ -0,0 +1,447 @@
+use crate::config::mqtt::MqttMessage;
+use crate::config::rabbitmq::backoff;
+use crate::model::mqtt::{headers_json, MAX_QOS};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use lapin::{message::Delivery, BasicProperties};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+const DEFAULT_QUEUE: &str = "test";
+const DEFAULT_PREFETCH: u16 = 10;
+const DEFAULT_QOS: u8 = 1;
+const MAX_CONCURRENCY: usize = 256;
+const MAX_QUEUE_BYTES: usize = 255;
+/// Leaves room for the `.dlq` and `.retry.<ms>` suffixes of the failure queues
+const MAX_RETRIED_QUEUE_BYTES: usize = 240;
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const MAX_ATTEMPTS: u32 = 100;
+const DEFAULT_INITIAL_DELAY_MS: u64 = 1000;
+const DEFAULT_MAX_DELAY_MS: u64 = 60_000;
+const MAX_DELAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// What a consumer worker does with each message
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HandlerSpec {
+    /// Print a line per message
+    #[default]
+    Log,
+    /// POST the message as JSON; any status outside 2xx is a failure
+    Webhook { url: String },
+    /// Store the message in a table whose partition key is the string `id`
+    Dynamodb { table: String },
+    /// Answer RPC requests with a registered responder, replying to their `reply_to`
+    Rpc { responder: String },
+}
+
+/// How a worker gets a failed message tried again
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryStrategy {
+    /// Nack with requeue for one more immediate try
+    #[default]
+    Requeue,
+    /// Republish through delay queues with exponential backoff, up to `max_attempts`
+    Retry,
+}
+
+/// What a worker does with messages its handler fails on; AMQP only
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FailurePolicy {
+    pub strategy: RetryStrategy,
+    /// Attempts with the `retry` strategy, the first delivery included
+    pub max_attempts: u32,
+    /// Delay before the first retry; each later one doubles it
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Send messages that are out of attempts to the dead-letter queue instead of dropping
+    /// them
+    pub dead_letter: bool,
+}
+
+impl Default for FailurePolicy {
+    fn default() -> Self {
+        FailurePolicy {
+            strategy: RetryStrategy::default(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_delay_ms: DEFAULT_INITIAL_DELAY_MS,
+            max_delay_ms: DEFAULT_MAX_DELAY_MS,
+            dead_letter: false,
+        }
+    }
+}
+
+impl FailurePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 || self.max_attempts > MAX_ATTEMPTS {
+            return Err(format!("max_attempts must be 1 to {}", MAX_ATTEMPTS));
+        }
+        if self.initial_delay_ms == 0 || self.initial_delay_ms > self.max_delay_ms {
+            return Err("initial_delay_ms must be at least 1 and at most max_delay_ms".to_string());
+        }
+        if self.max_delay_ms > MAX_DELAY_MS {
+            return Err(format!("max_delay_ms must be at most {}", MAX_DELAY_MS));
+        }
+        Ok(())
+    }
+
+    /// Whether failed messages leave the queue through delay or dead-letter queues
+    pub fn uses_failure_queues(&self) -> bool {
+        self.strategy == RetryStrategy::Retry || self.dead_letter
+    }
+
+    /// Delay before retry number `retry`, counting from 1
+    pub fn retry_delay(&self, retry: u32) -> Duration {
+        backoff(
+            retry,
+            Duration::from_millis(self.initial_delay_ms),
+            Duration::from_millis(self.max_delay_ms),
+        )
+    }
+}
+
+/// Body of `POST /mqtt/consumers`, and one entry of the `CONSUMERS_CONFIG` file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConsumerSpec {
+    /// Queue to consume, or topic filter over MQTT
+    pub queue: String,
+    /// Unacked messages the broker sends ahead; AMQP only
+    pub prefetch: u16,
+    /// QoS of the subscription, 0 to 2; MQTT only
+    pub qos: u8,
+    /// Messages handled at once
+    pub concurrency: usize,
+    pub handler: HandlerSpec,
+    pub on_failure: FailurePolicy,
+    /// Check messages against the schema registered for the queue or their routing key.
+    /// Over AMQP the ones that fail go to the dead-letter queue unhandled; other
+    /// transports skip them.
+    pub validate_schema: bool,
+}
+
+impl Default for ConsumerSpec {
+    fn default() -> Self {
+        ConsumerSpec {
+            queue: DEFAULT_QUEUE.to_string(),
+            prefetch: DEFAULT_PREFETCH,
+            qos: DEFAULT_QOS,
+            concurrency: 1,
+            handler: HandlerSpec::default(),
+            on_failure: FailurePolicy::default(),
+            validate_schema: false,
+        }
+    }
+}
+
+impl ConsumerSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        let queue = self.queue.trim();
+        if queue.is_empty() || queue.len() > MAX_QUEUE_BYTES || queue.contains('\0') {
+            return Err("queue must be 1 to 255 bytes without NUL characters".to_string());
+        }
+        if self.prefetch == 0 {
+            return Err("prefetch must be at least 1".to_string());
+        }
+        if self.qos > MAX_QOS {
+            return Err("qos must be 0, 1 or 2".to_string());
+        }
+        if self.concurrency == 0 || self.concurrency > MAX_CONCURRENCY {
+            return Err(format!("concurrency must be 1 to {}", MAX_CONCURRENCY));
+        }
+        self.on_failure.validate()?;
+        if (self.on_failure.uses_failure_queues() || self.validate_schema)
+            && queue.len() > MAX_RETRIED_QUEUE_BYTES
+        {
+            return Err(format!(
+                "queue must be at most {} bytes to retry or dead-letter",
+                MAX_RETRIED_QUEUE_BYTES
+            ));
+        }
+        match &self.handler {
+            HandlerSpec::Webhook { url }
+                if !(url.starts_with("http://") || url.starts_with("https://")) =>
+            {
+                Err("webhook url must start with http:// or https://".to_string())
+            }
+            HandlerSpec::Dynamodb { table } if table.trim().is_empty() => {
+                Err("dynamodb table must not be empty".to_string())
+            }
+            HandlerSpec::Rpc { responder } if responder.trim().is_empty() => {
+                Err("rpc responder must not be empty".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A message as handed to a worker's handler, from either transport
+#[derive(Debug, Clone)]
+pub struct ConsumedMessage {
+    /// The broker's message id, or a generated one when it has none
+    pub id: String,
+    /// Queue it was consumed from, or the topic it was published to over MQTT
+    pub source: String,
+    /// Exchange it was published to; empty for the default exchange and over MQTT
+    pub exchange: String,
+    /// Routing key it was published with, or the topic over MQTT
+    pub routing_key: String,
+    pub headers: BTreeMap<String, Value>,
+    pub body: Bytes,
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    /// Ties an RPC reply to its request
+    pub correlation_id: Option<String>,
+    /// Queue an RPC request wants its reply on
+    pub reply_to: Option<String>,
+    pub priority: Option<u8>,
+    /// Milliseconds the message could wait in a queue, as the publisher set it
+    pub expiration: Option<String>,
+    /// When the publisher sent it, to the second
+    pub timestamp: Option<DateTime<Utc>>,
+    pub app_id: Option<String>,
+    /// Delivered before and requeued (AMQP), or sent from the retained store (MQTT)
+    pub redelivered: bool,
+    pub received_at: DateTime<Utc>,
+}
+
+impl ConsumedMessage {
+    pub fn from_delivery(queue: &str, delivery: &Delivery) -> Self {
+        ConsumedMessage::from_properties(
+            queue,
+            delivery.exchange.as_str(),
+            delivery.routing_key.as_str(),
+            &delivery.properties,
+            Bytes::copy_from_slice(&delivery.data),
+            delivery.redelivered,
+        )
+    }
+
+    /// A message that came with AMQP properties, from RabbitMQ or the in-memory broker
+    pub fn from_properties(
+        source: &str,
+        exchange: &str,
+        routing_key: &str,
+        properties: &BasicProperties,
+        body: Bytes,
+        redelivered: bool,
+    ) -> Self {
+        ConsumedMessage {
+            id: properties
+                .message_id()
+                .as_ref()
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            source: source.to_string(),
+            exchange: exchange.to_string(),
+            routing_key: routing_key.to_string(),
+            headers: properties
+                .headers()
+                .as_ref()
+                .map(headers_json)
+                .unwrap_or_default(),
+            body,
+            content_type: properties.content_type().as_ref().map(|ct| ct.to_string()),
+            content_encoding: properties
+                .content_encoding()
+                .as_ref()
+                .map(|encoding| encoding.to_string()),
+            correlation_id: properties
+                .correlation_id()
+                .as_ref()
+                .map(|id| id.to_string()),
+            reply_to: properties
+                .reply_to()
+                .as_ref()
+                .map(|queue| queue.to_string()),
+            priority: *properties.priority(),
+            expiration: properties
+                .expiration()
+                .as_ref()
+                .map(|expiration| expiration.to_string()),
+            timestamp: properties
+                .timestamp()
+                .and_then(|seconds| DateTime::from_timestamp(seconds as i64, 0)),
+            app_id: properties.app_id().as_ref().map(|id| id.to_string()),
+            redelivered,
+            received_at: Utc::now(),
+        }
+    }
+
+    /// MQTT 3.1.1 has no message ids or headers, so the id is generated
+    pub fn from_mqtt(message: MqttMessage) -> Self {
+        ConsumedMessage {
+            id: Uuid::new_v4().to_string(),
+            source: message.topic.clone(),
+            exchange: String::new(),
+            routing_key: message.topic,
+            headers: BTreeMap::new(),
+            body: message.payload,
+            content_type: None,
+            content_encoding: None,
+            correlation_id: None,
+            reply_to: None,
+            priority: None,
+            expiration: None,
+            timestamp: None,
+            app_id: None,
+            redelivered: message.retain,
+            received_at: Utc::now(),
+        }
+    }
+
+    /// The body as JSON when it parses, otherwise as text
+    pub fn body_value(&self) -> Value {
+        serde_json::from_slice(&self.body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&self.body).into_owned()))
+    }
+
+    /// What the webhook handler posts and the stream endpoints push
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "source": self.source,
+            "exchange": self.exchange,
+            "routing_key": self.routing_key,
+            "headers": self.headers,
+            "content_type": self.content_type,
+            "content_encoding": self.content_encoding,
+            "correlation_id": self.correlation_id,
+            "reply_to": self.reply_to,
+            "priority": self.priority,
+            "expiration": self.expiration,
+            "timestamp": self.timestamp,
+            "app_id": self.app_id,
+            "redelivered": self.redelivered,
+            "received_at": self.received_at,
+            "body": self.body_value()
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerStatus {
+    Starting,
+    Running,
+    /// Lost its channel and waits to consume again
+    Reconnecting,
+    Stopping,
+    Stopped,
+}
+
+/// What `GET /mqtt/consumers` reports per worker
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerSnapshot {
+    pub id: String,
+    pub transport: &'static str,
+    pub spec: ConsumerSpec,
+    pub status: WorkerStatus,
+    pub started_at: DateTime<Utc>,
+    pub received: u64,
+    pub processed: u64,
+    pub failed: u64,
+    pub in_flight: u64,
+    /// Failed messages put back on the queue for one more try
+    pub requeued: u64,
+    /// Failed messages sent to a delay queue
+    pub retried: u64,
+    pub dead_lettered: u64,
+    /// Messages that did not match their schema, with `validate_schema`
+    pub invalid: u64,
+    pub reconnects: u64,
+    pub last_error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_specs_default_and_validate() {
+        let spec: ConsumerSpec =
+            serde_json::from_value(json!({"queue": "orders", "concurrency": 4})).unwrap();
+        assert_eq!(spec.prefetch, DEFAULT_PREFETCH);
+        assert_eq!(spec.handler, HandlerSpec::Log);
+        assert!(spec.validate().is_ok());
+
+        for body in [
+            json!({"queue": ""}),
+            json!({"prefetch": 0}),
+            json!({"qos": 3}),
+            json!({"concurrency": 0}),
+            json!({"handler": {"type": "webhook", "url": "ftp://example.com"}}),
+            json!({"handler": {"type": "dynamodb", "table": " "}}),
+            json!({"handler": {"type": "rpc", "responder": ""}}),
+        ] {
+            let spec: ConsumerSpec = serde_json::from_value(body).unwrap();
+            assert!(spec.validate().is_err());
+        }
+        assert!(
+            serde_json::from_value::<ConsumerSpec>(json!({"handler": {"type": "email"}})).is_err()
+        );
+    }
+
+    #[test]
+    fn test_failure_policy_backs_off_and_validates() {
+        let spec: ConsumerSpec = serde_json::from_value(json!({
+            "queue": "orders",
+            "on_failure": {"strategy": "retry", "max_attempts": 4, "max_delay_ms": 5000}
+        }))
+        .unwrap();
+        let policy = &spec.on_failure;
+        assert!(spec.validate().is_ok());
+        assert!(policy.uses_failure_queues() && !policy.dead_letter);
+        let delays: Vec<u128> = (1..=4)
+            .map(|retry| policy.retry_delay(retry).as_millis())
+            .collect();
+        assert_eq!(delays, vec![1000, 2000, 4000, 5000]);
+
+        assert!(!ConsumerSpec::default().on_failure.uses_failure_queues());
+        for on_failure in [
+            json!({"max_attempts": 0}),
+            json!({"initial_delay_ms": 0}),
+            json!({"initial_delay_ms": 10, "max_delay_ms": 5}),
+            json!({"max_delay_ms": MAX_DELAY_MS + 1}),
+        ] {
+            let spec: ConsumerSpec =
+                serde_json::from_value(json!({"on_failure": on_failure})).unwrap();
+            assert!(spec.validate().is_err());
+        }
+        let long_queue: ConsumerSpec = serde_json::from_value(json!({
+            "queue": "q".repeat(250),
+            "on_failure": {"dead_letter": true}
+        }))
+        .unwrap();
+        assert!(long_queue.validate().is_err());
+    }
+
+    #[test]
+    fn test_message_body_is_json_or_text() {
+        let message = |body: &'static str| ConsumedMessage {
+            id: "m-1".to_string(),
+            source: "orders".to_string(),
+            exchange: String::new(),
+            routing_key: "orders".to_string(),
+            headers: BTreeMap::new(),
+            body: Bytes::from(body),
+            content_type: None,
+            content_encoding: None,
+            correlation_id: None,
+            reply_to: None,
+            priority: None,
+            expiration: None,
+            timestamp: None,
+            app_id: None,
+            redelivered: false,
+            received_at: Utc::now(),
+        };
+        assert_eq!(message(r#"{"n":1}"#).to_json()["body"], json!({"n": 1}));
+        assert_eq!(message("hello").body_value(), json!("hello"));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::model::k6::{K6ParseError, K6Reader, K6Record, MetricPoint};
+use crate::model::report::{CoverageData, CoverageSummary, EndpointStats, TestInfo};
+use crate::model::route::{find_route, RouteInfo};
+use chrono::{DateTime, FixedOffset};
+use std::io::BufRead;
+
+#[derive(Debug, Default)]
+struct RouteSamples {
+    hits: u64,
+    errors: u64,
+    duration_sum: f64,
+    duration_count: u64,
+}
+
+/// Matches k6 HTTP samples against the routes the server registers, so coverage
+/// reflects what the binary serves rather than a hand-maintained list
+#[derive(Debug)]
+pub struct CoverageCalculator<'a> {
+    routes: &'a [RouteInfo],
+    samples: Vec<RouteSamples>,
+    iterations: u64,
+    vus: u64,
+    started_at: Option<DateTime<FixedOffset>>,
+    finished_at: Option<DateTime<FixedOffset>>,
+}
+
+impl<'a> CoverageCalculator<'a> {
+    pub fn new(routes: &'a [RouteInfo]) -> Self {
+        Self {
+            routes,
+            samples: routes.iter().map(|_| RouteSamples::default()).collect(),
+            iterations: 0,
+            vus: 0,
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
+    pub fn push(&mut self, record: &K6Record) {
+        if let K6Record::Point { metric, data } = record {
+            self.push_point(metric, data);
+        }
+    }
+
+    fn push_point(&mut self, metric: &str, point: &MetricPoint) {
+        self.started_at = Some(self.started_at.map_or(point.time, |t| t.min(point.time)));
+        self.finished_at = Some(self.finished_at.map_or(point.time, |t| t.max(point.time)));
+
+        match metric {
+            "iterations" => self.iterations += point.value as u64,
+            "vus" => self.vus = self.vus.max(point.value as u64),
+            "http_reqs" | "http_req_duration" => {
+                let (Some(method), Some(path)) = (point.tags.method.as_deref(), point.tags.path())
+                else {
+                    return;
+                };
+                let Some(index) = find_route(self.routes, method, path)
+                    .and_then(|route| self.routes.iter().position(|r| r == route))
+                else {
+                    return;
+                };
+
+                let samples = &mut self.samples[index];
+                if metric == "http_reqs" {
+                    samples.hits += 1;
+                    if point.tags.is_failed() {
+                        samples.errors += 1;
+                    }
+                } else {
+                    samples.duration_sum += point.value;
+                    samples.duration_count += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn finish(self) -> CoverageData {
+        let duration = match (self.started_at, self.finished_at) {
+            (Some(start), Some(end)) => (end - start).num_milliseconds() as f64 / 1000.0,
+            _ => 0.0,
+        };
+
+        let endpoint_coverage = self
+            .routes
+            .iter()
+            .zip(self.samples)
+            .map(|(route, samples)| {
+                let success_rate = if samples.hits > 0 {
+                    (samples.hits - samples.errors) as f64 / samples.hits as f64 * 100.0
+                } else {
+                    0.0
+                };
+                let avg_response_time = if samples.duration_count > 0 {
+                    samples.duration_sum / samples.duration_count as f64
+                } else {
+                    0.0
+                };
+                let stats = EndpointStats {
+                    hits: samples.hits,
+                    success_rate: format!("{:.1}%", success_rate),
+                    avg_response_time: format!("{:.2}ms", avg_response_time),
+                    errors: samples.errors,
+                    tested: samples.hits > 0,
+                };
+                (route.endpoint(), stats)
+            })
+            .collect::<std::collections::BTreeMap<_, _>>();
+
+        let total_endpoints = endpoint_coverage.len() as u64;
+        let tested_endpoints = endpoint_coverage.values().filter(|s| s.tested).count() as u64;
+        let coverage_percentage = if total_endpoints > 0 {
+            tested_endpoints as f64 / total_endpoints as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        CoverageData {
+            test_info: TestInfo {
+                timestamp: self.started_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                duration,
+                iterations: self.iterations,
+                vus: self.vus,
+            },
+            endpoint_coverage,
+            summary: CoverageSummary {
+                total_endpoints,
+                tested_endpoints,
+                coverage_percentage: format!("{:.1}%", coverage_percentage),
+            },
+        }
+    }
+
+    /// Compute coverage for a whole k6 stream without holding the file in memory
+    pub fn calculate<R: BufRead>(
+        routes: &[RouteInfo],
+        reader: K6Reader<R>,
+    ) -> Result<CoverageData, K6ParseError> {
+        let mut calculator = CoverageCalculator::new(routes);
+        for record in reader {
+            calculator.push(&record?);
+        }
+        Ok(calculator.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STREAM: &str = r#"{"metric":"http_reqs","type":"Point","data":{"time":"2025-06-29T21:43:22+07:00","value":1,"tags":{"method":"GET","status":"200","url":"http://127.0.0.1:8000/user/users/7"}}}
+{"metric":"http_req_duration","type":"Point","data":{"time":"2025-06-29T21:43:22+07:00","value":10,"tags":{"method":"GET","status":"200","url":"http://127.0.0.1:8000/user/users/7"}}}
+{"metric":"http_reqs","type":"Point","data":{"time":"2025-06-29T21:43:24+07:00","value":1,"tags":{"method":"GET","status":"500","url":"http://127.0.0.1:8000/user/users/8?full=1"}}}
+{"metric":"http_req_duration","type":"Point","data":{"time":"2025-06-29T21:43:24+07:00","value":30,"tags":{"method":"GET","status":"500","url":"http://127.0.0.1:8000/user/users/8?full=1"}}}
+{"metric":"http_reqs","type":"Point","data":{"time":"2025-06-29T21:43:25+07:00","value":1,"tags":{"method":"GET","status":"404","url":"http://127.0.0.1:8000/health"}}}
+{"metric":"iterations","type":"Point","data":{"time":"2025-06-29T21:43:25+07:00","value":1,"tags":null}}
+{"metric":"vus","type":"Point","data":{"time":"2025-06-29T21:43:26+07:00","value":5,"tags":null}}
+"#;
+
+    #[test]
+    fn test_coverage_from_k6_stream() {
+        let routes = vec![
+            RouteInfo::new("GET", "/user/users/:id"),
+            RouteInfo::new("POST", "/user/users"),
+        ];
+        let coverage =
+            CoverageCalculator::calculate(&routes, K6Reader::new(STREAM.as_bytes())).unwrap();
+
+        let tested = &coverage.endpoint_coverage["GET /user/users/:id"];
+        assert_eq!(tested.hits, 2);
+        assert_eq!(tested.errors, 1);
+        assert_eq!(tested.success_rate, "50.0%");
+        assert_eq!(tested.avg_response_time, "20.00ms");
+        assert!(!coverage.endpoint_coverage["POST /user/users"].tested);
+
+        // Requests to paths the server does not serve are not counted
+        assert_eq!(coverage.endpoint_coverage.len(), 2);
+        assert_eq!(coverage.summary.tested_endpoints, 1);
+        assert_eq!(coverage.summary.percentage(), 50.0);
+        assert_eq!(coverage.test_info.duration, 4.0);
+        assert_eq!(coverage.test_info.iterations, 1);
+        assert_eq!(coverage.test_info.vus, 5);
+    }
+}
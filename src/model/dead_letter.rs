@@ -0,0 +1,97 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Direct exchange every dead-lettered message goes through, routed by its source queue
+pub const DEAD_LETTER_EXCHANGE: &str = "rust-api.dead-letter";
+
+/// Retries the message has been through so far
+pub const RETRY_COUNT_HEADER: &str = "x-retry-count";
+/// The handler's error from the last failed attempt
+pub const FAILURE_REASON_HEADER: &str = "x-failure-reason";
+pub const FAILED_AT_HEADER: &str = "x-failed-at";
+/// Queue the message was consumed from, which a replay sends it back to
+pub const ORIGINAL_QUEUE_HEADER: &str = "x-original-queue";
+
+/// Headers a replay strips, so the message starts over with a clean count
+pub const FAILURE_HEADERS: [&str; 4] = [
+    RETRY_COUNT_HEADER,
+    FAILURE_REASON_HEADER,
+    FAILED_AT_HEADER,
+    ORIGINAL_QUEUE_HEADER,
+];
+
+const DEFAULT_REPLAY: usize = 1000;
+const MAX_REPLAY: usize = 100_000;
+
+/// The dead-letter queue of a consumed queue
+pub fn dead_letter_queue(queue: &str) -> String {
+    format!("{}.dlq", queue)
+}
+
+/// Messages wait out `delay` here, then expire back into `queue`. One queue per delay keeps
+/// every message in it on the same TTL, so none waits behind a longer one.
+pub fn delay_queue(queue: &str, delay: Duration) -> String {
+    format!("{}.retry.{}", queue, delay.as_millis())
+}
+
+/// Retries recorded on a message; 0 for a first delivery
+pub fn retry_count(headers: &BTreeMap<String, Value>) -> u32 {
+    headers
+        .get(RETRY_COUNT_HEADER)
+        .and_then(Value::as_u64)
+        .map_or(0, |count| count.min(u32::MAX as u64) as u32)
+}
+
+/// Query of `POST /mqtt/dlq/:queue/replay`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplayQuery {
+    #[serde(default = "default_replay")]
+    pub n: usize,
+}
+
+fn default_replay() -> usize {
+    DEFAULT_REPLAY
+}
+
+impl ReplayQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.n == 0 || self.n > MAX_REPLAY {
+            return Err(format!("n must be 1 to {}", MAX_REPLAY));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_queue_names() {
+        assert_eq!(dead_letter_queue("orders"), "orders.dlq");
+        assert_eq!(
+            delay_queue("orders", Duration::from_secs(4)),
+            "orders.retry.4000"
+        );
+    }
+
+    #[test]
+    fn test_retry_count_reads_the_header() {
+        let mut headers = BTreeMap::new();
+        assert_eq!(retry_count(&headers), 0);
+        headers.insert(RETRY_COUNT_HEADER.to_string(), json!(3));
+        assert_eq!(retry_count(&headers), 3);
+        headers.insert(RETRY_COUNT_HEADER.to_string(), json!("3"));
+        assert_eq!(retry_count(&headers), 0);
+    }
+
+    #[test]
+    fn test_replay_query_defaults_and_validates() {
+        let replay: ReplayQuery = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(replay.n, DEFAULT_REPLAY);
+        assert!(ReplayQuery { n: MAX_REPLAY + 1 }.validate().is_err());
+    }
+}
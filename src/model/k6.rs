@@ -0,0 +1,792 @@
+use crate::model::route::endpoint_matches;
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// One line of a k6 `--out json` stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum K6Record {
+    Metric {
+        metric: String,
+        data: MetricDefinition,
+    },
+    Point {
+        metric: String,
+        data: MetricPoint,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricType {
+    Counter,
+    Gauge,
+    Rate,
+    Trend,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricDefinition {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub metric_type: MetricType,
+    #[serde(default)]
+    pub contains: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub thresholds: Vec<String>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub submetrics: Vec<Submetric>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Submetric {
+    pub name: String,
+    #[serde(default)]
+    pub suffix: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricPoint {
+    pub time: DateTime<FixedOffset>,
+    pub value: f64,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub tags: PointTags,
+}
+
+/// The tags k6 attaches to HTTP samples; anything else ends up in `extra`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PointTags {
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "status_code")]
+    pub status: Option<u16>,
+    pub group: Option<String>,
+    #[serde(default, deserialize_with = "string_bool")]
+    pub expected_response: Option<bool>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+impl PointTags {
+    /// `METHOD /path` for HTTP samples. A custom `name` tag wins over the URL so that
+    /// k6 scripts can group parameterised URLs themselves.
+    pub fn endpoint(&self) -> Option<String> {
+        let method = self.method.as_deref()?;
+        let target = self
+            .name
+            .as_deref()
+            .filter(|name| !name.contains("://"))
+            .or(self.url.as_deref())?;
+
+        Some(format!("{} {}", method, url_path(target)))
+    }
+
+    /// The concrete request path, without scheme, host or query string
+    pub fn path(&self) -> Option<&str> {
+        self.url.as_deref().map(url_path)
+    }
+
+    /// Whether the request counts as failed, following k6's `http_req_failed` rules
+    pub fn is_failed(&self) -> bool {
+        match self.expected_response {
+            Some(expected) => !expected,
+            None => matches!(self.status, Some(status) if status == 0 || status >= 400),
+        }
+    }
+}
+
+/// Strip scheme, host and query string from a URL
+fn url_path(url: &str) -> &str {
+    let path = match url.find("://") {
+        Some(scheme_end) => {
+            let rest = &url[scheme_end + 3..];
+            rest.find('/').map(|i| &rest[i..]).unwrap_or("/")
+        }
+        None => url,
+    };
+    path.split(['?', '#']).next().unwrap_or(path)
+}
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+fn status_code<'de, D>(deserializer: D) -> Result<Option<u16>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::String(s)) => s.parse().ok(),
+        Some(Value::Number(n)) => n.as_u64().and_then(|n| u16::try_from(n).ok()),
+        _ => None,
+    })
+}
+
+fn string_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::String(s)) => s.parse().ok(),
+        Some(Value::Bool(b)) => Some(b),
+        _ => None,
+    })
+}
+
+#[derive(Debug)]
+pub enum K6ParseError {
+    Io(io::Error),
+    Json {
+        line: usize,
+        source: serde_json::Error,
+    },
+}
+
+impl fmt::Display for K6ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            K6ParseError::Io(e) => write!(f, "Failed to read k6 output: {}", e),
+            K6ParseError::Json { line, source } => {
+                write!(f, "Invalid k6 record on line {}: {}", line, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for K6ParseError {}
+
+impl From<io::Error> for K6ParseError {
+    fn from(e: io::Error) -> Self {
+        K6ParseError::Io(e)
+    }
+}
+
+/// Reads a k6 NDJSON stream one line at a time
+pub struct K6Reader<R> {
+    reader: R,
+    buffer: String,
+    line: usize,
+}
+
+impl<R: BufRead> K6Reader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: String::new(),
+            line: 0,
+        }
+    }
+}
+
+impl K6Reader<BufReader<File>> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> Iterator for K6Reader<R> {
+    type Item = Result<K6Record, K6ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buffer.clear();
+            self.line += 1;
+
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+
+            let line = self.buffer.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            return Some(
+                serde_json::from_str(line).map_err(|source| K6ParseError::Json {
+                    line: self.line,
+                    source,
+                }),
+            );
+        }
+    }
+}
+
+/// Summary statistics for a series of samples
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MetricStats {
+    pub count: u64,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    /// Share of non-zero samples, which is what k6 reports for `rate` metrics
+    pub rate: f64,
+}
+
+impl MetricStats {
+    /// Sorts `values` in place, so summarizing them again later is cheap
+    fn from_samples(values: &mut [f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+
+        values.sort_by(|a, b| a.total_cmp(b));
+        let count = values.len();
+        let sum: f64 = values.iter().sum();
+        let non_zero = values.iter().filter(|v| **v != 0.0).count();
+
+        Self {
+            count: count as u64,
+            sum,
+            min: values[0],
+            max: values[count - 1],
+            avg: sum / count as f64,
+            p50: percentile(values, 0.50),
+            p90: percentile(values, 0.90),
+            p95: percentile(values, 0.95),
+            p99: percentile(values, 0.99),
+            rate: non_zero as f64 / count as f64,
+        }
+    }
+}
+
+/// Linear interpolation between closest ranks, the same way k6 computes `p(N)`
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    match sorted.len() {
+        0 => 0.0,
+        1 => sorted[0],
+        len => {
+            let rank = pct * (len - 1) as f64;
+            let lower = sorted[rank.floor() as usize];
+            let upper = sorted[rank.ceil() as usize];
+            lower + (upper - lower) * rank.fract()
+        }
+    }
+}
+
+/// Ratio between neighbouring histogram buckets. A bucket's value is within about 1% of
+/// every sample in it.
+const HISTOGRAM_GROWTH: f64 = 1.02;
+
+/// Samples counted in log-scale buckets, so memory depends on how spread out the values
+/// are rather than on how many there are. Everything but the percentiles is exact.
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    non_zero: u64,
+    /// Keyed by sign, then by the bucket's exponent; negative exponents are flipped so
+    /// the keys sort the same way as the values
+    buckets: BTreeMap<(i8, i64), u64>,
+}
+
+impl Histogram {
+    fn push(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += 1;
+        self.sum += value;
+        if value != 0.0 {
+            self.non_zero += 1;
+        }
+        *self.buckets.entry(bucket_key(value)).or_default() += 1;
+    }
+
+    fn stats(&self) -> MetricStats {
+        if self.count == 0 {
+            return MetricStats::default();
+        }
+
+        MetricStats {
+            count: self.count,
+            sum: self.sum,
+            min: self.min,
+            max: self.max,
+            avg: self.sum / self.count as f64,
+            p50: self.percentile(0.50),
+            p90: self.percentile(0.90),
+            p95: self.percentile(0.95),
+            p99: self.percentile(0.99),
+            rate: self.non_zero as f64 / self.count as f64,
+        }
+    }
+
+    /// The same interpolation as `percentile`, with each sample taken at its bucket's value
+    fn percentile(&self, pct: f64) -> f64 {
+        let rank = pct * (self.count - 1) as f64;
+        let lower = self.value_at(rank.floor() as u64);
+        let upper = self.value_at(rank.ceil() as u64);
+        lower + (upper - lower) * rank.fract()
+    }
+
+    fn value_at(&self, rank: u64) -> f64 {
+        let mut seen = 0;
+        for (&(sign, exponent), count) in &self.buckets {
+            seen += count;
+            if rank < seen {
+                let value = match sign {
+                    0 => 0.0,
+                    1 => HISTOGRAM_GROWTH.powi(exponent as i32),
+                    _ => -HISTOGRAM_GROWTH.powi(-exponent as i32),
+                };
+                return value.clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+}
+
+fn bucket_key(value: f64) -> (i8, i64) {
+    if value == 0.0 || !value.is_finite() {
+        return (0, 0);
+    }
+    let exponent = (value.abs().ln() / HISTOGRAM_GROWTH.ln()).round() as i64;
+    if value > 0.0 {
+        (1, exponent)
+    } else {
+        (-1, -exponent)
+    }
+}
+
+/// One metric's samples: every value, or a histogram of them for aggregators that run as
+/// long as a live test does
+#[derive(Debug, Clone)]
+enum Samples {
+    All(Vec<f64>),
+    Histogram(Histogram),
+}
+
+impl Samples {
+    fn new(histogram: bool) -> Self {
+        if histogram {
+            Samples::Histogram(Histogram::default())
+        } else {
+            Samples::All(Vec::new())
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        match self {
+            Samples::All(values) => values.push(value),
+            Samples::Histogram(histogram) => histogram.push(value),
+        }
+    }
+
+    fn stats(&mut self) -> MetricStats {
+        match self {
+            Samples::All(values) => MetricStats::from_samples(values),
+            Samples::Histogram(histogram) => histogram.stats(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricSummary {
+    pub metric_type: Option<MetricType>,
+    pub thresholds: Vec<String>,
+    pub stats: MetricStats,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EndpointSummary {
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub status_codes: BTreeMap<u16, u64>,
+    /// `http_req_duration` for this endpoint
+    pub duration: MetricStats,
+    pub metrics: BTreeMap<String, MetricStats>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CheckCounts {
+    pub passes: u64,
+    pub fails: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct K6Aggregates {
+    pub started_at: Option<DateTime<FixedOffset>>,
+    pub finished_at: Option<DateTime<FixedOffset>>,
+    pub metrics: BTreeMap<String, MetricSummary>,
+    pub endpoints: BTreeMap<String, EndpointSummary>,
+    /// Endpoints pooled under the route templates given to `K6Aggregator::with_groups`.
+    /// Templates that no sample matched are missing.
+    pub groups: BTreeMap<String, EndpointSummary>,
+    pub checks: BTreeMap<String, CheckCounts>,
+}
+
+impl K6Aggregates {
+    /// Wall-clock time between the first and last sample
+    pub fn duration_ms(&self) -> u64 {
+        match (self.started_at, self.finished_at) {
+            (Some(start), Some(end)) => (end - start).num_milliseconds().max(0) as u64,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct EndpointSamples {
+    requests: u64,
+    errors: u64,
+    status_codes: BTreeMap<u16, u64>,
+    metrics: BTreeMap<String, Samples>,
+}
+
+impl EndpointSamples {
+    fn push(&mut self, metric: &str, point: &MetricPoint, histograms: bool) {
+        if metric == "http_reqs" {
+            self.requests += 1;
+            if point.tags.is_failed() {
+                self.errors += 1;
+            }
+            if let Some(status) = point.tags.status {
+                *self.status_codes.entry(status).or_default() += 1;
+            }
+        }
+        self.metrics
+            .entry(metric.to_string())
+            .or_insert_with(|| Samples::new(histograms))
+            .push(point.value);
+    }
+
+    fn summary(&mut self) -> EndpointSummary {
+        let metrics: BTreeMap<String, MetricStats> = self
+            .metrics
+            .iter_mut()
+            .map(|(name, values)| (name.clone(), values.stats()))
+            .collect();
+        EndpointSummary {
+            requests: self.requests,
+            errors: self.errors,
+            error_rate: if self.requests > 0 {
+                self.errors as f64 / self.requests as f64
+            } else {
+                0.0
+            },
+            status_codes: self.status_codes.clone(),
+            duration: metrics
+                .get("http_req_duration")
+                .cloned()
+                .unwrap_or_default(),
+            metrics,
+        }
+    }
+}
+
+/// Folds k6 records into per-metric and per-endpoint statistics
+#[derive(Debug, Clone, Default)]
+pub struct K6Aggregator {
+    definitions: BTreeMap<String, MetricDefinition>,
+    samples: BTreeMap<String, Samples>,
+    endpoints: BTreeMap<String, EndpointSamples>,
+    groups: BTreeMap<String, EndpointSamples>,
+    /// The groups each endpoint seen so far falls under
+    endpoint_groups: HashMap<String, Vec<String>>,
+    checks: BTreeMap<String, CheckCounts>,
+    started_at: Option<DateTime<FixedOffset>>,
+    finished_at: Option<DateTime<FixedOffset>>,
+    /// Keep histograms instead of every sample
+    histograms: bool,
+}
+
+impl K6Aggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// For streams of any length, such as live runs: memory stays flat and snapshots are
+    /// cheap, but percentiles are only accurate to about 1%
+    pub fn streaming() -> Self {
+        Self {
+            histograms: true,
+            ..Self::default()
+        }
+    }
+
+    /// Also pool the samples of every endpoint under each of these keys, which are
+    /// endpoints or route templates such as `GET /users/:id`
+    pub fn with_groups(mut self, groups: impl IntoIterator<Item = String>) -> Self {
+        self.groups = groups
+            .into_iter()
+            .map(|group| (group, EndpointSamples::default()))
+            .collect();
+        self
+    }
+
+    pub fn push(&mut self, record: K6Record) {
+        match record {
+            K6Record::Metric { metric, data } => {
+                self.definitions.insert(metric, data);
+            }
+            K6Record::Point { metric, data } => self.push_point(metric, data),
+        }
+    }
+
+    fn push_point(&mut self, metric: String, point: MetricPoint) {
+        self.started_at = Some(match self.started_at {
+            Some(start) => start.min(point.time),
+            None => point.time,
+        });
+        self.finished_at = Some(match self.finished_at {
+            Some(end) => end.max(point.time),
+            None => point.time,
+        });
+
+        if metric == "checks" {
+            if let Some(Value::String(check)) = point.tags.extra.get("check") {
+                let counts = self.checks.entry(check.clone()).or_default();
+                if point.value != 0.0 {
+                    counts.passes += 1;
+                } else {
+                    counts.fails += 1;
+                }
+            }
+        }
+
+        if let Some(endpoint) = point.tags.endpoint() {
+            if !self.groups.is_empty() {
+                let groups = self
+                    .endpoint_groups
+                    .entry(endpoint.clone())
+                    .or_insert_with(|| {
+                        self.groups
+                            .keys()
+                            .filter(|group| endpoint_matches(group, &endpoint))
+                            .cloned()
+                            .collect()
+                    });
+                for group in groups.iter() {
+                    if let Some(samples) = self.groups.get_mut(group) {
+                        samples.push(&metric, &point, self.histograms);
+                    }
+                }
+            }
+            self.endpoints
+                .entry(endpoint)
+                .or_default()
+                .push(&metric, &point, self.histograms);
+        }
+
+        self.samples
+            .entry(metric)
+            .or_insert_with(|| Samples::new(self.histograms))
+            .push(point.value);
+    }
+
+    pub fn finish(mut self) -> K6Aggregates {
+        self.snapshot()
+    }
+
+    /// Aggregates of everything pushed so far, leaving the aggregator usable
+    pub fn snapshot(&mut self) -> K6Aggregates {
+        let metrics = self
+            .samples
+            .iter_mut()
+            .map(|(name, samples)| {
+                let definition = self.definitions.get(name);
+                let summary = MetricSummary {
+                    metric_type: definition.map(|d| d.metric_type),
+                    thresholds: definition.map(|d| d.thresholds.clone()).unwrap_or_default(),
+                    stats: samples.stats(),
+                };
+                (name.clone(), summary)
+            })
+            .collect();
+
+        let endpoints = self
+            .endpoints
+            .iter_mut()
+            .map(|(endpoint, samples)| (endpoint.clone(), samples.summary()))
+            .collect();
+        let groups = self
+            .groups
+            .iter_mut()
+            .filter(|(_, samples)| !samples.metrics.is_empty())
+            .map(|(group, samples)| (group.clone(), samples.summary()))
+            .collect();
+
+        K6Aggregates {
+            started_at: self.started_at,
+            finished_at: self.finished_at,
+            metrics,
+            endpoints,
+            groups,
+            checks: self.checks.clone(),
+        }
+    }
+
+    /// Parse and aggregate a whole k6 stream without holding the file in memory
+    pub fn aggregate<R: BufRead>(reader: K6Reader<R>) -> Result<K6Aggregates, K6ParseError> {
+        Self::new().aggregate_reader(reader)
+    }
+
+    /// `aggregate` for an aggregator that has been set up first, e.g. with groups
+    pub fn aggregate_reader<R: BufRead>(
+        mut self,
+        reader: K6Reader<R>,
+    ) -> Result<K6Aggregates, K6ParseError> {
+        for record in reader {
+            self.push(record?);
+        }
+        Ok(self.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{"type":"Metric","data":{"name":"http_req_duration","type":"trend","contains":"time","thresholds":["p(95)<500"],"submetrics":null},"metric":"http_req_duration"}
+{"type":"Metric","data":{"name":"http_reqs","type":"counter","contains":"default","thresholds":[],"submetrics":null},"metric":"http_reqs"}
+{"metric":"http_reqs","type":"Point","data":{"time":"2025-06-29T21:43:22.83923+07:00","value":1,"tags":{"expected_response":"true","group":"::setup","method":"GET","name":"http://127.0.0.1:8000/","status":"200","url":"http://127.0.0.1:8000/"}}}
+{"metric":"http_req_duration","type":"Point","data":{"time":"2025-06-29T21:43:22.83923+07:00","value":10,"tags":{"expected_response":"true","method":"GET","name":"http://127.0.0.1:8000/","status":"200","url":"http://127.0.0.1:8000/"}}}
+
+{"metric":"http_reqs","type":"Point","data":{"time":"2025-06-29T21:43:24.5+07:00","value":1,"tags":{"expected_response":"false","method":"GET","name":"http://127.0.0.1:8000/","status":"500","url":"http://127.0.0.1:8000/"}}}
+{"metric":"http_req_duration","type":"Point","data":{"time":"2025-06-29T21:43:24.5+07:00","value":30,"tags":{"expected_response":"false","method":"GET","name":"http://127.0.0.1:8000/","status":"500","url":"http://127.0.0.1:8000/"}}}
+{"metric":"vus","type":"Point","data":{"time":"2025-06-29T21:43:25+07:00","value":5,"tags":null}}
+{"metric":"checks","type":"Point","data":{"time":"2025-06-29T21:43:24.5+07:00","value":0,"tags":{"check":"status is 200","group":""}}}
+"#;
+
+    #[test]
+    fn test_reader_parses_records() {
+        let records: Vec<K6Record> = K6Reader::new(SAMPLE.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 8);
+
+        match &records[2] {
+            K6Record::Point { metric, data } => {
+                assert_eq!(metric, "http_reqs");
+                assert_eq!(data.tags.method.as_deref(), Some("GET"));
+                assert_eq!(data.tags.status, Some(200));
+                assert_eq!(data.tags.expected_response, Some(true));
+                assert_eq!(data.tags.group.as_deref(), Some("::setup"));
+            }
+            other => panic!("Expected a point, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reader_reports_line_number() {
+        let input = "{\"type\":\"Metric\",\"data\":{\"name\":\"vus\",\"type\":\"gauge\"},\"metric\":\"vus\"}\nnot json\n";
+        let result: Result<Vec<K6Record>, _> = K6Reader::new(input.as_bytes()).collect();
+
+        match result {
+            Err(K6ParseError::Json { line, .. }) => assert_eq!(line, 2),
+            other => panic!("Expected a JSON error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_per_endpoint_and_metric() {
+        let aggregates = K6Aggregator::aggregate(K6Reader::new(SAMPLE.as_bytes())).unwrap();
+
+        let duration = &aggregates.metrics["http_req_duration"];
+        assert_eq!(duration.metric_type, Some(MetricType::Trend));
+        assert_eq!(duration.thresholds, vec!["p(95)<500".to_string()]);
+        assert_eq!(duration.stats.count, 2);
+        assert_eq!(duration.stats.min, 10.0);
+        assert_eq!(duration.stats.max, 30.0);
+        assert_eq!(duration.stats.avg, 20.0);
+        assert_eq!(duration.stats.p50, 20.0);
+        assert_eq!(duration.stats.p90, 28.0);
+
+        let endpoint = &aggregates.endpoints["GET /"];
+        assert_eq!(endpoint.requests, 2);
+        assert_eq!(endpoint.errors, 1);
+        assert_eq!(endpoint.error_rate, 0.5);
+        assert_eq!(endpoint.status_codes[&500], 1);
+        assert_eq!(endpoint.duration.count, 2);
+
+        assert_eq!(aggregates.duration_ms(), 2160);
+        assert_eq!(aggregates.checks["status is 200"].fails, 1);
+    }
+
+    #[test]
+    fn test_endpoint_prefers_custom_name() {
+        let tags = PointTags {
+            method: Some("GET".to_string()),
+            url: Some("http://localhost:8000/user/users/42?x=1".to_string()),
+            name: Some("http://localhost:8000/user/users/42?x=1".to_string()),
+            ..PointTags::default()
+        };
+        assert_eq!(tags.endpoint().as_deref(), Some("GET /user/users/42"));
+
+        let named = PointTags {
+            name: Some("/user/users/:id".to_string()),
+            ..tags
+        };
+        assert_eq!(named.endpoint().as_deref(), Some("GET /user/users/:id"));
+    }
+
+    #[test]
+    fn test_streaming_percentiles_stay_within_a_percent() {
+        let mut aggregator = K6Aggregator::streaming();
+        let mut exact = K6Aggregator::new();
+        for i in 0..10_000 {
+            let value = (i % 997) as f64 * 0.731 + (i % 13) as f64;
+            let record = K6Record::Point {
+                metric: "http_req_duration".to_string(),
+                data: MetricPoint {
+                    time: DateTime::parse_from_rfc3339("2025-06-29T21:43:22+07:00").unwrap(),
+                    value,
+                    tags: PointTags::default(),
+                },
+            };
+            aggregator.push(record.clone());
+            exact.push(record);
+        }
+
+        let streaming = aggregator.snapshot().metrics["http_req_duration"]
+            .stats
+            .clone();
+        let exact = exact.finish().metrics["http_req_duration"].stats.clone();
+        assert_eq!(streaming.count, exact.count);
+        assert_eq!((streaming.min, streaming.max), (exact.min, exact.max));
+        assert!((streaming.avg - exact.avg).abs() < 1e-9);
+        for (approximate, actual) in [
+            (streaming.p50, exact.p50),
+            (streaming.p90, exact.p90),
+            (streaming.p95, exact.p95),
+            (streaming.p99, exact.p99),
+        ] {
+            assert!((approximate - actual).abs() <= actual * 0.01);
+        }
+    }
+
+    #[test]
+    fn test_percentile_matches_k6() {
+        let values = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(percentile(&values, 0.5), 2.5);
+        assert!((percentile(&values, 0.95) - 3.85).abs() < 1e-9);
+        assert_eq!(percentile(&[7.0], 0.99), 7.0);
+    }
+}
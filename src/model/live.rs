@@ -0,0 +1,165 @@
+use crate::model::k6::{K6Aggregates, MetricType};
+use crate::model::threshold::{stats_values, ThresholdExpr};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveStatus {
+    Running,
+    /// The run ended and was stored as a report
+    Saved,
+    Failed,
+}
+
+/// What live viewers receive while a k6 run streams in
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveSnapshot {
+    pub id: String,
+    pub test_type: String,
+    pub started_at: DateTime<Utc>,
+    pub status: LiveStatus,
+    /// k6 records received so far
+    pub records: u64,
+    pub aggregates: K6Aggregates,
+    /// Set once the run has been saved
+    pub report_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Runs currently being ingested, keyed by run id. Each entry hands out receivers for
+/// the run's latest snapshot; slow viewers simply skip intermediate snapshots.
+#[derive(Clone, Debug, Default)]
+pub struct LiveRuns {
+    runs: Arc<Mutex<BTreeMap<String, watch::Receiver<LiveSnapshot>>>>,
+}
+
+impl LiveRuns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, snapshot: LiveSnapshot) -> watch::Sender<LiveSnapshot> {
+        let id = snapshot.id.clone();
+        let (sender, receiver) = watch::channel(snapshot);
+        self.runs.lock().unwrap().insert(id, receiver);
+        sender
+    }
+
+    pub fn remove(&self, id: &str) {
+        self.runs.lock().unwrap().remove(id);
+    }
+
+    pub fn subscribe(&self, id: &str) -> Option<watch::Receiver<LiveSnapshot>> {
+        self.runs.lock().unwrap().get(id).cloned()
+    }
+
+    /// Latest snapshot of every active run, oldest first
+    pub fn list(&self) -> Vec<LiveSnapshot> {
+        let mut runs: Vec<LiveSnapshot> = self
+            .runs
+            .lock()
+            .unwrap()
+            .values()
+            .map(|receiver| receiver.borrow().clone())
+            .collect();
+        runs.sort_by_key(|run| run.started_at);
+        runs
+    }
+}
+
+/// Build a `performance-data.json` in k6's `handleSummary` layout from raw aggregates, so a
+/// live run is stored like any other completed report
+pub fn summary_export(aggregates: &K6Aggregates) -> Value {
+    let duration_ms = aggregates.duration_ms();
+    let duration_secs = duration_ms as f64 / 1000.0;
+
+    let metrics: Map<String, Value> = aggregates
+        .metrics
+        .iter()
+        .map(|(name, metric)| {
+            let values = stats_values(&metric.stats, metric.metric_type, duration_secs);
+            let thresholds: Map<String, Value> = metric
+                .thresholds
+                .iter()
+                .filter_map(|expression| {
+                    let expr: ThresholdExpr = expression.parse().ok()?;
+                    let ok = expr
+                        .actual(&values)
+                        .is_some_and(|actual| expr.passes(actual));
+                    Some((expression.clone(), json!({ "ok": ok })))
+                })
+                .collect();
+
+            let mut entry = json!({
+                "type": metric.metric_type.map(metric_type_name),
+                "values": values,
+            });
+            if !thresholds.is_empty() {
+                entry["thresholds"] = Value::Object(thresholds);
+            }
+            (name.clone(), entry)
+        })
+        .collect();
+
+    let checks: Vec<Value> = aggregates
+        .checks
+        .iter()
+        .map(|(name, counts)| {
+            json!({
+                "name": name,
+                "passes": counts.passes,
+                "fails": counts.fails
+            })
+        })
+        .collect();
+
+    json!({
+        "metrics": metrics,
+        "root_group": { "checks": checks },
+        "state": { "testRunDurationMs": duration_ms }
+    })
+}
+
+fn metric_type_name(metric_type: MetricType) -> &'static str {
+    match metric_type {
+        MetricType::Counter => "counter",
+        MetricType::Gauge => "gauge",
+        MetricType::Rate => "rate",
+        MetricType::Trend => "trend",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::k6::{K6Aggregator, K6Reader};
+    use crate::model::report::PerformanceSummary;
+
+    #[test]
+    fn test_summary_export_round_trips_through_summary_parser() {
+        let stream = r#"{"metric":"http_req_duration","type":"Metric","data":{"name":"http_req_duration","type":"trend","contains":"time","thresholds":["p(95)<25"],"submetrics":null}}
+{"metric":"http_reqs","type":"Metric","data":{"name":"http_reqs","type":"counter","contains":"default","thresholds":[],"submetrics":null}}
+{"metric":"http_reqs","type":"Point","data":{"time":"2025-06-29T21:43:22+07:00","value":1,"tags":{"method":"GET","status":"200","url":"http://127.0.0.1:8000/"}}}
+{"metric":"http_req_duration","type":"Point","data":{"time":"2025-06-29T21:43:22+07:00","value":10,"tags":{"method":"GET","status":"200","url":"http://127.0.0.1:8000/"}}}
+{"metric":"http_reqs","type":"Point","data":{"time":"2025-06-29T21:43:24+07:00","value":1,"tags":{"method":"GET","status":"200","url":"http://127.0.0.1:8000/"}}}
+{"metric":"http_req_duration","type":"Point","data":{"time":"2025-06-29T21:43:24+07:00","value":30,"tags":{"method":"GET","status":"200","url":"http://127.0.0.1:8000/"}}}
+{"metric":"checks","type":"Point","data":{"time":"2025-06-29T21:43:24+07:00","value":1,"tags":{"check":"status is 200"}}}
+"#;
+        let aggregates = K6Aggregator::aggregate(K6Reader::new(stream.as_bytes())).unwrap();
+        let summary = PerformanceSummary::from_value(&summary_export(&aggregates));
+
+        assert_eq!(summary.duration_ms, 2000.0);
+        assert_eq!(summary.metrics["http_reqs"].values["count"], 2.0);
+        assert_eq!(summary.metrics["http_reqs"].values["rate"], 1.0);
+        let duration = &summary.metrics["http_req_duration"];
+        assert_eq!(duration.metric_type.as_deref(), Some("trend"));
+        assert_eq!(duration.values["avg"], 20.0);
+        assert!(!duration.thresholds["p(95)<25"]);
+        assert_eq!(summary.checks[0].passes, 1);
+    }
+}
@@ -1 +1,19 @@
+pub mod comparison;
+pub mod consumer;
+pub mod coverage;
+pub mod dead_letter;
+pub mod k6;
+pub mod live;
+pub mod mqtt;
+pub mod publish_job;
+pub mod report;
+pub mod retention;
+pub mod route;
+pub mod rpc;
+pub mod schedule;
+pub mod schema;
+pub mod stream;
+pub mod threshold;
+pub mod topology;
+pub mod trend;
 pub mod user;
@@ -0,0 +1,594 @@
+use crate::model::topology::validate_exchange_name;
+use chrono::{DateTime, Utc};
+use lapin::{
+    publisher_confirm::Confirmation,
+    types::{AMQPValue, FieldArray, FieldTable, LongString, ShortString},
+    BasicProperties,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// AMQP limits queue names and routing keys to 255 bytes
+const MAX_ROUTING_KEY_BYTES: usize = 255;
+pub const MAX_QOS: u8 = 2;
+/// How far ahead a message can be scheduled
+const MAX_DELAY_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+
+/// Body of `POST /mqtt/publish`, also served as `POST /mqtt/publisher`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PublishRequest {
+    /// MQTT-style topic such as `sensors/temperature`
+    pub topic: String,
+    /// Sent as text when it is a JSON string, otherwise as JSON
+    pub message: Value,
+    /// 0 publishes without waiting for the broker; 1 and 2 wait for a publisher confirm
+    #[serde(default)]
+    pub qos: u8,
+    /// Extra AMQP headers; ignored over MQTT
+    #[serde(default)]
+    pub headers: BTreeMap<String, Value>,
+    /// Survive a broker restart; defaults to true. Ignored over MQTT.
+    pub persistent: Option<bool>,
+    /// Have an MQTT broker keep the message for future subscribers; ignored over AMQP
+    #[serde(default)]
+    pub retain: bool,
+    /// Exchange to publish to over AMQP; unset publishes straight to the topic's queue
+    pub exchange: Option<String>,
+    /// Routing key to use instead of the one the topic maps to; AMQP only
+    pub routing_key: Option<String>,
+    /// Publish this many milliseconds from now instead of right away
+    pub delay_ms: Option<u64>,
+    /// Publish at this time instead of right away; a time already past publishes now
+    pub deliver_at: Option<DateTime<Utc>>,
+    // The AMQP properties below are ignored over MQTT
+    /// 0 to 255, honoured by queues declared with `max_priority`
+    pub priority: Option<u8>,
+    /// Drop the message once it has waited this long in a queue
+    pub expiration_ms: Option<u64>,
+    /// Overrides `text/plain` or `application/json`, which depend on `message`
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    /// Defaults to a generated id
+    pub message_id: Option<String>,
+    /// Defaults to the time the message is published; AMQP keeps whole seconds
+    pub timestamp: Option<DateTime<Utc>>,
+    pub app_id: Option<String>,
+    pub correlation_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublishValidationError(String);
+
+impl fmt::Display for PublishValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid publish request: {}", self.0)
+    }
+}
+
+impl std::error::Error for PublishValidationError {}
+
+impl PublishRequest {
+    pub fn validate(&self) -> Result<(), PublishValidationError> {
+        let error = |message: &str| Err(PublishValidationError(message.to_string()));
+
+        validate_topic("topic", &self.topic)?;
+        if self.qos > MAX_QOS {
+            return error("qos must be 0, 1 or 2");
+        }
+        if let Some(name) = self
+            .headers
+            .keys()
+            .find(|name| name.is_empty() || name.len() > MAX_ROUTING_KEY_BYTES)
+        {
+            return Err(PublishValidationError(format!(
+                "header name '{}' must be 1 to 255 bytes",
+                name
+            )));
+        }
+        if let Some(exchange) = &self.exchange {
+            validate_exchange_name("exchange", exchange).map_err(PublishValidationError)?;
+        }
+        if self
+            .routing_key
+            .as_ref()
+            .is_some_and(|key| key.len() > MAX_ROUTING_KEY_BYTES)
+        {
+            return error("routing_key must be at most 255 bytes");
+        }
+        if self.routing_key.is_some() && self.exchange.is_none() {
+            return error("routing_key needs an exchange; without one the topic names the queue");
+        }
+        for (field, value) in [
+            ("content_type", &self.content_type),
+            ("content_encoding", &self.content_encoding),
+            ("message_id", &self.message_id),
+            ("app_id", &self.app_id),
+            ("correlation_id", &self.correlation_id),
+        ] {
+            if value
+                .as_ref()
+                .is_some_and(|value| value.is_empty() || value.len() > MAX_ROUTING_KEY_BYTES)
+            {
+                return Err(PublishValidationError(format!(
+                    "{} must be 1 to 255 bytes",
+                    field
+                )));
+            }
+        }
+        if self.timestamp.is_some_and(|at| at.timestamp() < 0) {
+            return error("timestamp must not be before 1970");
+        }
+        if self.delay_ms.is_some() && self.deliver_at.is_some() {
+            return error("give delay_ms or deliver_at, not both");
+        }
+        let max_delay = chrono::Duration::milliseconds(MAX_DELAY_MS as i64);
+        if self.delay_ms.is_some_and(|delay| delay > MAX_DELAY_MS)
+            || self
+                .deliver_at
+                .is_some_and(|at| at > Utc::now() + max_delay)
+        {
+            return error("messages can be scheduled at most 30 days ahead");
+        }
+
+        Ok(())
+    }
+
+    /// The topic as an AMQP routing key, with MQTT's `/` separators turned into `.` the way
+    /// RabbitMQ's MQTT plugin does, unless `routing_key` is given. Without an exchange,
+    /// messages go to the durable queue of that name.
+    pub fn routing_key(&self) -> String {
+        match &self.routing_key {
+            Some(routing_key) => routing_key.clone(),
+            None => self.topic.trim().replace('/', "."),
+        }
+    }
+
+    /// The exchange to publish to; empty for the default exchange
+    pub fn exchange(&self) -> &str {
+        self.exchange.as_deref().unwrap_or_default()
+    }
+
+    /// When to publish the message, if later than `now`
+    pub fn scheduled_for(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let at = match (self.delay_ms, self.deliver_at) {
+            (Some(delay), _) => now + chrono::Duration::milliseconds(delay as i64),
+            (None, Some(at)) => at,
+            (None, None) => return None,
+        };
+        (at > now).then_some(at)
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.persistent.unwrap_or(true)
+    }
+
+    /// Payload bytes and their content type
+    pub fn payload(&self) -> (Vec<u8>, &'static str) {
+        message_payload(&self.message)
+    }
+
+    /// The request's AMQP properties. `message_id` and `content_type` are the defaults for
+    /// what the request leaves unset.
+    pub fn properties(&self, message_id: &str, content_type: &str) -> BasicProperties {
+        let timestamp = self.timestamp.unwrap_or_else(Utc::now);
+        let mut properties = BasicProperties::default()
+            .with_message_id(self.message_id.as_deref().unwrap_or(message_id).into())
+            .with_content_type(self.content_type.as_deref().unwrap_or(content_type).into())
+            .with_delivery_mode(if self.is_persistent() { 2 } else { 1 })
+            .with_timestamp(timestamp.timestamp() as u64);
+
+        if let Some(priority) = self.priority {
+            properties = properties.with_priority(priority);
+        }
+        if let Some(expiration_ms) = self.expiration_ms {
+            properties = properties.with_expiration(expiration_ms.to_string().into());
+        }
+        if let Some(content_encoding) = &self.content_encoding {
+            properties = properties.with_content_encoding(content_encoding.as_str().into());
+        }
+        if let Some(app_id) = &self.app_id {
+            properties = properties.with_app_id(app_id.as_str().into());
+        }
+        if let Some(correlation_id) = &self.correlation_id {
+            properties = properties.with_correlation_id(correlation_id.as_str().into());
+        }
+        if self.headers.is_empty() {
+            return properties;
+        }
+        properties.with_headers(field_table(&self.headers))
+    }
+}
+
+/// A string message goes as text, anything else as JSON
+pub fn message_payload(message: &Value) -> (Vec<u8>, &'static str) {
+    match message {
+        Value::String(text) => (text.clone().into_bytes(), "text/plain"),
+        value => (value.to_string().into_bytes(), "application/json"),
+    }
+}
+
+/// A topic or queue name must work as an MQTT topic and as an AMQP routing key
+pub fn validate_topic(field: &str, topic: &str) -> Result<(), PublishValidationError> {
+    let error = |message: &str| Err(PublishValidationError(format!("{} {}", field, message)));
+
+    if topic.trim().is_empty() {
+        return error("must not be empty");
+    }
+    if topic.contains(['+', '#']) {
+        return error("must not contain the wildcards '+' or '#'");
+    }
+    if topic.contains('\0') {
+        return error("must not contain NUL characters");
+    }
+    if topic.trim().len() > MAX_ROUTING_KEY_BYTES {
+        return error("must be at most 255 bytes");
+    }
+    Ok(())
+}
+
+pub fn field_table(headers: &BTreeMap<String, Value>) -> FieldTable {
+    let mut table = FieldTable::default();
+    for (name, value) in headers {
+        table.insert(ShortString::from(name.clone()), amqp_value(value));
+    }
+    table
+}
+
+fn amqp_value(value: &Value) -> AMQPValue {
+    match value {
+        Value::Null => AMQPValue::Void,
+        Value::Bool(flag) => AMQPValue::Boolean(*flag),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => AMQPValue::LongLongInt(integer),
+            None => AMQPValue::Double(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => AMQPValue::LongString(LongString::from(text.clone())),
+        Value::Array(values) => AMQPValue::FieldArray(FieldArray::from(
+            values.iter().map(amqp_value).collect::<Vec<_>>(),
+        )),
+        Value::Object(map) => {
+            let mut table = FieldTable::default();
+            for (name, value) in map {
+                table.insert(ShortString::from(name.clone()), amqp_value(value));
+            }
+            AMQPValue::FieldTable(table)
+        }
+    }
+}
+
+/// Header values as JSON, the reverse of what a publish request's headers become
+pub fn headers_json(headers: &FieldTable) -> BTreeMap<String, Value> {
+    headers
+        .inner()
+        .iter()
+        .map(|(name, value)| (name.to_string(), json_value(value)))
+        .collect()
+}
+
+fn json_value(value: &AMQPValue) -> Value {
+    match value {
+        AMQPValue::Void => Value::Null,
+        AMQPValue::Boolean(flag) => Value::Bool(*flag),
+        AMQPValue::ShortShortInt(number) => Value::from(*number),
+        AMQPValue::ShortShortUInt(number) => Value::from(*number),
+        AMQPValue::ShortInt(number) => Value::from(*number),
+        AMQPValue::ShortUInt(number) => Value::from(*number),
+        AMQPValue::LongInt(number) => Value::from(*number),
+        AMQPValue::LongUInt(number) => Value::from(*number),
+        AMQPValue::LongLongInt(number) => Value::from(*number),
+        AMQPValue::Timestamp(seconds) => Value::from(*seconds),
+        AMQPValue::Float(number) => Value::from(*number),
+        AMQPValue::Double(number) => Value::from(*number),
+        AMQPValue::DecimalValue(decimal) => {
+            Value::from(decimal.value as f64 / 10f64.powi(decimal.scale as i32))
+        }
+        AMQPValue::ShortString(text) => Value::String(text.to_string()),
+        AMQPValue::LongString(text) => Value::String(text.to_string()),
+        AMQPValue::ByteArray(bytes) => {
+            Value::String(String::from_utf8_lossy(bytes.as_slice()).into_owned())
+        }
+        AMQPValue::FieldArray(values) => {
+            Value::Array(values.as_slice().iter().map(json_value).collect())
+        }
+        AMQPValue::FieldTable(table) => Value::Object(
+            table
+                .inner()
+                .iter()
+                .map(|(name, value)| (name.to_string(), json_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// What the broker said about a published message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmStatus {
+    Ack,
+    Nack,
+    /// Acked, but returned as unroutable
+    Returned,
+    /// Published with qos 0, so no confirm was awaited
+    NotRequested,
+}
+
+impl From<&Confirmation> for ConfirmStatus {
+    fn from(confirmation: &Confirmation) -> Self {
+        match confirmation {
+            Confirmation::Ack(None) => ConfirmStatus::Ack,
+            Confirmation::Ack(Some(_)) => ConfirmStatus::Returned,
+            Confirmation::Nack(_) => ConfirmStatus::Nack,
+            Confirmation::NotRequested => ConfirmStatus::NotRequested,
+        }
+    }
+}
+
+/// How the bulk producer waits for publisher confirms
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishStrategy {
+    /// Publish without confirms; fastest, but the broker may drop messages unnoticed
+    FireAndForget,
+    /// Wait for each confirm before publishing the next message
+    PerMessage,
+    /// Keep up to `window` unconfirmed messages in flight
+    #[default]
+    Windowed,
+}
+
+impl PublishStrategy {
+    pub fn uses_confirms(self) -> bool {
+        self != PublishStrategy::FireAndForget
+    }
+}
+
+/// Outcome of a bulk publish, summed over producers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PublishCounts {
+    /// Messages handed to the broker, retries included
+    pub published: u64,
+    /// Confirmed by the broker, possibly after a retry
+    pub accepted: u64,
+    /// Sent without waiting for a confirm
+    pub unconfirmed: u64,
+    pub nacked: u64,
+    /// Acked but returned as unroutable
+    pub returned: u64,
+    pub retried: u64,
+    /// Still nacked or returned after the last retry
+    pub failed: u64,
+}
+
+impl PublishCounts {
+    /// Count a confirm; true when the message should go to the retry path
+    pub fn record(&mut self, status: ConfirmStatus) -> bool {
+        match status {
+            ConfirmStatus::Ack => self.accepted += 1,
+            ConfirmStatus::NotRequested => self.unconfirmed += 1,
+            ConfirmStatus::Nack => self.nacked += 1,
+            ConfirmStatus::Returned => self.returned += 1,
+        }
+        matches!(status, ConfirmStatus::Nack | ConfirmStatus::Returned)
+    }
+
+    pub fn add(&mut self, other: &PublishCounts) {
+        self.published += other.published;
+        self.accepted += other.accepted;
+        self.unconfirmed += other.unconfirmed;
+        self.nacked += other.nacked;
+        self.returned += other.returned;
+        self.retried += other.retried;
+        self.failed += other.failed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(body: Value) -> PublishRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn test_message_can_be_text_or_json() {
+        let text = request(json!({"topic": "test/load", "message": "hello", "qos": 1}));
+        assert_eq!(text.payload(), (b"hello".to_vec(), "text/plain"));
+        assert_eq!(text.routing_key(), "test.load");
+        assert_eq!(text.qos, 1);
+        assert!(text.is_persistent());
+
+        let json = request(json!({
+            "topic": "sensors/temperature",
+            "message": {"temperature": 23.5},
+            "persistent": false
+        }));
+        assert_eq!(
+            json.payload(),
+            (br#"{"temperature":23.5}"#.to_vec(), "application/json")
+        );
+        assert_eq!(json.qos, 0);
+        assert!(!json.is_persistent());
+        assert!(!json.retain);
+    }
+
+    #[test]
+    fn test_exchange_publishes_use_the_routing_key() {
+        let routed = request(json!({
+            "topic": "orders/created",
+            "message": "x",
+            "exchange": "orders",
+            "routing_key": "orders.eu.created"
+        }));
+        assert!(routed.validate().is_ok());
+        assert_eq!(routed.exchange(), "orders");
+        assert_eq!(routed.routing_key(), "orders.eu.created");
+
+        let fanout =
+            request(json!({"topic": "orders/created", "message": "x", "exchange": "audit"}));
+        assert_eq!(fanout.routing_key(), "orders.created");
+        assert_eq!(
+            request(json!({"topic": "t", "message": "x"})).exchange(),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_topics_and_qos() {
+        let valid = json!({"topic": "alerts/critical", "message": "x", "qos": 2});
+        assert!(request(valid).validate().is_ok());
+
+        for body in [
+            json!({"topic": " ", "message": "x"}),
+            json!({"topic": "sensors/+", "message": "x"}),
+            json!({"topic": "sensors/#", "message": "x"}),
+            json!({"topic": "a".repeat(256), "message": "x"}),
+            json!({"topic": "test", "message": "x", "qos": 3}),
+            json!({"topic": "test", "message": "x", "headers": {"": 1}}),
+            json!({"topic": "test", "message": "x", "exchange": ""}),
+            json!({"topic": "test", "message": "x", "routing_key": "orders.eu"}),
+            json!({"topic": "test", "message": "x", "delay_ms": MAX_DELAY_MS + 1}),
+            json!({"topic": "test", "message": "x", "deliver_at": "2999-01-01T00:00:00Z"}),
+            json!({
+                "topic": "test",
+                "message": "x",
+                "delay_ms": 10,
+                "deliver_at": "2030-01-01T00:00:00Z"
+            }),
+        ] {
+            assert!(request(body).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_delays_and_past_times_schedule_as_expected() {
+        let now = Utc::now();
+        let delayed = request(json!({"topic": "reminders", "message": "x", "delay_ms": 1500}));
+        assert!(delayed.validate().is_ok());
+        assert_eq!(
+            delayed.scheduled_for(now),
+            Some(now + chrono::Duration::milliseconds(1500))
+        );
+
+        let at = now + chrono::Duration::hours(1);
+        let timed = request(json!({"topic": "reminders", "message": "x", "deliver_at": at}));
+        assert_eq!(timed.scheduled_for(now), Some(at));
+
+        let past = request(json!({
+            "topic": "reminders",
+            "message": "x",
+            "deliver_at": "2020-01-01T00:00:00Z"
+        }));
+        assert!(past.validate().is_ok());
+        assert_eq!(past.scheduled_for(now), None);
+        assert_eq!(
+            request(json!({"topic": "t", "message": "x"})).scheduled_for(now),
+            None
+        );
+    }
+
+    #[test]
+    fn test_headers_become_amqp_values() {
+        let request = request(json!({
+            "topic": "test",
+            "message": "x",
+            "headers": {"retries": 3, "ratio": 0.5, "source": "k6", "tags": ["a"], "debug": true}
+        }));
+        let properties = request.properties("id-1", "text/plain");
+        let headers = properties.headers().as_ref().unwrap().inner();
+
+        assert_eq!(headers["retries"], AMQPValue::LongLongInt(3));
+        assert_eq!(headers["ratio"], AMQPValue::Double(0.5));
+        assert_eq!(headers["source"], AMQPValue::LongString("k6".into()));
+        assert_eq!(headers["debug"], AMQPValue::Boolean(true));
+        assert!(matches!(headers["tags"], AMQPValue::FieldArray(_)));
+        assert_eq!(properties.delivery_mode(), &Some(2));
+
+        // Decoding gives the request's headers back
+        assert_eq!(
+            headers_json(properties.headers().as_ref().unwrap()),
+            request.headers
+        );
+    }
+
+    #[test]
+    fn test_requests_set_every_amqp_property() {
+        let full = request(json!({
+            "topic": "orders",
+            "message": "eJwrSS0u0S0pTs1RyC9KSQUAKyQFbQ==",
+            "priority": 7,
+            "expiration_ms": 60000,
+            "content_type": "application/zlib",
+            "content_encoding": "base64",
+            "message_id": "order-42",
+            "timestamp": "2024-05-01T12:00:00Z",
+            "app_id": "checkout",
+            "correlation_id": "c-42"
+        }));
+        assert!(full.validate().is_ok());
+        let properties = full.properties("generated", "text/plain");
+
+        assert_eq!(properties.priority(), &Some(7));
+        assert_eq!(properties.expiration().as_ref().unwrap().as_str(), "60000");
+        assert_eq!(
+            properties.content_type().as_ref().unwrap().as_str(),
+            "application/zlib"
+        );
+        assert_eq!(
+            properties.content_encoding().as_ref().unwrap().as_str(),
+            "base64"
+        );
+        assert_eq!(
+            properties.message_id().as_ref().unwrap().as_str(),
+            "order-42"
+        );
+        assert_eq!(properties.timestamp(), &Some(1_714_564_800));
+        assert_eq!(properties.app_id().as_ref().unwrap().as_str(), "checkout");
+        assert_eq!(
+            properties.correlation_id().as_ref().unwrap().as_str(),
+            "c-42"
+        );
+
+        let defaults = request(json!({"topic": "orders", "message": "x"}))
+            .properties("generated", "text/plain");
+        assert_eq!(
+            defaults.message_id().as_ref().unwrap().as_str(),
+            "generated"
+        );
+        assert_eq!(defaults.priority(), &None);
+        assert_eq!(defaults.expiration(), &None);
+
+        for body in [
+            json!({"topic": "t", "message": "x", "app_id": ""}),
+            json!({"topic": "t", "message": "x", "message_id": "m".repeat(256)}),
+            json!({"topic": "t", "message": "x", "timestamp": "1960-01-01T00:00:00Z"}),
+        ] {
+            assert!(request(body).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_counts_send_nacks_and_returns_to_retry() {
+        let mut counts = PublishCounts::default();
+        assert!(!counts.record(ConfirmStatus::Ack));
+        assert!(!counts.record(ConfirmStatus::NotRequested));
+        assert!(counts.record(ConfirmStatus::Nack));
+        assert!(counts.record(ConfirmStatus::Returned));
+
+        let mut total = PublishCounts {
+            published: 4,
+            ..PublishCounts::default()
+        };
+        total.add(&counts);
+        assert_eq!(
+            (total.published, total.accepted, total.unconfirmed),
+            (4, 1, 1)
+        );
+        assert_eq!((total.nacked, total.returned), (1, 1));
+        assert!(!PublishStrategy::FireAndForget.uses_confirms());
+        assert!(PublishStrategy::default().uses_confirms());
+    }
+}
@@ -0,0 +1,414 @@
+use crate::model::mqtt::{validate_topic, PublishCounts, PublishStrategy};
+use crate::model::topology::validate_exchange_name;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_QUEUE: &str = "test";
+const PRODUCER_COUNT: u32 = 10;
+const ITERATION_PER_PRODUCER: u32 = 100000;
+/// Each producer holds a pooled channel for the whole job, so jobs together may take at
+/// most half of the default pool of 64 and leave the rest to other requests
+const MAX_PRODUCERS: u32 = 16;
+const MAX_RUNNING_JOBS: usize = 2;
+const PUBLISH_WINDOW: usize = 256;
+const MAX_PUBLISH_WINDOW: usize = 10_000;
+const MAX_PUBLISH_RETRIES: u32 = 3;
+const MAX_PAYLOAD_BYTES: usize = 1024 * 1024;
+/// Finished jobs kept for status polling; older ones are dropped first
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// Parameters of a bulk publish, from the query of `POST /mqtt/pub` or the body of
+/// `POST /mqtt/jobs`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProducerConfig {
+    #[serde(rename = "producers")]
+    pub producer_count: u32,
+    #[serde(rename = "messages")]
+    pub iterations_per_producer: u32,
+    /// Queue, or topic over MQTT, the messages go to
+    pub queue: String,
+    /// Exchange to publish to over AMQP, with `queue` as the routing key; unset sends
+    /// straight to the queue
+    pub exchange: Option<String>,
+    pub strategy: PublishStrategy,
+    /// Unconfirmed messages each producer keeps in flight with the windowed strategy
+    pub window: usize,
+    /// Republish attempts for a nacked or returned message
+    pub max_retries: u32,
+    /// Pad the default JSON body to at least this many bytes
+    pub payload_size: Option<usize>,
+    /// Message body with `{{producer_id}}`, `{{task_number}}` and `{{timestamp}}`
+    /// placeholders, used instead of the default JSON body
+    pub template: Option<String>,
+}
+
+impl Default for ProducerConfig {
+    fn default() -> Self {
+        Self {
+            producer_count: PRODUCER_COUNT,
+            iterations_per_producer: ITERATION_PER_PRODUCER,
+            queue: DEFAULT_QUEUE.to_string(),
+            exchange: None,
+            strategy: PublishStrategy::default(),
+            window: PUBLISH_WINDOW,
+            max_retries: MAX_PUBLISH_RETRIES,
+            payload_size: None,
+            template: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TaskBody {
+    producer_id: u32,
+    task_number: u32,
+}
+
+impl ProducerConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.producer_count == 0 || self.producer_count > MAX_PRODUCERS {
+            return Err(format!("producers must be 1 to {}", MAX_PRODUCERS));
+        }
+        if self.window == 0 || self.window > MAX_PUBLISH_WINDOW {
+            return Err(format!("window must be 1 to {}", MAX_PUBLISH_WINDOW));
+        }
+        validate_topic("queue", &self.queue).map_err(|e| e.to_string())?;
+        if let Some(exchange) = &self.exchange {
+            validate_exchange_name("exchange", exchange)?;
+        }
+        if self.payload_size.is_some() && self.template.is_some() {
+            return Err("use either payload_size or template, not both".to_string());
+        }
+        if self
+            .payload_size
+            .is_some_and(|size| size > MAX_PAYLOAD_BYTES)
+            || self
+                .template
+                .as_ref()
+                .is_some_and(|template| template.len() > MAX_PAYLOAD_BYTES)
+        {
+            return Err(format!(
+                "payloads must be at most {} bytes",
+                MAX_PAYLOAD_BYTES
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn total_messages(&self) -> u64 {
+        self.producer_count as u64 * self.iterations_per_producer as u64
+    }
+
+    /// Confirms awaited at once; 1 makes every publish wait for its own confirm
+    pub fn confirm_window(&self) -> usize {
+        match self.strategy {
+            PublishStrategy::Windowed => self.window,
+            PublishStrategy::PerMessage | PublishStrategy::FireAndForget => 1,
+        }
+    }
+
+    /// Body of one task: `{"producer_id":..,"task_number":..}`, padded to `payload_size`
+    /// with a `padding` field, or the rendered template
+    pub fn render_payload(&self, producer_id: u32, task_number: u32) -> Vec<u8> {
+        if let Some(template) = &self.template {
+            return template
+                .replace("{{producer_id}}", &producer_id.to_string())
+                .replace("{{task_number}}", &task_number.to_string())
+                .replace("{{timestamp}}", &Utc::now().timestamp_millis().to_string())
+                .into_bytes();
+        }
+
+        let mut body = serde_json::to_vec(&TaskBody {
+            producer_id,
+            task_number,
+        })
+        .unwrap_or_default();
+        const PADDING_FIELD: &[u8] = br#","padding":"""#;
+        if let Some(size) = self.payload_size {
+            let overhead = body.len() + PADDING_FIELD.len();
+            if size > overhead {
+                body.pop();
+                body.extend_from_slice(&PADDING_FIELD[..PADDING_FIELD.len() - 1]);
+                body.resize(body.len() + size - overhead, b'x');
+                body.extend_from_slice(b"\"}");
+            }
+        }
+        body
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    /// A producer stopped early, e.g. because its channel was lost
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct JobOutcome {
+    status: JobStatus,
+    finished_at: DateTime<Utc>,
+    elapsed: Duration,
+}
+
+/// A bulk publish running in the background. Each producer updates its own counts, so
+/// producers never wait on each other.
+#[derive(Debug)]
+pub struct PublishJob {
+    pub id: String,
+    pub transport: &'static str,
+    pub config: ProducerConfig,
+    pub started_at: DateTime<Utc>,
+    started: Instant,
+    producers: Vec<Mutex<PublishCounts>>,
+    errors: Mutex<Vec<String>>,
+    cancelled: AtomicBool,
+    outcome: Mutex<Option<JobOutcome>>,
+}
+
+/// What `GET /mqtt/jobs/:id` reports
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishJobSnapshot {
+    pub id: String,
+    pub status: JobStatus,
+    pub transport: &'static str,
+    pub config: ProducerConfig,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub elapsed_ms: f64,
+    pub total_messages: u64,
+    /// Messages confirmed, sent without a confirm or given up on, out of `total_messages`
+    pub progress_percent: f64,
+    pub counts: PublishCounts,
+    pub messages_per_sec: f64,
+    pub errors: Vec<String>,
+}
+
+impl PublishJob {
+    pub fn new(id: String, transport: &'static str, config: ProducerConfig) -> Self {
+        let producers = (0..config.producer_count)
+            .map(|_| Mutex::new(PublishCounts::default()))
+            .collect();
+        PublishJob {
+            id,
+            transport,
+            config,
+            started_at: Utc::now(),
+            started: Instant::now(),
+            producers,
+            errors: Mutex::new(Vec::new()),
+            cancelled: AtomicBool::new(false),
+            outcome: Mutex::new(None),
+        }
+    }
+
+    /// Update one producer's counts
+    pub fn update<T>(&self, producer_id: u32, f: impl FnOnce(&mut PublishCounts) -> T) -> T {
+        f(&mut self.producers[producer_id as usize].lock().unwrap())
+    }
+
+    pub fn counts(&self) -> PublishCounts {
+        let mut total = PublishCounts::default();
+        for counts in &self.producers {
+            total.add(&counts.lock().unwrap());
+        }
+        total
+    }
+
+    pub fn push_error(&self, error: String) {
+        self.errors.lock().unwrap().push(error);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Ask the producers to stop; false when the job already finished
+    pub fn cancel(&self) -> bool {
+        if self.outcome.lock().unwrap().is_some() {
+            return false;
+        }
+        self.cancelled.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Called once every producer has stopped
+    pub fn finish(&self) -> JobStatus {
+        let status = if self.is_cancelled() {
+            JobStatus::Cancelled
+        } else if !self.errors.lock().unwrap().is_empty() {
+            JobStatus::Failed
+        } else {
+            JobStatus::Completed
+        };
+        *self.outcome.lock().unwrap() = Some(JobOutcome {
+            status,
+            finished_at: Utc::now(),
+            elapsed: self.started.elapsed(),
+        });
+        status
+    }
+
+    pub fn snapshot(&self) -> PublishJobSnapshot {
+        let outcome = *self.outcome.lock().unwrap();
+        let elapsed = outcome.map_or_else(|| self.started.elapsed(), |outcome| outcome.elapsed);
+        let counts = self.counts();
+        let delivered = counts.accepted + counts.unconfirmed;
+        let total_messages = self.config.total_messages();
+        let done = delivered + counts.failed;
+
+        PublishJobSnapshot {
+            id: self.id.clone(),
+            status: outcome.map_or(JobStatus::Running, |outcome| outcome.status),
+            transport: self.transport,
+            config: self.config.clone(),
+            started_at: self.started_at,
+            finished_at: outcome.map(|outcome| outcome.finished_at),
+            elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+            total_messages,
+            progress_percent: if total_messages == 0 {
+                100.0
+            } else {
+                (done as f64 / total_messages as f64 * 100.0).min(100.0)
+            },
+            counts,
+            messages_per_sec: delivered as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            errors: self.errors.lock().unwrap().clone(),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.outcome.lock().unwrap().is_some()
+    }
+}
+
+/// Bulk publish jobs by id. At most `MAX_RUNNING_JOBS` run at once. Finished jobs stay
+/// until `MAX_FINISHED_JOBS` newer ones have finished.
+#[derive(Clone, Debug, Default)]
+pub struct PublishJobs {
+    jobs: Arc<Mutex<BTreeMap<String, Arc<PublishJob>>>>,
+}
+
+impl PublishJobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails while `MAX_RUNNING_JOBS` jobs are still running
+    pub fn insert(&self, job: PublishJob) -> Result<Arc<PublishJob>, String> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs.values().filter(|job| !job.is_finished()).count() >= MAX_RUNNING_JOBS {
+            return Err(format!(
+                "{} publish jobs are already running; wait for one to finish or cancel it",
+                MAX_RUNNING_JOBS
+            ));
+        }
+        let job = Arc::new(job);
+
+        let mut finished: Vec<&Arc<PublishJob>> =
+            jobs.values().filter(|job| job.is_finished()).collect();
+        if finished.len() >= MAX_FINISHED_JOBS {
+            finished.sort_by_key(|job| job.started_at);
+            let expired: Vec<String> = finished[..=finished.len() - MAX_FINISHED_JOBS]
+                .iter()
+                .map(|job| job.id.clone())
+                .collect();
+            for id in expired {
+                jobs.remove(&id);
+            }
+        }
+
+        jobs.insert(job.id.clone(), job.clone());
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<PublishJob>> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    /// Every job, newest first
+    pub fn list(&self) -> Vec<PublishJobSnapshot> {
+        let mut jobs: Vec<PublishJobSnapshot> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .map(|job| job.snapshot())
+            .collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.started_at));
+        jobs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payloads_are_padded_or_templated() {
+        let mut config = ProducerConfig {
+            payload_size: Some(100),
+            ..ProducerConfig::default()
+        };
+        let body = config.render_payload(3, 42);
+        assert_eq!(body.len(), 100);
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["producer_id"], 3);
+        assert_eq!(value["task_number"], 42);
+
+        config.payload_size = Some(10);
+        assert_eq!(
+            config.render_payload(3, 42),
+            br#"{"producer_id":3,"task_number":42}"#.to_vec()
+        );
+
+        config.payload_size = None;
+        config.template = Some("sensor-{{producer_id}}:{{task_number}}".to_string());
+        assert_eq!(config.render_payload(1, 7), b"sensor-1:7".to_vec());
+
+        config.payload_size = Some(100);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_job_reports_progress_and_cancellation() {
+        let config = ProducerConfig {
+            producer_count: 2,
+            iterations_per_producer: 10,
+            ..ProducerConfig::default()
+        };
+        let job = PublishJob::new("job-1".to_string(), "amqp", config);
+        job.update(0, |counts| counts.accepted = 5);
+        job.update(1, |counts| counts.accepted = 5);
+
+        let snapshot = job.snapshot();
+        assert_eq!(snapshot.status, JobStatus::Running);
+        assert_eq!(snapshot.progress_percent, 50.0);
+
+        assert!(job.cancel());
+        assert_eq!(job.finish(), JobStatus::Cancelled);
+        assert!(!job.cancel());
+        assert!(job.snapshot().finished_at.is_some());
+    }
+
+    #[test]
+    fn test_running_jobs_are_capped() {
+        let jobs = PublishJobs::new();
+        let job = |id: &str| PublishJob::new(id.to_string(), "amqp", ProducerConfig::default());
+        let first = jobs.insert(job("job-1")).unwrap();
+        jobs.insert(job("job-2")).unwrap();
+        assert!(jobs.insert(job("job-3")).is_err());
+
+        first.finish();
+        jobs.insert(job("job-3")).unwrap();
+        assert_eq!(jobs.list().len(), 3);
+    }
+}
@@ -0,0 +1,324 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A single k6 run stored as a directory under the reports folder
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub id: String,
+    pub name: String,
+    pub test_type: String,
+    pub status: String,
+    pub timestamp: Option<NaiveDateTime>,
+    pub duration: u64,
+    pub coverage_percentage: f64,
+    /// Pinned as a baseline; retention never removes it
+    pub baseline: bool,
+    pub files: Vec<String>,
+    pub summary: String,
+}
+
+/// Contents of `metadata.json`, written for reports created through the API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportMetadata {
+    pub id: String,
+    pub test_type: String,
+    pub created_at: DateTime<Utc>,
+    pub source: String,
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub baseline: bool,
+}
+
+/// Contents of `endpoint-coverage.json` as written by the k6 scripts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageData {
+    #[serde(default)]
+    pub test_info: TestInfo,
+    #[serde(default)]
+    pub endpoint_coverage: BTreeMap<String, EndpointStats>,
+    #[serde(default)]
+    pub summary: CoverageSummary,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestInfo {
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub timestamp: String,
+    #[serde(default)]
+    pub duration: f64,
+    #[serde(default)]
+    pub iterations: u64,
+    #[serde(default)]
+    pub vus: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointStats {
+    #[serde(default)]
+    pub hits: u64,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub success_rate: String,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub avg_response_time: String,
+    #[serde(default)]
+    pub errors: u64,
+    #[serde(default)]
+    pub tested: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageSummary {
+    #[serde(default)]
+    pub total_endpoints: u64,
+    #[serde(default)]
+    pub tested_endpoints: u64,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub coverage_percentage: String,
+}
+
+impl CoverageSummary {
+    /// Coverage as a number, accepting both "87.5%" and "87.50"
+    pub fn percentage(&self) -> f64 {
+        self.coverage_percentage
+            .trim()
+            .trim_end_matches('%')
+            .parse()
+            .unwrap_or(0.0)
+    }
+}
+
+// The k6 scripts are not consistent about writing numbers or strings
+fn string_or_number<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => s,
+        Value::Null => String::new(),
+        other => other.to_string(),
+    })
+}
+
+/// The parts of `performance-data.json` the API exposes, normalised across the k6
+/// `handleSummary` export, the legacy `--summary-export` format and hand-written files
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PerformanceSummary {
+    pub duration_ms: f64,
+    pub metrics: BTreeMap<String, MetricValues>,
+    pub checks: Vec<CheckResult>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MetricValues {
+    pub metric_type: Option<String>,
+    pub values: BTreeMap<String, f64>,
+    /// Threshold expression to whether k6 reported it as passing
+    pub thresholds: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub passes: u64,
+    pub fails: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThresholdResult {
+    pub metric: String,
+    pub expression: String,
+    pub passed: bool,
+}
+
+impl PerformanceSummary {
+    pub fn from_value(data: &Value) -> Self {
+        let duration_ms = data
+            .pointer("/state/testRunDurationMs")
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0);
+
+        let metrics = data
+            .get("metrics")
+            .and_then(Value::as_object)
+            .map(|metrics| {
+                metrics
+                    .iter()
+                    .map(|(name, metric)| (name.clone(), MetricValues::from_value(metric)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let checks = data
+            .get("root_group")
+            .or_else(|| data.get("rootGroup"))
+            .and_then(|group| group.get("checks"))
+            .map(CheckResult::from_value)
+            .unwrap_or_default();
+
+        Self {
+            duration_ms,
+            metrics,
+            checks,
+        }
+    }
+
+    pub fn threshold_results(&self) -> Vec<ThresholdResult> {
+        self.metrics
+            .iter()
+            .flat_map(|(metric, values)| {
+                values
+                    .thresholds
+                    .iter()
+                    .map(move |(expression, passed)| ThresholdResult {
+                        metric: metric.clone(),
+                        expression: expression.clone(),
+                        passed: *passed,
+                    })
+            })
+            .collect()
+    }
+}
+
+impl MetricValues {
+    fn from_value(metric: &Value) -> Self {
+        // handleSummary nests samples under "values"; --summary-export keeps them inline
+        let values = metric
+            .get("values")
+            .and_then(Value::as_object)
+            .or_else(|| metric.as_object())
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|(key, value)| value.as_f64().map(|v| (key.clone(), v)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let thresholds = metric
+            .get("thresholds")
+            .and_then(Value::as_object)
+            .map(|thresholds| {
+                thresholds
+                    .iter()
+                    .filter_map(|(expression, result)| {
+                        let passed = match result {
+                            Value::Object(result) => result.get("ok").and_then(Value::as_bool),
+                            // --summary-export records whether the threshold was crossed
+                            Value::Bool(failed) => Some(!failed),
+                            _ => None,
+                        }?;
+                        Some((expression.clone(), passed))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            metric_type: metric
+                .get("type")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            values,
+            thresholds,
+        }
+    }
+}
+
+impl CheckResult {
+    fn from_value(checks: &Value) -> Vec<Self> {
+        let counts = |check: &Value| {
+            (
+                check.get("passes").and_then(Value::as_u64).unwrap_or(0),
+                check.get("fails").and_then(Value::as_u64).unwrap_or(0),
+            )
+        };
+
+        match checks {
+            Value::Array(checks) => checks
+                .iter()
+                .map(|check| {
+                    let (passes, fails) = counts(check);
+                    CheckResult {
+                        name: check
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string(),
+                        passes,
+                        fails,
+                    }
+                })
+                .collect(),
+            Value::Object(checks) => checks
+                .iter()
+                .map(|(name, check)| {
+                    let (passes, fails) = counts(check);
+                    CheckResult {
+                        name: name.clone(),
+                        passes,
+                        fails,
+                    }
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_summary_from_handle_summary_export() {
+        let data = json!({
+            "state": {"testRunDurationMs": 60752.5},
+            "root_group": {"checks": [{"name": "status is 200", "passes": 91, "fails": 30}]},
+            "metrics": {
+                "http_req_duration": {
+                    "type": "trend",
+                    "values": {"avg": 6778.4, "p(95)": 19008.7},
+                    "thresholds": {"p(95)<500": {"ok": false}}
+                }
+            }
+        });
+
+        let summary = PerformanceSummary::from_value(&data);
+        assert_eq!(summary.duration_ms, 60752.5);
+        assert_eq!(summary.checks[0].fails, 30);
+
+        let duration = &summary.metrics["http_req_duration"];
+        assert_eq!(duration.metric_type.as_deref(), Some("trend"));
+        assert_eq!(duration.values["p(95)"], 19008.7);
+        assert!(!duration.thresholds["p(95)<500"]);
+    }
+
+    #[test]
+    fn test_summary_from_legacy_summary_export() {
+        let data = json!({
+            "metrics": {
+                "mqtt_publish_response_time": {
+                    "avg": 1.9, "p(95)": 3,
+                    "thresholds": {"p(95)<1000": false, "p(99)<2000": true}
+                }
+            }
+        });
+
+        let summary = PerformanceSummary::from_value(&data);
+        let results = summary.threshold_results();
+
+        assert_eq!(
+            summary.metrics["mqtt_publish_response_time"].values["p(95)"],
+            3.0
+        );
+        assert_eq!(results.len(), 2);
+        assert!(results[0].passed);
+        assert!(!results[1].passed);
+    }
+}
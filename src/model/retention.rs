@@ -0,0 +1,142 @@
+use crate::config::retention::RetentionPolicy;
+use crate::model::report::Report;
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Why a report survives the retention policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionReason {
+    /// Pinned as a baseline; never removed
+    Baseline,
+    /// Among the newest `keep_last` of its test type
+    Latest,
+    /// Younger than `max_age_days`
+    Recent,
+    /// The id carries no timestamp, so its age is unknown
+    UnknownAge,
+    /// No retention rule is configured
+    NoPolicy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RetainedReport {
+    pub id: String,
+    pub reason: RetentionReason,
+}
+
+/// Which reports a retention pass keeps and which it removes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetentionPlan {
+    pub kept: Vec<RetainedReport>,
+    pub expired: Vec<String>,
+    /// Expired reports that could not be removed
+    pub failed: Vec<String>,
+}
+
+impl RetentionPlan {
+    /// `reports` must be newest first, as `ReportConfig::list_reports` returns them
+    pub fn new(reports: &[Report], policy: &RetentionPolicy, now: NaiveDateTime) -> Self {
+        let mut plan = Self::default();
+        let mut seen_per_type: BTreeMap<&str, usize> = BTreeMap::new();
+
+        for report in reports {
+            let reason = if report.baseline {
+                Some(RetentionReason::Baseline)
+            } else if !policy.is_enabled() {
+                Some(RetentionReason::NoPolicy)
+            } else if let Some(timestamp) = report.timestamp {
+                let seen = seen_per_type.entry(&report.test_type).or_default();
+                *seen += 1;
+
+                if policy.keep_last.is_some_and(|keep_last| *seen <= keep_last) {
+                    Some(RetentionReason::Latest)
+                } else if policy
+                    .max_age_days
+                    .is_some_and(|days| now - timestamp < Duration::days(days as i64))
+                {
+                    Some(RetentionReason::Recent)
+                } else {
+                    None
+                }
+            } else {
+                Some(RetentionReason::UnknownAge)
+            };
+
+            match reason {
+                Some(reason) => plan.kept.push(RetainedReport {
+                    id: report.id.clone(),
+                    reason,
+                }),
+                None => plan.expired.push(report.id.clone()),
+            }
+        }
+
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(id: &str, timestamp: Option<&str>, baseline: bool) -> Report {
+        let test_type = id.split('_').next().unwrap_or(id);
+        Report {
+            id: id.to_string(),
+            name: id.to_string(),
+            test_type: test_type.to_string(),
+            status: "completed".to_string(),
+            timestamp: timestamp
+                .and_then(|ts| NaiveDateTime::parse_from_str(ts, "%Y%m%d_%H%M%S").ok()),
+            duration: 0,
+            coverage_percentage: 0.0,
+            baseline,
+            files: Vec::new(),
+            summary: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_keeps_latest_recent_and_baselines() {
+        let reports = vec![
+            report("ci_3", Some("20250710_090000"), false),
+            report("ci_2", Some("20250709_090000"), false),
+            report("demo_2", Some("20250705_090000"), false),
+            report("ci_1", Some("20250601_090000"), true),
+            report("demo_1", Some("20250601_090000"), false),
+            report("ci_0", Some("20250501_090000"), false),
+            report("adhoc", None, false),
+        ];
+        let policy = RetentionPolicy {
+            keep_last: Some(1),
+            max_age_days: Some(7),
+            ..RetentionPolicy::default()
+        };
+        let now = NaiveDateTime::parse_from_str("20250711_090000", "%Y%m%d_%H%M%S").unwrap();
+
+        let plan = RetentionPlan::new(&reports, &policy, now);
+
+        let kept: Vec<(&str, RetentionReason)> = plan
+            .kept
+            .iter()
+            .map(|kept| (kept.id.as_str(), kept.reason))
+            .collect();
+        assert_eq!(
+            kept,
+            vec![
+                ("ci_3", RetentionReason::Latest),
+                ("ci_2", RetentionReason::Recent),
+                ("demo_2", RetentionReason::Latest),
+                ("ci_1", RetentionReason::Baseline),
+                ("adhoc", RetentionReason::UnknownAge),
+            ]
+        );
+        assert_eq!(plan.expired, vec!["demo_1", "ci_0"]);
+
+        let plan = RetentionPlan::new(&reports, &RetentionPolicy::default(), now);
+        assert!(plan.expired.is_empty());
+        assert_eq!(plan.kept.len(), reports.len());
+    }
+}
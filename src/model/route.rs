@@ -0,0 +1,109 @@
+use serde::Serialize;
+
+/// A method and path template the server answers, e.g. `GET /reports/:id`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct RouteInfo {
+    pub method: String,
+    pub path: String,
+}
+
+impl RouteInfo {
+    pub fn new(method: &str, path: &str) -> Self {
+        Self {
+            method: method.to_uppercase(),
+            path: path.to_string(),
+        }
+    }
+
+    /// `METHOD /path`, the key used in `endpoint-coverage.json`
+    pub fn endpoint(&self) -> String {
+        format!("{} {}", self.method, self.path)
+    }
+
+    /// Whether a concrete request is served by this route. `:param` matches one
+    /// segment and `*rest` matches everything after it, as in axum.
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        if !self.method.eq_ignore_ascii_case(method) {
+            return false;
+        }
+
+        let mut template = segments(&self.path);
+        let mut request = segments(path);
+        loop {
+            match (template.next(), request.next()) {
+                (Some(t), _) if t.starts_with('*') => return true,
+                (Some(t), Some(r)) if t.starts_with(':') || t == r => continue,
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+
+    /// Number of literal segments, so `/reports/compare` wins over `/reports/:id`
+    pub fn specificity(&self) -> usize {
+        segments(&self.path)
+            .filter(|segment| !segment.starts_with([':', '*']))
+            .count()
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// Whether an aggregated endpoint such as `GET /users/7` falls under a key that is either
+/// the same string or a route template like `GET /users/:id`
+pub fn endpoint_matches(pattern: &str, endpoint: &str) -> bool {
+    if pattern == endpoint {
+        return true;
+    }
+    match (pattern.split_once(' '), endpoint.split_once(' ')) {
+        (Some((method, template)), Some((endpoint_method, path))) => {
+            RouteInfo::new(method, template).matches(endpoint_method, path)
+        }
+        _ => false,
+    }
+}
+
+/// The most specific route serving a request, if any
+pub fn find_route<'a>(routes: &'a [RouteInfo], method: &str, path: &str) -> Option<&'a RouteInfo> {
+    routes
+        .iter()
+        .filter(|route| route.matches(method, path))
+        .max_by_key(|route| route.specificity())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_path_templates() {
+        let route = RouteInfo::new("get", "/users/:id");
+        assert!(route.matches("GET", "/users/42"));
+        assert!(route.matches("GET", "/users/42/"));
+        assert!(!route.matches("POST", "/users/42"));
+        assert!(!route.matches("GET", "/users"));
+        assert!(!route.matches("GET", "/users/42/posts"));
+
+        assert!(RouteInfo::new("GET", "/").matches("GET", "/"));
+        assert!(RouteInfo::new("GET", "/static/*path").matches("GET", "/static/css/app.css"));
+    }
+
+    #[test]
+    fn test_find_route_prefers_literal_segments() {
+        let routes = vec![
+            RouteInfo::new("GET", "/reports/:id"),
+            RouteInfo::new("GET", "/reports/compare"),
+        ];
+        assert_eq!(
+            find_route(&routes, "GET", "/reports/compare").unwrap().path,
+            "/reports/compare"
+        );
+        assert_eq!(
+            find_route(&routes, "GET", "/reports/latest").unwrap().path,
+            "/reports/:id"
+        );
+        assert!(find_route(&routes, "GET", "/health").is_none());
+    }
+}
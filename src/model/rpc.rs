@@ -0,0 +1,108 @@
+use crate::model::mqtt::{field_table, message_payload};
+use crate::model::topology::{validate_exchange_name, validate_queue_name};
+use lapin::BasicProperties;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+const MAX_TIMEOUT_MS: u64 = 60_000;
+
+/// Set on a reply when the responder failed; the body then holds `{"error": ...}`
+pub const RPC_ERROR_HEADER: &str = "x-rpc-error";
+
+/// Body of `POST /mqtt/rpc`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpcRequest {
+    /// Queue the responder consumes, or the routing key when `exchange` is set
+    pub queue: String,
+    pub exchange: Option<String>,
+    /// Sent as text when it is a JSON string, otherwise as JSON
+    pub message: Value,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Value>,
+    /// How long to wait for the reply. The request expires in the queue after as long.
+    #[serde(default = "default_timeout")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT_MS
+}
+
+impl RpcRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_queue_name("queue", &self.queue)?;
+        if let Some(exchange) = &self.exchange {
+            validate_exchange_name("exchange", exchange)?;
+        }
+        if self.timeout_ms == 0 || self.timeout_ms > MAX_TIMEOUT_MS {
+            return Err(format!("timeout_ms must be 1 to {}", MAX_TIMEOUT_MS));
+        }
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    /// Payload bytes and the properties that route the reply back
+    pub fn message(
+        &self,
+        message_id: &str,
+        correlation_id: &str,
+        reply_to: &str,
+    ) -> (Vec<u8>, BasicProperties) {
+        let (payload, content_type) = message_payload(&self.message);
+        let properties = BasicProperties::default()
+            .with_message_id(message_id.into())
+            .with_correlation_id(correlation_id.into())
+            .with_reply_to(reply_to.into())
+            .with_content_type(content_type.into())
+            .with_expiration(self.timeout_ms.to_string().into())
+            .with_timestamp(chrono::Utc::now().timestamp() as u64);
+
+        if self.headers.is_empty() {
+            return (payload, properties);
+        }
+        (payload, properties.with_headers(field_table(&self.headers)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_requests_default_and_carry_the_reply_address() {
+        let request: RpcRequest =
+            serde_json::from_value(json!({"queue": "rpc.prices", "message": {"sku": "a-1"}}))
+                .unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.timeout(), Duration::from_millis(DEFAULT_TIMEOUT_MS));
+
+        let (payload, properties) = request.message("m-1", "c-1", "amq.gen-1");
+        assert_eq!(payload, br#"{"sku":"a-1"}"#.to_vec());
+        assert_eq!(
+            properties.correlation_id().as_ref().unwrap().as_str(),
+            "c-1"
+        );
+        assert_eq!(
+            properties.reply_to().as_ref().unwrap().as_str(),
+            "amq.gen-1"
+        );
+        assert_eq!(properties.expiration().as_ref().unwrap().as_str(), "5000");
+
+        for body in [
+            json!({"queue": "", "message": 1}),
+            json!({"queue": "rpc", "message": 1, "timeout_ms": 0}),
+            json!({"queue": "rpc", "message": 1, "timeout_ms": MAX_TIMEOUT_MS + 1}),
+            json!({"queue": "rpc", "message": 1, "exchange": "prices/v2"}),
+        ] {
+            let request: RpcRequest = serde_json::from_value(body).unwrap();
+            assert!(request.validate().is_err());
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use crate::model::mqtt::PublishRequest;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A publish request held back until `deliver_at`, as listed by `GET /mqtt/scheduled` and
+/// kept in the `SCHEDULED_MESSAGES` file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    /// Also the message id the message is published with, unless the request sets one
+    pub id: String,
+    pub deliver_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    /// Empty for the default exchange
+    pub exchange: String,
+    /// The routing key, or the topic over MQTT, resolved when the message was scheduled
+    pub routing_key: String,
+    pub request: PublishRequest,
+}
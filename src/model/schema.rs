@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// A JSON Schema that payloads sent to `subject` must match, as kept in the
+/// `SCHEMA_REGISTRY` file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisteredSchema {
+    /// Queue name or routing key, or the topic over MQTT
+    pub subject: String,
+    pub schema: Value,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One way a payload fails its schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    /// JSON Pointer to the offending value; empty for the payload as a whole
+    pub path: String,
+    pub message: String,
+}
+
+/// Why a payload was refused, with every field that failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaViolation {
+    pub subject: String,
+    pub errors: Vec<FieldError>,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Payload does not match the schema for '{}'",
+            self.subject
+        )?;
+        if let Some(error) = self.errors.first() {
+            let path = if error.path.is_empty() {
+                "/"
+            } else {
+                &error.path
+            };
+            write!(f, ": {} {}", path, error.message)?;
+        }
+        if self.errors.len() > 1 {
+            write!(f, " (and {} more)", self.errors.len() - 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SchemaViolation {}
@@ -0,0 +1,127 @@
+use crate::model::mqtt::MAX_QOS;
+use serde::Deserialize;
+
+const DEFAULT_PREFETCH: u16 = 10;
+const DEFAULT_QOS: u8 = 1;
+const MAX_QUEUE_BYTES: usize = 255;
+
+/// When a streamed message is acknowledged to the broker
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AckMode {
+    /// As soon as it is received, before the client has it
+    Auto,
+    /// Once it has been written to the client's connection
+    #[default]
+    AfterSend,
+    /// When the client acks its delivery tag; WebSocket over AMQP only
+    Client,
+}
+
+/// Query of `GET /mqtt/stream` and `GET /mqtt/stream/ws`
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamQuery {
+    /// Queue to consume, or topic filter over MQTT
+    pub queue: String,
+    #[serde(default)]
+    pub ack: AckMode,
+    /// Unacked messages the broker sends ahead; AMQP only
+    #[serde(default = "default_prefetch")]
+    pub prefetch: u16,
+    /// QoS of the subscription, 0 to 2; MQTT only
+    #[serde(default = "default_qos")]
+    pub qos: u8,
+}
+
+fn default_prefetch() -> u16 {
+    DEFAULT_PREFETCH
+}
+
+fn default_qos() -> u8 {
+    DEFAULT_QOS
+}
+
+impl StreamQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        let queue = self.queue.trim();
+        if queue.is_empty() || queue.len() > MAX_QUEUE_BYTES || queue.contains('\0') {
+            return Err("queue must be 1 to 255 bytes without NUL characters".to_string());
+        }
+        if self.prefetch == 0 {
+            return Err("prefetch must be at least 1".to_string());
+        }
+        if self.qos > MAX_QOS {
+            return Err("qos must be 0, 1 or 2".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What a WebSocket client sends in `ack=client` mode, e.g. `{"ack": 3}` or
+/// `{"nack": 3, "requeue": true}`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum ClientAck {
+    Ack {
+        ack: u64,
+    },
+    Nack {
+        nack: u64,
+        #[serde(default)]
+        requeue: bool,
+    },
+}
+
+impl ClientAck {
+    pub fn delivery_tag(&self) -> u64 {
+        match self {
+            ClientAck::Ack { ack } => *ack,
+            ClientAck::Nack { nack, .. } => *nack,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_query_defaults_and_validate() {
+        let query: StreamQuery = serde_json::from_value(json!({"queue": "orders"})).unwrap();
+        assert_eq!(query.ack, AckMode::AfterSend);
+        assert_eq!(query.prefetch, DEFAULT_PREFETCH);
+        assert_eq!(query.qos, DEFAULT_QOS);
+        assert!(query.validate().is_ok());
+
+        let query: StreamQuery =
+            serde_json::from_value(json!({"queue": "orders", "ack": "client", "prefetch": 1}))
+                .unwrap();
+        assert_eq!(query.ack, AckMode::Client);
+
+        for body in [
+            json!({"queue": " "}),
+            json!({"queue": "orders", "prefetch": 0}),
+            json!({"queue": "orders", "qos": 3}),
+        ] {
+            let query: StreamQuery = serde_json::from_value(body).unwrap();
+            assert!(query.validate().is_err());
+        }
+        assert!(serde_json::from_value::<StreamQuery>(json!({"ack": "auto"})).is_err());
+    }
+
+    #[test]
+    fn test_client_acks_parse() {
+        let parse = |text: &str| serde_json::from_str::<ClientAck>(text).ok();
+        assert_eq!(parse(r#"{"ack": 3}"#), Some(ClientAck::Ack { ack: 3 }));
+        assert_eq!(
+            parse(r#"{"nack": 4, "requeue": true}"#),
+            Some(ClientAck::Nack {
+                nack: 4,
+                requeue: true
+            })
+        );
+        assert_eq!(parse(r#"{"nack": 5}"#).unwrap().delivery_tag(), 5);
+        assert_eq!(parse(r#"{"reject": 5}"#), None);
+    }
+}
@@ -0,0 +1,492 @@
+use crate::config::slo::SloConfig;
+use crate::model::k6::{K6Aggregates, MetricStats, MetricType};
+use crate::model::report::PerformanceSummary;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Aggregation name (`avg`, `p(95)`, `rate`, ...) to value for one metric
+pub type MetricValueMap = BTreeMap<String, f64>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Operator {
+    fn compare(self, actual: f64, expected: f64) -> bool {
+        match self {
+            Operator::Lt => actual < expected,
+            Operator::Le => actual <= expected,
+            Operator::Gt => actual > expected,
+            Operator::Ge => actual >= expected,
+            Operator::Eq => actual == expected,
+            Operator::Ne => actual != expected,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThresholdParseError(String);
+
+impl fmt::Display for ThresholdParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid threshold expression '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ThresholdParseError {}
+
+/// A k6 threshold expression such as `p(95)<500`, `rate<0.1` or `count>100`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdExpr {
+    pub aggregation: String,
+    pub operator: Operator,
+    pub value: f64,
+}
+
+impl FromStr for ThresholdExpr {
+    type Err = ThresholdParseError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let error = || ThresholdParseError(expression.to_string());
+
+        let start = expression.find(['<', '>', '=', '!']).ok_or_else(error)?;
+        let (aggregation, rest) = expression.split_at(start);
+        // Longest operators first so `<=` is not read as `<`
+        let (operator, value) = [
+            ("===", Operator::Eq),
+            ("==", Operator::Eq),
+            ("!=", Operator::Ne),
+            ("<=", Operator::Le),
+            (">=", Operator::Ge),
+            ("<", Operator::Lt),
+            (">", Operator::Gt),
+        ]
+        .into_iter()
+        .find_map(|(symbol, operator)| rest.strip_prefix(symbol).map(|value| (operator, value)))
+        .ok_or_else(error)?;
+
+        let aggregation = normalize_aggregation(aggregation.trim()).ok_or_else(error)?;
+        let value = value.trim().parse().map_err(|_| error())?;
+
+        Ok(Self {
+            aggregation,
+            operator,
+            value,
+        })
+    }
+}
+
+/// Canonical key for an aggregation, matching the keys in k6 summaries
+fn normalize_aggregation(aggregation: &str) -> Option<String> {
+    match aggregation {
+        "avg" | "min" | "max" | "med" | "count" | "rate" | "value" => Some(aggregation.to_string()),
+        _ => {
+            let pct: f64 = aggregation
+                .strip_prefix("p(")?
+                .strip_suffix(')')?
+                .trim()
+                .parse()
+                .ok()?;
+            (0.0..=100.0).contains(&pct).then(|| format!("p({})", pct))
+        }
+    }
+}
+
+impl ThresholdExpr {
+    /// The aggregated value this expression is checked against, if the run has it
+    pub fn actual(&self, values: &MetricValueMap) -> Option<f64> {
+        values
+            .get(&self.aggregation)
+            .copied()
+            .or_else(|| match self.aggregation.as_str() {
+                "med" => values.get("p(50)").copied(),
+                "p(50)" => values.get("med").copied(),
+                // Legacy --summary-export writes a rate metric's ratio as `value`
+                "rate" => values.get("value").copied(),
+                _ => None,
+            })
+    }
+
+    pub fn passes(&self, actual: f64) -> bool {
+        self.operator.compare(actual, self.value)
+    }
+}
+
+/// The values k6 would report in its summary for a metric built from raw samples
+pub fn stats_values(
+    stats: &MetricStats,
+    metric_type: Option<MetricType>,
+    duration_secs: f64,
+) -> MetricValueMap {
+    let mut values = MetricValueMap::new();
+    match metric_type {
+        Some(MetricType::Counter) => {
+            values.insert("count".to_string(), stats.sum);
+            if duration_secs > 0.0 {
+                values.insert("rate".to_string(), stats.sum / duration_secs);
+            }
+        }
+        Some(MetricType::Rate) => {
+            values.insert("rate".to_string(), stats.rate);
+        }
+        _ => {
+            values.extend([
+                ("avg".to_string(), stats.avg),
+                ("min".to_string(), stats.min),
+                ("max".to_string(), stats.max),
+                ("med".to_string(), stats.p50),
+                ("p(90)".to_string(), stats.p90),
+                ("p(95)".to_string(), stats.p95),
+                ("p(99)".to_string(), stats.p99),
+                ("count".to_string(), stats.count as f64),
+            ]);
+            if metric_type.is_none() {
+                values.insert("rate".to_string(), stats.rate);
+            }
+        }
+    }
+    values
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThresholdSource {
+    /// Declared in the k6 script
+    K6,
+    /// Declared in the server's SLO config
+    Slo,
+}
+
+impl fmt::Display for ThresholdSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThresholdSource::K6 => write!(f, "k6"),
+            ThresholdSource::Slo => write!(f, "SLO"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThresholdEvaluation {
+    pub source: ThresholdSource,
+    pub metric: String,
+    pub endpoint: Option<String>,
+    pub expression: String,
+    pub actual: Option<f64>,
+    /// `None` when the run has no data for the metric or the expression is invalid
+    pub passed: Option<bool>,
+    /// What k6 itself reported, when the summary export has it
+    pub k6_passed: Option<bool>,
+    pub error: Option<String>,
+}
+
+impl ThresholdEvaluation {
+    fn new(
+        source: ThresholdSource,
+        metric: &str,
+        endpoint: Option<&str>,
+        expression: &str,
+        values: Option<&MetricValueMap>,
+    ) -> Self {
+        let (actual, passed, error) = match expression.parse::<ThresholdExpr>() {
+            Ok(expr) => {
+                let actual = values.and_then(|values| expr.actual(values));
+                (actual, actual.map(|actual| expr.passes(actual)), None)
+            }
+            Err(e) => (None, None, Some(e.to_string())),
+        };
+
+        Self {
+            source,
+            metric: metric.to_string(),
+            endpoint: endpoint.map(str::to_string),
+            expression: expression.to_string(),
+            actual,
+            passed,
+            k6_passed: None,
+            error,
+        }
+    }
+
+    pub fn actual_display(&self) -> String {
+        match self.actual {
+            Some(actual) => format!("{:.3}", actual),
+            None => "-".to_string(),
+        }
+    }
+}
+
+/// Every threshold and SLO evaluated against one run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ThresholdReport {
+    pub results: Vec<ThresholdEvaluation>,
+    pub passed: usize,
+    pub failed: usize,
+    /// Thresholds that could not be evaluated for lack of data
+    pub unknown: usize,
+}
+
+impl ThresholdReport {
+    /// Summary values are preferred since they are exactly what k6 computed; raw
+    /// aggregates fill in when there is no summary and are needed for per-endpoint SLOs.
+    pub fn evaluate(
+        summary: Option<&PerformanceSummary>,
+        aggregates: Option<&K6Aggregates>,
+        slos: &SloConfig,
+    ) -> Self {
+        let duration_secs = aggregates
+            .map(|aggregates| aggregates.duration_ms() as f64 / 1000.0)
+            .unwrap_or(0.0);
+        let metric_values: BTreeMap<String, MetricValueMap> = match (summary, aggregates) {
+            (Some(summary), _) => summary
+                .metrics
+                .iter()
+                .map(|(name, metric)| (name.clone(), metric.values.clone()))
+                .collect(),
+            (None, Some(aggregates)) => aggregates
+                .metrics
+                .iter()
+                .map(|(name, metric)| {
+                    let values = stats_values(&metric.stats, metric.metric_type, duration_secs);
+                    (name.clone(), values)
+                })
+                .collect(),
+            (None, None) => BTreeMap::new(),
+        };
+
+        let mut results = Vec::new();
+
+        match (summary, aggregates) {
+            (Some(summary), _) => {
+                for threshold in summary.threshold_results() {
+                    let mut result = ThresholdEvaluation::new(
+                        ThresholdSource::K6,
+                        &threshold.metric,
+                        None,
+                        &threshold.expression,
+                        metric_values.get(&threshold.metric),
+                    );
+                    result.k6_passed = Some(threshold.passed);
+                    results.push(result);
+                }
+            }
+            (None, Some(aggregates)) => {
+                for (metric, summary) in &aggregates.metrics {
+                    for expression in &summary.thresholds {
+                        results.push(ThresholdEvaluation::new(
+                            ThresholdSource::K6,
+                            metric,
+                            None,
+                            expression,
+                            metric_values.get(metric),
+                        ));
+                    }
+                }
+            }
+            (None, None) => {}
+        }
+
+        for (metric, expressions) in &slos.metrics {
+            for expression in expressions {
+                results.push(ThresholdEvaluation::new(
+                    ThresholdSource::Slo,
+                    metric,
+                    None,
+                    expression,
+                    metric_values.get(metric),
+                ));
+            }
+        }
+
+        // Every endpoint under a route template is pooled into one group by the aggregator,
+        // so an SLO is checked once for the route rather than once per id
+        for (pattern, metrics) in &slos.endpoints {
+            let group = aggregates.and_then(|aggregates| aggregates.groups.get(pattern));
+
+            for (metric, expressions) in metrics {
+                let metric_type = aggregates
+                    .and_then(|aggregates| aggregates.metrics.get(metric))
+                    .and_then(|metric| metric.metric_type);
+                let values = group
+                    .and_then(|group| group.metrics.get(metric))
+                    .map(|stats| stats_values(stats, metric_type, duration_secs));
+
+                // SLOs for endpoints the run never hit are still listed, as unknown
+                for expression in expressions {
+                    results.push(ThresholdEvaluation::new(
+                        ThresholdSource::Slo,
+                        metric,
+                        Some(pattern),
+                        expression,
+                        values.as_ref(),
+                    ));
+                }
+            }
+        }
+
+        let passed = results.iter().filter(|r| r.passed == Some(true)).count();
+        let failed = results.iter().filter(|r| r.passed == Some(false)).count();
+        let unknown = results.len() - passed - failed;
+
+        Self {
+            results,
+            passed,
+            failed,
+            unknown,
+        }
+    }
+}
+
+impl ThresholdReport {
+    /// No threshold failed; thresholds without data do not fail a run
+    pub fn is_passing(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// Bootstrap colour for a badge summarising the run
+    pub fn badge_class(&self) -> &'static str {
+        if self.failed > 0 {
+            "bg-danger"
+        } else if self.passed > 0 {
+            "bg-success"
+        } else {
+            "bg-secondary"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::k6::{K6Aggregator, K6Reader};
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_threshold_expressions() {
+        let expr: ThresholdExpr = "p(95)<500".parse().unwrap();
+        assert_eq!(expr.aggregation, "p(95)");
+        assert_eq!(expr.operator, Operator::Lt);
+        assert_eq!(expr.value, 500.0);
+
+        let expr: ThresholdExpr = " count >= 100 ".parse().unwrap();
+        assert_eq!(expr.operator, Operator::Ge);
+        assert_eq!(expr.aggregation, "count");
+
+        let expr: ThresholdExpr = "p(99.0)===1".parse().unwrap();
+        assert_eq!(expr.aggregation, "p(99)");
+        assert_eq!(expr.operator, Operator::Eq);
+
+        assert!("p95<500".parse::<ThresholdExpr>().is_err());
+        assert!("rate<".parse::<ThresholdExpr>().is_err());
+        assert!("rate".parse::<ThresholdExpr>().is_err());
+    }
+
+    #[test]
+    fn test_evaluates_summary_thresholds_and_slos() {
+        let summary = PerformanceSummary::from_value(&json!({
+            "metrics": {
+                "http_req_duration": {
+                    "values": {"med": 38.1, "p(95)": 620.0},
+                    "thresholds": {"p(95)<500": {"ok": false}}
+                },
+                "http_reqs": {"count": 1247, "rate": 41.5}
+            }
+        }));
+        let slos = SloConfig {
+            metrics: BTreeMap::from([
+                ("http_reqs".to_string(), vec!["count>100".to_string()]),
+                (
+                    "http_req_duration".to_string(),
+                    vec!["p(50)<50".to_string()],
+                ),
+                ("http_req_failed".to_string(), vec!["rate<0.1".to_string()]),
+            ]),
+            endpoints: BTreeMap::new(),
+        };
+
+        let report = ThresholdReport::evaluate(Some(&summary), None, &slos);
+        let k6 = &report.results[0];
+        assert_eq!(k6.source, ThresholdSource::K6);
+        assert_eq!(k6.actual, Some(620.0));
+        assert_eq!(k6.passed, Some(false));
+        assert_eq!(k6.k6_passed, Some(false));
+
+        // p(50) falls back to med; http_req_failed is missing from the run
+        assert_eq!((report.passed, report.failed, report.unknown), (2, 1, 1));
+        assert!(!report.is_passing());
+
+        let legacy = PerformanceSummary::from_value(&json!({
+            "metrics": {"http_req_failed": {"value": 0.02, "passes": 5, "fails": 245}}
+        }));
+        let report = ThresholdReport::evaluate(Some(&legacy), None, &slos);
+        let failed = report
+            .results
+            .iter()
+            .find(|result| result.metric == "http_req_failed")
+            .unwrap();
+        assert_eq!(failed.actual, Some(0.02));
+        assert_eq!(failed.passed, Some(true));
+    }
+
+    #[test]
+    fn test_evaluates_endpoint_slos_from_raw_stream() {
+        let stream = r#"{"metric":"http_req_duration","type":"Metric","data":{"name":"http_req_duration","type":"trend","contains":"time","thresholds":["p(95)<100"],"submetrics":null}}
+{"metric":"http_req_duration","type":"Point","data":{"time":"2025-06-29T21:43:22+07:00","value":80,"tags":{"method":"GET","status":"200","url":"http://127.0.0.1:8000/user/users/7"}}}
+{"metric":"http_req_duration","type":"Point","data":{"time":"2025-06-29T21:43:22+07:00","value":250,"tags":{"method":"GET","status":"200","url":"http://127.0.0.1:8000/user/users/8"}}}
+{"metric":"http_req_duration","type":"Point","data":{"time":"2025-06-29T21:43:23+07:00","value":300,"tags":{"method":"POST","status":"200","url":"http://127.0.0.1:8000/mqtt/pub"}}}
+"#;
+        let slos = SloConfig {
+            metrics: BTreeMap::new(),
+            endpoints: BTreeMap::from([
+                (
+                    "GET /user/users/:id".to_string(),
+                    BTreeMap::from([(
+                        "http_req_duration".to_string(),
+                        vec!["p(95)<200".to_string()],
+                    )]),
+                ),
+                (
+                    "DELETE /user/users/:id".to_string(),
+                    BTreeMap::from([(
+                        "http_req_duration".to_string(),
+                        vec!["p(95)<200".to_string()],
+                    )]),
+                ),
+            ]),
+        };
+
+        let aggregates = K6Aggregator::new()
+            .with_groups(slos.endpoints.keys().cloned())
+            .aggregate_reader(K6Reader::new(stream.as_bytes()))
+            .unwrap();
+
+        let report = ThresholdReport::evaluate(None, Some(&aggregates), &slos);
+        // The k6 threshold comes from the metric definition in the stream
+        assert_eq!(report.results[0].source, ThresholdSource::K6);
+        assert_eq!(report.results[0].passed, Some(false));
+
+        // Both ids are pooled into one row for the route, so the slow one fails it
+        let endpoints: Vec<&ThresholdEvaluation> = report
+            .results
+            .iter()
+            .filter(|r| r.endpoint.as_deref() == Some("GET /user/users/:id"))
+            .collect();
+        assert_eq!(endpoints.len(), 1);
+        assert_eq!(endpoints[0].passed, Some(false));
+        assert!(endpoints[0].actual.unwrap() > 200.0);
+        let missing = report
+            .results
+            .iter()
+            .find(|r| r.endpoint.as_deref() == Some("DELETE /user/users/:id"))
+            .unwrap();
+        assert_eq!(missing.passed, None);
+    }
+}
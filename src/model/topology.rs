@@ -0,0 +1,403 @@
+use crate::model::mqtt::{field_table, headers_json};
+use lapin::{
+    options::{ExchangeDeclareOptions, QueueDeclareOptions},
+    types::FieldTable,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// AMQP limits exchange and queue names to 255 bytes
+const MAX_NAME_BYTES: usize = 255;
+const DEFAULT_PEEK: usize = 10;
+const MAX_PEEK: usize = 100;
+
+/// How an exchange routes what is published to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExchangeType {
+    /// To queues bound with exactly the routing key
+    Direct,
+    /// To queues whose binding pattern matches the routing key, with `*` and `#` wildcards
+    Topic,
+    /// To every bound queue, ignoring the routing key
+    Fanout,
+    /// To queues whose binding arguments match the message headers
+    Headers,
+}
+
+impl From<ExchangeType> for lapin::ExchangeKind {
+    fn from(kind: ExchangeType) -> Self {
+        match kind {
+            ExchangeType::Direct => lapin::ExchangeKind::Direct,
+            ExchangeType::Topic => lapin::ExchangeKind::Topic,
+            ExchangeType::Fanout => lapin::ExchangeKind::Fanout,
+            ExchangeType::Headers => lapin::ExchangeKind::Headers,
+        }
+    }
+}
+
+/// Body of `POST /mqtt/exchanges`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExchangeSpec {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: ExchangeType,
+    /// Survive a broker restart; defaults to true
+    #[serde(default = "default_true")]
+    pub durable: bool,
+    /// Delete the exchange once its last binding is removed
+    #[serde(default)]
+    pub auto_delete: bool,
+    /// Only reachable through exchange-to-exchange bindings, not by publishers
+    #[serde(default)]
+    pub internal: bool,
+    /// Extra arguments such as `alternate-exchange`
+    #[serde(default)]
+    pub arguments: BTreeMap<String, Value>,
+}
+
+impl ExchangeSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_exchange_name("name", &self.name)
+    }
+
+    pub fn options(&self) -> ExchangeDeclareOptions {
+        ExchangeDeclareOptions {
+            durable: self.durable,
+            auto_delete: self.auto_delete,
+            internal: self.internal,
+            ..ExchangeDeclareOptions::default()
+        }
+    }
+
+    pub fn arguments(&self) -> FieldTable {
+        field_table(&self.arguments)
+    }
+}
+
+/// RabbitMQ's queue implementations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueType {
+    Classic,
+    /// Replicated; must be durable and neither exclusive nor auto-delete
+    Quorum,
+    /// Append-only log; the same restrictions as quorum queues
+    Stream,
+}
+
+impl QueueType {
+    fn as_str(self) -> &'static str {
+        match self {
+            QueueType::Classic => "classic",
+            QueueType::Quorum => "quorum",
+            QueueType::Stream => "stream",
+        }
+    }
+}
+
+/// Body of `POST /mqtt/queues`. The typed fields are shorthands for the matching `x-`
+/// arguments; anything else goes in `arguments`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QueueSpec {
+    pub name: String,
+    /// Survive a broker restart; defaults to true
+    #[serde(default = "default_true")]
+    pub durable: bool,
+    /// Only usable by this server's connection, and deleted when it closes
+    #[serde(default)]
+    pub exclusive: bool,
+    /// Delete the queue once its last consumer goes away
+    #[serde(default)]
+    pub auto_delete: bool,
+    /// `x-message-ttl`: drop or dead-letter messages older than this
+    pub message_ttl_ms: Option<u32>,
+    /// `x-max-length`: messages kept before the oldest are dropped or dead-lettered
+    pub max_length: Option<u32>,
+    /// `x-max-length-bytes`: the same limit in body bytes
+    pub max_length_bytes: Option<u64>,
+    /// `x-max-priority`: the highest message priority the queue orders by; higher ones
+    /// count as this
+    pub max_priority: Option<u8>,
+    /// `x-queue-type`
+    pub queue_type: Option<QueueType>,
+    /// `x-dead-letter-exchange`: where expired, rejected and overflowing messages go
+    pub dead_letter_exchange: Option<String>,
+    /// `x-dead-letter-routing-key`; the message's own routing key when unset
+    pub dead_letter_routing_key: Option<String>,
+    #[serde(default)]
+    pub arguments: BTreeMap<String, Value>,
+}
+
+impl QueueSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_queue_name("name", &self.name)?;
+        if let Some(exchange) = &self.dead_letter_exchange {
+            // The default exchange is a valid dead-letter target
+            if !exchange.is_empty() {
+                validate_exchange_name("dead_letter_exchange", exchange)?;
+            }
+        }
+        if self.max_priority == Some(0) {
+            return Err("max_priority must be 1 to 255".to_string());
+        }
+        if matches!(self.queue_type, Some(QueueType::Quorum | QueueType::Stream))
+            && (!self.durable || self.exclusive || self.auto_delete)
+        {
+            return Err(
+                "quorum and stream queues must be durable, not exclusive or auto_delete"
+                    .to_string(),
+            );
+        }
+        if let Some(name) = self
+            .typed_arguments()
+            .into_keys()
+            .find(|name| self.arguments.contains_key(name))
+        {
+            return Err(format!("{} is set both as a field and in arguments", name));
+        }
+        Ok(())
+    }
+
+    pub fn options(&self) -> QueueDeclareOptions {
+        QueueDeclareOptions {
+            durable: self.durable,
+            exclusive: self.exclusive,
+            auto_delete: self.auto_delete,
+            ..QueueDeclareOptions::default()
+        }
+    }
+
+    /// `arguments` plus the `x-` arguments of the typed fields
+    pub fn arguments(&self) -> BTreeMap<String, Value> {
+        let mut arguments = self.arguments.clone();
+        arguments.extend(self.typed_arguments());
+        arguments
+    }
+
+    fn typed_arguments(&self) -> BTreeMap<String, Value> {
+        let mut arguments = BTreeMap::new();
+        let mut set = |name: &str, value: Option<Value>| {
+            if let Some(value) = value {
+                arguments.insert(name.to_string(), value);
+            }
+        };
+        set("x-message-ttl", self.message_ttl_ms.map(Value::from));
+        set("x-max-length", self.max_length.map(Value::from));
+        set("x-max-length-bytes", self.max_length_bytes.map(Value::from));
+        set("x-max-priority", self.max_priority.map(Value::from));
+        set(
+            "x-queue-type",
+            self.queue_type.map(|kind| Value::from(kind.as_str())),
+        );
+        set(
+            "x-dead-letter-exchange",
+            self.dead_letter_exchange.clone().map(Value::from),
+        );
+        set(
+            "x-dead-letter-routing-key",
+            self.dead_letter_routing_key.clone().map(Value::from),
+        );
+        arguments
+    }
+}
+
+/// Body of `POST /mqtt/bindings` and `DELETE /mqtt/bindings`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BindingSpec {
+    pub exchange: String,
+    pub queue: String,
+    /// Ignored by fanout and headers exchanges
+    #[serde(default)]
+    pub routing_key: String,
+    /// What a headers exchange matches on, e.g. `{"x-match": "all", "region": "eu"}`
+    #[serde(default)]
+    pub arguments: BTreeMap<String, Value>,
+}
+
+impl BindingSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_exchange_name("exchange", &self.exchange)?;
+        validate_queue_name("queue", &self.queue)?;
+        if self.routing_key.len() > MAX_NAME_BYTES {
+            return Err("routing_key must be at most 255 bytes".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn arguments(&self) -> FieldTable {
+        field_table(&self.arguments)
+    }
+}
+
+/// Query of `DELETE /mqtt/exchanges/:name` and `DELETE /mqtt/queues/:name`
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct DeleteQuery {
+    /// Refuse to delete an exchange with bindings or a queue with consumers
+    pub if_unused: bool,
+    /// Refuse to delete a queue that still holds messages
+    pub if_empty: bool,
+}
+
+/// Query of `GET /mqtt/queues/:name/peek` and `GET /mqtt/dlq/:queue`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PeekQuery {
+    /// Messages to fetch and put back
+    #[serde(default = "default_peek")]
+    pub n: usize,
+}
+
+fn default_peek() -> usize {
+    DEFAULT_PEEK
+}
+
+impl PeekQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.n == 0 || self.n > MAX_PEEK {
+            return Err(format!("n must be 1 to {}", MAX_PEEK));
+        }
+        Ok(())
+    }
+}
+
+/// How this server declared a queue, as reported by `GET /mqtt/queues/:name`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueueDeclaration {
+    pub durable: bool,
+    pub exclusive: bool,
+    pub auto_delete: bool,
+    pub arguments: BTreeMap<String, Value>,
+}
+
+impl QueueDeclaration {
+    pub fn new(options: &QueueDeclareOptions, arguments: &FieldTable) -> Self {
+        QueueDeclaration {
+            durable: options.durable,
+            exclusive: options.exclusive,
+            auto_delete: options.auto_delete,
+            arguments: headers_json(arguments),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Exchange names are limited to letters, digits, `-`, `_`, `.` and `:`
+pub fn validate_exchange_name(field: &str, name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_BYTES {
+        return Err(format!("{} must be 1 to 255 bytes", field));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+    {
+        return Err(format!(
+            "{} may only contain letters, digits, '-', '_', '.' and ':'",
+            field
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_queue_name(field: &str, name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name.len() > MAX_NAME_BYTES || name.contains('\0') {
+        return Err(format!(
+            "{} must be 1 to 255 bytes without NUL characters",
+            field
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_exchange_defaults_and_names() {
+        let spec: ExchangeSpec =
+            serde_json::from_value(json!({"name": "devices.events", "type": "topic"})).unwrap();
+        assert_eq!(spec.kind, ExchangeType::Topic);
+        assert!(spec.durable && !spec.auto_delete && !spec.internal);
+        assert!(spec.validate().is_ok());
+
+        for name in ["", "has space", "sensors/#"] {
+            assert!(validate_exchange_name("name", name).is_err());
+        }
+        assert!(serde_json::from_value::<ExchangeSpec>(json!({"name": "x", "type": "x"})).is_err());
+    }
+
+    #[test]
+    fn test_queue_fields_become_arguments() {
+        let spec: QueueSpec = serde_json::from_value(json!({
+            "name": "orders",
+            "message_ttl_ms": 60000,
+            "max_length": 1000,
+            "max_priority": 10,
+            "queue_type": "quorum",
+            "dead_letter_exchange": "",
+            "arguments": {"x-overflow": "reject-publish"}
+        }))
+        .unwrap();
+        assert!(spec.validate().is_ok());
+        assert_eq!(
+            spec.arguments(),
+            BTreeMap::from([
+                ("x-dead-letter-exchange".to_string(), json!("")),
+                ("x-max-length".to_string(), json!(1000)),
+                ("x-max-priority".to_string(), json!(10)),
+                ("x-message-ttl".to_string(), json!(60000)),
+                ("x-overflow".to_string(), json!("reject-publish")),
+                ("x-queue-type".to_string(), json!("quorum")),
+            ])
+        );
+        assert!(spec.options().durable);
+    }
+
+    #[test]
+    fn test_queue_validate_rejects_conflicts() {
+        for body in [
+            json!({"name": " "}),
+            json!({"name": "q", "queue_type": "quorum", "exclusive": true}),
+            json!({"name": "q", "queue_type": "stream", "durable": false}),
+            json!({"name": "q", "message_ttl_ms": 5, "arguments": {"x-message-ttl": 10}}),
+            json!({"name": "q", "dead_letter_exchange": "bad name"}),
+            json!({"name": "q", "max_priority": 0}),
+        ] {
+            let spec: QueueSpec = serde_json::from_value(body).unwrap();
+            assert!(spec.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_peek_query_and_declarations() {
+        let peek: PeekQuery = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(peek.n, DEFAULT_PEEK);
+        assert!(peek.validate().is_ok());
+        assert!(PeekQuery { n: 0 }.validate().is_err());
+        assert!(PeekQuery { n: MAX_PEEK + 1 }.validate().is_err());
+
+        let spec: QueueSpec =
+            serde_json::from_value(json!({"name": "orders", "max_length": 5})).unwrap();
+        let declaration = QueueDeclaration::new(&spec.options(), &field_table(&spec.arguments()));
+        assert!(declaration.durable && !declaration.exclusive);
+        assert_eq!(declaration.arguments, spec.arguments());
+    }
+
+    #[test]
+    fn test_binding_needs_a_named_exchange() {
+        let binding: BindingSpec =
+            serde_json::from_value(json!({"exchange": "devices", "queue": "audit"})).unwrap();
+        assert_eq!(binding.routing_key, "");
+        assert!(binding.validate().is_ok());
+
+        let binding: BindingSpec =
+            serde_json::from_value(json!({"exchange": "", "queue": "audit"})).unwrap();
+        assert!(binding.validate().is_err());
+    }
+}
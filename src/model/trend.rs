@@ -0,0 +1,128 @@
+use crate::model::comparison::{RunMetrics, RunSnapshot};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// One run's numbers for the whole run or a single endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct TrendPoint {
+    pub report_id: String,
+    pub timestamp: Option<NaiveDateTime>,
+    pub p95: Option<f64>,
+    pub throughput: Option<f64>,
+    pub error_rate: Option<f64>,
+    /// Endpoint coverage in percent; only known for the whole run
+    pub coverage: Option<f64>,
+}
+
+impl TrendPoint {
+    fn new(run: &TrendRun, metrics: &RunMetrics, coverage: Option<f64>) -> Self {
+        Self {
+            report_id: run.snapshot.report_id.clone(),
+            timestamp: run.timestamp,
+            p95: metrics.p95,
+            throughput: metrics.throughput,
+            error_rate: metrics.error_rate,
+            coverage,
+        }
+    }
+}
+
+/// Every run of one test type, oldest first
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TestTypeTrend {
+    pub overall: Vec<TrendPoint>,
+    pub endpoints: BTreeMap<String, Vec<TrendPoint>>,
+}
+
+/// What a single stored run contributes to the trends
+#[derive(Debug, Clone)]
+pub struct TrendRun {
+    pub test_type: String,
+    pub timestamp: Option<NaiveDateTime>,
+    pub coverage: Option<f64>,
+    pub snapshot: RunSnapshot,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Trends {
+    pub test_types: BTreeMap<String, TestTypeTrend>,
+}
+
+impl Trends {
+    pub fn new(mut runs: Vec<TrendRun>) -> Self {
+        runs.sort_by(|a, b| {
+            a.timestamp
+                .cmp(&b.timestamp)
+                .then_with(|| a.snapshot.report_id.cmp(&b.snapshot.report_id))
+        });
+
+        let mut test_types: BTreeMap<String, TestTypeTrend> = BTreeMap::new();
+        for run in &runs {
+            let trend = test_types.entry(run.test_type.clone()).or_default();
+            trend
+                .overall
+                .push(TrendPoint::new(run, &run.snapshot.overall, run.coverage));
+            for (endpoint, metrics) in &run.snapshot.endpoints {
+                trend
+                    .endpoints
+                    .entry(endpoint.clone())
+                    .or_default()
+                    .push(TrendPoint::new(run, metrics, None));
+            }
+        }
+
+        Self { test_types }
+    }
+
+    /// Number of runs across all test types
+    pub fn runs(&self) -> usize {
+        self.test_types
+            .values()
+            .map(|trend| trend.overall.len())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(id: &str, test_type: &str, timestamp: &str, p95: f64) -> TrendRun {
+        let metrics = RunMetrics {
+            p95: Some(p95),
+            ..RunMetrics::default()
+        };
+        TrendRun {
+            test_type: test_type.to_string(),
+            timestamp: NaiveDateTime::parse_from_str(timestamp, "%Y%m%d_%H%M%S").ok(),
+            coverage: Some(50.0),
+            snapshot: RunSnapshot {
+                report_id: id.to_string(),
+                overall: metrics,
+                endpoints: BTreeMap::from([("POST /mqtt/pub".to_string(), metrics)]),
+                checks: BTreeMap::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_groups_runs_by_test_type_oldest_first() {
+        let trends = Trends::new(vec![
+            run("b", "comprehensive", "20250702_090000", 120.0),
+            run("a", "comprehensive", "20250701_090000", 100.0),
+            run("c", "demo", "20250701_100000", 50.0),
+        ]);
+
+        assert_eq!(trends.runs(), 3);
+        let comprehensive = &trends.test_types["comprehensive"];
+        let p95: Vec<Option<f64>> = comprehensive.endpoints["POST /mqtt/pub"]
+            .iter()
+            .map(|point| point.p95)
+            .collect();
+        assert_eq!(p95, vec![Some(100.0), Some(120.0)]);
+        assert_eq!(comprehensive.overall[0].coverage, Some(50.0));
+        assert_eq!(comprehensive.endpoints["POST /mqtt/pub"][0].coverage, None);
+        assert_eq!(trends.test_types["demo"].overall.len(), 1);
+    }
+}
@@ -1,7 +1,6 @@
-use axum::{routing::post, Router};
-
 use crate::controller::channel::pub_user;
+use crate::routes::registry::RouteRegistry;
 
-pub async fn channel_router() -> Router {
-    Router::new().route("/pub", post(pub_user))
+pub async fn channel_router() -> RouteRegistry {
+    RouteRegistry::new().post("/pub", pub_user)
 }
@@ -1,19 +1,27 @@
 pub mod channel;
 pub mod dynamodb;
 pub mod mqtt;
+pub mod registry;
+pub mod report;
 pub mod user;
 
 use crate::routes::channel::channel_router;
 use crate::routes::dynamodb::dynamodb_router;
 use crate::routes::mqtt::mqtt_router;
+use crate::routes::registry::RouteRegistry;
+use crate::routes::report::report_router;
 use crate::routes::user::user_router;
 use axum::Router;
 
 pub async fn routes() -> Router {
-    let app = Router::new()
+    let services = RouteRegistry::new()
         .nest("/user", user_router().await)
         .nest("/channel", channel_router().await)
         .nest("/mqtt", mqtt_router().await)
         .nest("/dynamodb", dynamodb_router().await);
-    app
+
+    report_router(services.routes().to_vec())
+        .await
+        .merge(services)
+        .into_router()
 }
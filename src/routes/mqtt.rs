@@ -1,14 +1,435 @@
-use axum::{
-    routing::{get, post},
-    Router,
+use crate::config::consumer::ConsumerWorkers;
+use crate::config::scheduler::Scheduler;
+use crate::config::schema::SchemaRegistry;
+use crate::config::transport::Transport;
+use crate::controller::dead_letter::{peek_dead_letters, purge_dead_letters, replay_dead_letters};
+use crate::controller::mqtt::{
+    cancel_job, cancel_scheduled, consumer, create_job, get_consumer, get_job, get_scheduled,
+    list_consumers, list_jobs, list_scheduled, pool_stats, publisher, publisher_with_task,
+    start_consumer, stop_consumer, MqttState,
 };
+use crate::controller::rpc::{list_responders, rpc};
+use crate::controller::schema::{delete_schema, get_schema, list_schemas, put_schema};
+use crate::controller::stream::{stream_sse, stream_ws};
+use crate::controller::topology::{
+    bind_queue, declare_exchange, declare_queue, delete_exchange, delete_queue, get_queue,
+    peek_queue, purge_queue, unbind_queue,
+};
+use crate::model::publish_job::PublishJobs;
+use crate::routes::registry::RouteRegistry;
+
+pub async fn mqtt_router() -> RouteRegistry {
+    // One connection for every handler, over AMQP or MQTT; it is opened in the background
+    let transport = Transport::from_env();
+    let schemas = SchemaRegistry::from_env();
+    let consumers = ConsumerWorkers::new(transport.clone(), schemas.clone());
+    consumers.start_from_env().await;
+    let scheduler = Scheduler::from_env(transport.clone());
+    scheduler.start();
+
+    mqtt_routes(MqttState {
+        transport,
+        jobs: PublishJobs::new(),
+        consumers,
+        scheduler,
+        schemas,
+    })
+}
+
+/// The `/mqtt` endpoints over a given transport
+pub fn mqtt_routes(state: MqttState) -> RouteRegistry {
+    RouteRegistry::new()
+        .post("/publish", publisher)
+        .post("/publisher", publisher)
+        .post("/pub", publisher_with_task)
+        .get("/consume", consumer)
+        .get("/pool", pool_stats)
+        .get("/stream", stream_sse)
+        .get("/stream/ws", stream_ws)
+        .post("/rpc", rpc)
+        .get("/rpc/responders", list_responders)
+        .get("/jobs", list_jobs)
+        .post("/jobs", create_job)
+        .get("/jobs/:id", get_job)
+        .delete("/jobs/:id", cancel_job)
+        .get("/schemas", list_schemas)
+        .get("/schemas/:subject", get_schema)
+        .put("/schemas/:subject", put_schema)
+        .delete("/schemas/:subject", delete_schema)
+        .get("/scheduled", list_scheduled)
+        .get("/scheduled/:id", get_scheduled)
+        .delete("/scheduled/:id", cancel_scheduled)
+        .get("/consumers", list_consumers)
+        .post("/consumers", start_consumer)
+        .get("/consumers/:id", get_consumer)
+        .delete("/consumers/:id", stop_consumer)
+        .get("/dlq/:queue", peek_dead_letters)
+        .post("/dlq/:queue/replay", replay_dead_letters)
+        .post("/dlq/:queue/purge", purge_dead_letters)
+        .post("/exchanges", declare_exchange)
+        .delete("/exchanges/:name", delete_exchange)
+        .post("/queues", declare_queue)
+        .get("/queues/:name", get_queue)
+        .delete("/queues/:name", delete_queue)
+        .post("/queues/:name/purge", purge_queue)
+        .get("/queues/:name/peek", peek_queue)
+        .post("/bindings", bind_queue)
+        .delete("/bindings", unbind_queue)
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::broker::MessageBroker;
+    use crate::config::memory::MemoryBroker;
+    use crate::config::rpc::{ReplyFuture, RpcResponder};
+    use crate::model::consumer::ConsumedMessage;
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+        Router,
+    };
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn memory_state() -> (MqttState, MemoryBroker) {
+        let broker = MemoryBroker::new();
+        let transport = Transport::Memory(broker.clone());
+        let scheduler = Scheduler::new(transport.clone(), None);
+        scheduler.start();
+        let schemas = SchemaRegistry::new(None);
+        let state = MqttState {
+            consumers: ConsumerWorkers::new(transport.clone(), schemas.clone()),
+            scheduler,
+            schemas,
+            transport,
+            jobs: PublishJobs::new(),
+        };
+        (state, broker)
+    }
+
+    fn memory_router() -> (Router, MemoryBroker) {
+        let (state, broker) = memory_state();
+        (mqtt_routes(state).into_router(), broker)
+    }
+
+    async fn call(
+        router: &Router,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn test_publisher_queues_and_confirms() {
+        let (router, broker) = memory_router();
+        let (status, body) = call(
+            &router,
+            Method::POST,
+            "/publisher",
+            Some(json!({"topic": "orders/created", "message": {"id": 7}, "qos": 1})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["transport"], "memory");
+        assert_eq!(body["routing_key"], "orders.created");
+        assert_eq!(body["confirm"], "ack");
+        assert_eq!(broker.stats().queues["orders.created"], 1);
+
+        let (status, body) = call(
+            &router,
+            Method::POST,
+            "/publisher",
+            Some(json!({"topic": "orders", "message": "hi", "exchange": "events", "qos": 1})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["confirm"], "returned");
+
+        let (status, _) = call(
+            &router,
+            Method::POST,
+            "/publisher",
+            Some(json!({"topic": ""})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_published_properties_reach_consumers() {
+        let (router, broker) = memory_router();
+        let message = json!({
+            "topic": "invoices",
+            "message": {"total": 12},
+            "priority": 5,
+            "expiration_ms": 30000,
+            "content_encoding": "utf-8",
+            "message_id": "inv-1",
+            "timestamp": "2024-05-01T12:00:00Z",
+            "app_id": "billing",
+            "correlation_id": "c-9",
+            "headers": {"tenant": "acme"}
+        });
+        let (status, body) = call(&router, Method::POST, "/publish", Some(message)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["message_id"], "inv-1");
+
+        let mut subscription = broker.subscribe("invoices", 1).await.unwrap();
+        let received = subscription.next().await.unwrap().unwrap().to_json();
+        assert_eq!(received["id"], "inv-1");
+        assert_eq!(received["priority"], 5);
+        assert_eq!(received["expiration"], "30000");
+        assert_eq!(received["content_type"], "application/json");
+        assert_eq!(received["content_encoding"], "utf-8");
+        assert_eq!(received["timestamp"], "2024-05-01T12:00:00Z");
+        assert_eq!(received["app_id"], "billing");
+        assert_eq!(received["correlation_id"], "c-9");
+        assert_eq!(received["headers"]["tenant"], "acme");
+    }
+
+    #[tokio::test]
+    async fn test_delayed_publishes_are_listed_cancelled_and_delivered() {
+        let (router, broker) = memory_router();
+        let (status, body) = call(
+            &router,
+            Method::POST,
+            "/publish",
+            Some(json!({"topic": "reminders", "message": "later", "delay_ms": 3_600_000})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let id = body["scheduled"]["id"].as_str().unwrap().to_string();
+        assert_eq!(body["scheduled"]["routing_key"], "reminders");
+        assert!(!broker.stats().queues.contains_key("reminders"));
+
+        let (status, body) = call(&router, Method::GET, "/scheduled", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["scheduled"][0]["id"], id.as_str());
+
+        let (status, _) = call(&router, Method::DELETE, &format!("/scheduled/{}", id), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(&router, Method::GET, &format!("/scheduled/{}", id), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = call(
+            &router,
+            Method::POST,
+            "/publish",
+            Some(json!({"topic": "reminders", "message": "soon", "delay_ms": 20})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let mut subscription = broker.subscribe("reminders", 1).await.unwrap();
+        let received = tokio::time::timeout(Duration::from_secs(5), subscription.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.body, bytes::Bytes::from("soon"));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_publish_counts_every_message() {
+        let (router, broker) = memory_router();
+        let (status, body) = call(
+            &router,
+            Method::POST,
+            "/pub?producers=3&messages=20&queue=bulk&window=4",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["messages"], 60);
+        assert_eq!(body["counts"]["accepted"], 60);
+        assert_eq!(broker.stats().queues["bulk"], 60);
+
+        let (status, body) = call(&router, Method::GET, "/pool", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["broker"]["published"], 60);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_consume_drains_the_queue() {
+        let (router, broker) = memory_router();
+        call(&router, Method::POST, "/pub?producers=1&messages=5", None).await;
+
+        let (status, body) = call(&router, Method::GET, "/consume", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.as_str().unwrap().contains("Processed 5 messages"));
+        assert_eq!(broker.stats().queues["test"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_consumer_workers_handle_published_messages() {
+        let (router, _) = memory_router();
+        let (status, body) = call(
+            &router,
+            Method::POST,
+            "/consumers",
+            Some(json!({"queue": "jobs", "concurrency": 2})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = body["consumer"]["id"].as_str().unwrap().to_string();
+
+        for n in 0..3 {
+            let message = json!({"topic": "jobs", "message": {"n": n}});
+            call(&router, Method::POST, "/publisher", Some(message)).await;
+        }
+        let mut processed = 0;
+        for _ in 0..100 {
+            let (_, body) = call(&router, Method::GET, &format!("/consumers/{}", id), None).await;
+            processed = body["consumer"]["processed"].as_u64().unwrap();
+            if processed == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(processed, 3);
+
+        let retrying = json!({"queue": "jobs", "on_failure": {"strategy": "retry"}});
+        let (status, _) = call(&router, Method::POST, "/consumers", Some(retrying)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) =
+            call(&router, Method::DELETE, &format!("/consumers/{}", id), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["consumer"]["status"], "stopped");
+    }
+
+    #[tokio::test]
+    async fn test_schemas_gate_publishes_and_consumers() {
+        let (router, _) = memory_router();
+        // Published before the schema exists, so only the consumer can catch it
+        let early = json!({"topic": "orders", "message": {"id": 1}});
+        call(&router, Method::POST, "/publish", Some(early)).await;
+
+        let schema = json!({
+            "type": "object",
+            "required": ["id"],
+            "properties": {"id": {"type": "string"}}
+        });
+        let (status, _) = call(&router, Method::PUT, "/schemas/orders", Some(schema)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = call(&router, Method::GET, "/schemas", None).await;
+        assert_eq!(body["schemas"][0]["subject"], "orders");
+        let (status, _) = call(
+            &router,
+            Method::PUT,
+            "/schemas/orders",
+            Some(json!({"type": 5})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let invalid = json!({"topic": "orders", "message": {"id": 2}});
+        let (status, body) = call(&router, Method::POST, "/publish", Some(invalid)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["errors"][0]["path"], "/id");
+
+        let spec = json!({"queue": "orders", "validate_schema": true});
+        let (_, body) = call(&router, Method::POST, "/consumers", Some(spec)).await;
+        let id = body["consumer"]["id"].as_str().unwrap().to_string();
+        let valid = json!({"topic": "orders", "message": {"id": "o-3"}});
+        let (status, _) = call(&router, Method::POST, "/publish", Some(valid)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let mut consumer = Value::Null;
+        for _ in 0..100 {
+            let (_, body) = call(&router, Method::GET, &format!("/consumers/{}", id), None).await;
+            consumer = body["consumer"].clone();
+            if consumer["processed"] == 1 && consumer["invalid"] == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(consumer["processed"], 1);
+        assert_eq!(consumer["invalid"], 1);
+
+        let (status, _) = call(&router, Method::DELETE, "/schemas/orders", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(&router, Method::GET, "/schemas/orders", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_topology_needs_amqp() {
+        let (router, _) = memory_router();
+        let (status, _) = call(&router, Method::GET, "/queues/orders", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    struct FailingResponder;
+
+    impl RpcResponder for FailingResponder {
+        fn respond<'a>(&'a self, _request: &'a ConsumedMessage) -> ReplyFuture<'a> {
+            Box::pin(async { Err("out of stock".to_string()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpc_round_trips_through_a_responder() {
+        let (state, broker) = memory_state();
+        state
+            .consumers
+            .responders()
+            .register("failing", FailingResponder);
+        let router = mqtt_routes(state).into_router();
+        for (queue, responder) in [("rpc.echo", "echo"), ("rpc.failing", "failing")] {
+            let spec = json!({"queue": queue, "handler": {"type": "rpc", "responder": responder}});
+            let (status, _) = call(&router, Method::POST, "/consumers", Some(spec)).await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/rpc")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"queue": "rpc.echo", "message": {"ping": 1}}).to_string(),
+            ))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        assert!(response.headers().contains_key("x-correlation-id"));
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], br#"{"ping":1}"#);
+
+        let failing = json!({"queue": "rpc.failing", "message": "sku-1"});
+        let (status, body) = call(&router, Method::POST, "/rpc", Some(failing)).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(body["error"], "The responder failed: out of stock");
 
-use crate::controller::mqtt::{consumer, publisher, publisher_with_task};
+        let unanswered = json!({"queue": "rpc.nobody", "message": 1, "timeout_ms": 20});
+        let (status, _) = call(&router, Method::POST, "/rpc", Some(unanswered)).await;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        // Reply queues are gone once their request is answered or times out
+        let queues = broker.stats().queues;
+        assert!(queues.keys().all(|queue| !queue.starts_with("amq.gen-")));
 
-pub async fn mqtt_router() -> Router {
-    let router = Router::new()
-        .route("/publisher", post(publisher))
-        .route("/pub", post(publisher_with_task))
-        .route("/consume", get(consumer));
-    router
+        let (_, body) = call(&router, Method::GET, "/rpc/responders", None).await;
+        assert_eq!(body["responders"], json!(["echo", "failing"]));
+        let unknown = json!({"queue": "rpc.x", "handler": {"type": "rpc", "responder": "nope"}});
+        let (status, _) = call(&router, Method::POST, "/consumers", Some(unknown)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
 }
@@ -0,0 +1,156 @@
+use crate::model::route::RouteInfo;
+use axum::{
+    handler::Handler,
+    http::Method,
+    routing::{delete, get, post, put, MethodRouter},
+    Router,
+};
+
+/// A router that records every method and path it registers, so the server can
+/// report what it actually serves
+pub struct RouteRegistry<S = ()> {
+    router: Router<S>,
+    routes: Vec<RouteInfo>,
+}
+
+impl<S> Default for RouteRegistry<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self {
+            router: Router::new(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+impl<S> RouteRegistry<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `method` for `path` and route it. Paths registered more than once answer
+    /// every method they were given, so the recorded list always matches the router.
+    fn add(mut self, path: &str, method: Method, method_router: MethodRouter<S>) -> Self {
+        self.routes.push(RouteInfo::new(method.as_str(), path));
+        self.router = self.router.route(path, method_router);
+        self
+    }
+
+    pub fn get<H, T>(self, path: &str, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.add(path, Method::GET, get(handler))
+    }
+
+    pub fn post<H, T>(self, path: &str, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.add(path, Method::POST, post(handler))
+    }
+
+    pub fn put<H, T>(self, path: &str, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.add(path, Method::PUT, put(handler))
+    }
+
+    pub fn delete<H, T>(self, path: &str, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.add(path, Method::DELETE, delete(handler))
+    }
+
+    pub fn merge(mut self, other: RouteRegistry<S>) -> Self {
+        self.routes.extend(other.routes);
+        self.router = self.router.merge(other.router);
+        self
+    }
+
+    pub fn nest(mut self, prefix: &str, other: RouteRegistry<S>) -> Self {
+        self.routes.extend(other.routes.into_iter().map(|route| {
+            let path = match route.path.as_str() {
+                "/" => prefix.to_string(),
+                path => format!("{}{}", prefix, path),
+            };
+            RouteInfo { path, ..route }
+        }));
+        self.router = self.router.nest(prefix, other.router);
+        self
+    }
+
+    pub fn with_state<S2>(self, state: S) -> RouteRegistry<S2> {
+        RouteRegistry {
+            router: self.router.with_state(state),
+            routes: self.routes,
+        }
+    }
+
+    pub fn routes(&self) -> &[RouteInfo] {
+        &self.routes
+    }
+
+    pub fn into_router(self) -> Router<S> {
+        self.router
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, http::StatusCode};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_nested_routes_are_prefixed() {
+        let users = RouteRegistry::<()>::new()
+            .post("/users", || async { "created" })
+            .get("/users/:id", || async { "user" });
+        let registry = RouteRegistry::new()
+            .get("/", || async { "index" })
+            .nest("/user", users);
+
+        let endpoints: Vec<String> = registry.routes().iter().map(RouteInfo::endpoint).collect();
+        assert_eq!(
+            endpoints,
+            vec!["GET /", "POST /user/users", "GET /user/users/:id"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recorded_methods_match_the_router() {
+        let registry = RouteRegistry::<()>::new()
+            .get("/items/:id", || async { "item" })
+            .delete("/items/:id", || async { "deleted" });
+
+        let endpoints: Vec<String> = registry.routes().iter().map(RouteInfo::endpoint).collect();
+        assert_eq!(endpoints, vec!["GET /items/:id", "DELETE /items/:id"]);
+
+        let router = registry.into_router();
+        for (method, expected) in [
+            (Method::GET, StatusCode::OK),
+            (Method::DELETE, StatusCode::OK),
+            (Method::POST, StatusCode::METHOD_NOT_ALLOWED),
+        ] {
+            let request = Request::builder()
+                .method(method)
+                .uri("/items/7")
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), expected);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::config::report::ReportConfig;
+use crate::controller::live::{self, LiveLimits, LiveState};
+use crate::controller::report::{
+    compare_reports, index, list_reports, report_coverage, report_detail, report_raw, report_trends,
+};
+use crate::controller::report_api;
+use crate::model::live::LiveRuns;
+use crate::model::route::RouteInfo;
+use crate::routes::registry::RouteRegistry;
+use axum::{extract::DefaultBodyLimit, handler::Handler};
+
+/// `served_routes` are the routes of the other routers, which coverage is measured against
+/// together with the report viewer's own
+pub async fn report_router(served_routes: Vec<RouteInfo>) -> RouteRegistry {
+    let registry = RouteRegistry::<ReportConfig>::new()
+        .get("/", index)
+        .get("/reports", list_reports)
+        .get("/reports/compare", compare_reports)
+        .get("/reports/trends", report_trends)
+        .get("/reports/:id", report_detail)
+        .get("/reports/:id/coverage", report_coverage)
+        .get("/reports/:id/raw", report_raw)
+        .get("/api/reports", report_api::list_reports)
+        .post(
+            "/api/reports",
+            report_api::upload_report.layer(DefaultBodyLimit::max(report_api::MAX_UPLOAD_BYTES)),
+        )
+        .get("/api/reports/compare", report_api::compare_reports)
+        .get("/api/reports/trends", report_api::trends)
+        .get("/api/reports/retention", report_api::retention_plan)
+        .post("/api/reports/retention", report_api::apply_retention)
+        .get("/api/reports/:id", report_api::get_report)
+        .delete("/api/reports/:id", report_api::delete_report)
+        .post("/api/reports/:id/archive", report_api::archive_report)
+        .put("/api/reports/:id/baseline", report_api::pin_baseline)
+        .delete("/api/reports/:id/baseline", report_api::unpin_baseline)
+        .get("/api/reports/:id/thresholds", report_api::get_thresholds)
+        .get("/api/routes", report_api::list_routes);
+
+    let live_registry = RouteRegistry::<LiveState>::new()
+        .get("/reports/live", live::live_index)
+        .get("/reports/live/:id", live::live_run)
+        // `curl -T` uploads with PUT
+        .get("/api/live", live::list_runs)
+        .post("/api/live", live::ingest)
+        .put("/api/live", live::ingest)
+        .get("/api/live/:id/events", live::run_events);
+
+    let mut routes = registry.routes().to_vec();
+    routes.extend(live_registry.routes().iter().cloned());
+    routes.extend(served_routes);
+    let report_config = ReportConfig::from_env().with_routes(routes);
+    report_api::spawn_retention(report_config.clone());
+
+    let live_state = LiveState {
+        config: report_config.clone(),
+        runs: LiveRuns::new(),
+        limits: LiveLimits::default(),
+    };
+    registry
+        .with_state(report_config)
+        .merge(live_registry.with_state(live_state))
+}
@@ -1,14 +1,9 @@
-use axum::{
-    routing::{get, post},
-    Router,
-};
-
 use crate::controller::user::{create_user, get_user, get_users};
+use crate::routes::registry::RouteRegistry;
 
-pub async fn user_router() -> Router {
-    let router = Router::new()
-        .route("/users", post(create_user))
-        .route("/users", get(get_users))
-        .route("/users/:id", get(get_user));
-    router
+pub async fn user_router() -> RouteRegistry {
+    RouteRegistry::new()
+        .post("/users", create_user)
+        .get("/users", get_users)
+        .get("/users/:id", get_user)
 }
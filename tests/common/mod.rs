@@ -2,7 +2,6 @@ use serde_json::Value;
 use std::collections::HashMap;
 use tokio::time::{timeout, Duration};
 
-pub mod mock_rabbitmq;
 pub mod test_helpers;
 
 /// Test configuration constants
@@ -8,6 +8,5 @@ mod placeholder {
     async fn integration_tests_coming_soon() {
         // This is a placeholder test
         // Real integration tests will be implemented in Task 2
-        assert!(true, "Integration tests will be implemented in Task 2");
     }
 }
@@ -1,9 +1,9 @@
 // Test library for rust-api
-// This file organizes all test modules (unit, integration, common utilities)
+// This file organizes the test modules (integration, common utilities). Unit tests live next
+// to the code they cover, under `#[cfg(test)]`.
 
 pub mod common;
 pub mod integration;
-pub mod unit;
 
 // Re-export common test utilities for easy access
 pub use common::*;
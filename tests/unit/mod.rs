@@ -1,6 +0,0 @@
-// Unit test modules
-pub mod mqtt_controller_test;
-pub mod publisher_test;
-
-// Re-export common test utilities for use in unit tests
-pub use crate::common::*;
@@ -1,271 +0,0 @@
-use pretty_assertions::assert_eq;
-use std::time::Duration;
-use tokio_test;
-
-// Import the common test utilities
-use crate::common::mock_rabbitmq::{create_mock_connection, MockRabbitMQ};
-use crate::common::{with_timeout, TestEnvironment};
-
-// Since the actual functions are private, we'll test them through integration
-// or create test-friendly wrappers. For now, let's test the connection logic concept.
-
-/// Test configuration constants
-const TEST_QUEUE_NAME: &str = "test_queue";
-
-/// Test helper to simulate connection creation
-async fn test_create_connection(should_fail: bool) -> Result<bool, anyhow::Error> {
-    if should_fail {
-        Err(anyhow::anyhow!("Connection failed"))
-    } else {
-        Ok(true)
-    }
-}
-
-/// Test helper to simulate channel and queue setup
-async fn test_setup_channel_and_queue(connected: bool) -> Result<bool, anyhow::Error> {
-    if !connected {
-        return Err(anyhow::anyhow!("Cannot create channel - not connected"));
-    }
-
-    // Simulate queue declaration
-    Ok(true)
-}
-
-/// Test helper to simulate graceful connection close
-async fn test_close_connection_gracefully(connected: bool) -> Result<(), anyhow::Error> {
-    if !connected {
-        return Err(anyhow::anyhow!("Cannot close - already disconnected"));
-    }
-
-    // Simulate graceful close
-    Ok(())
-}
-
-#[tokio::test]
-async fn test_create_connection_success() {
-    let result = with_timeout(test_create_connection(false)).await;
-
-    assert!(result.is_ok(), "Timeout should not occur");
-    let connection_result = result.unwrap();
-    assert!(connection_result.is_ok(), "Connection should succeed");
-    assert_eq!(connection_result.unwrap(), true);
-}
-
-#[tokio::test]
-async fn test_create_connection_failure() {
-    let result = with_timeout(test_create_connection(true)).await;
-
-    assert!(result.is_ok(), "Timeout should not occur");
-    let connection_result = result.unwrap();
-    assert!(connection_result.is_err(), "Connection should fail");
-    assert!(connection_result
-        .unwrap_err()
-        .to_string()
-        .contains("Connection failed"));
-}
-
-#[tokio::test]
-async fn test_setup_channel_and_queue_success() {
-    let _env = TestEnvironment::new().unwrap();
-
-    let result = with_timeout(test_setup_channel_and_queue(true)).await;
-
-    assert!(result.is_ok(), "Timeout should not occur");
-    let setup_result = result.unwrap();
-    assert!(
-        setup_result.is_ok(),
-        "Channel and queue setup should succeed"
-    );
-    assert_eq!(setup_result.unwrap(), true);
-}
-
-#[tokio::test]
-async fn test_setup_channel_and_queue_failure() {
-    let _env = TestEnvironment::new().unwrap();
-
-    let result = with_timeout(test_setup_channel_and_queue(false)).await;
-
-    assert!(result.is_ok(), "Timeout should not occur");
-    let setup_result = result.unwrap();
-    assert!(
-        setup_result.is_err(),
-        "Channel and queue setup should fail when not connected"
-    );
-    assert!(setup_result
-        .unwrap_err()
-        .to_string()
-        .contains("not connected"));
-}
-
-#[tokio::test]
-async fn test_close_connection_gracefully_success() {
-    let result = with_timeout(test_close_connection_gracefully(true)).await;
-
-    assert!(result.is_ok(), "Timeout should not occur");
-    let close_result = result.unwrap();
-    assert!(close_result.is_ok(), "Connection close should succeed");
-}
-
-#[tokio::test]
-async fn test_close_connection_gracefully_already_closed() {
-    let result = with_timeout(test_close_connection_gracefully(false)).await;
-
-    assert!(result.is_ok(), "Timeout should not occur");
-    let close_result = result.unwrap();
-    assert!(
-        close_result.is_err(),
-        "Close should fail on already closed connection"
-    );
-    assert!(close_result
-        .unwrap_err()
-        .to_string()
-        .contains("already disconnected"));
-}
-
-#[tokio::test]
-async fn test_connection_workflow_success() {
-    // Test the complete connection workflow
-    let _env = TestEnvironment::new().unwrap();
-
-    // Step 1: Create connection
-    let connection_result = test_create_connection(false).await;
-    assert!(
-        connection_result.is_ok(),
-        "Connection creation should succeed"
-    );
-
-    // Step 2: Setup channel and queue
-    let setup_result = test_setup_channel_and_queue(true).await;
-    assert!(setup_result.is_ok(), "Channel setup should succeed");
-
-    // Step 3: Close connection gracefully
-    let close_result = test_close_connection_gracefully(true).await;
-    assert!(close_result.is_ok(), "Connection close should succeed");
-}
-
-#[tokio::test]
-async fn test_connection_workflow_failure() {
-    // Test workflow when initial connection fails
-    let _env = TestEnvironment::new().unwrap();
-
-    // Step 1: Fail to create connection
-    let connection_result = test_create_connection(true).await;
-    assert!(
-        connection_result.is_err(),
-        "Connection creation should fail"
-    );
-
-    // Step 2: Channel setup should also fail
-    let setup_result = test_setup_channel_and_queue(false).await;
-    assert!(
-        setup_result.is_err(),
-        "Channel setup should fail when not connected"
-    );
-}
-
-#[tokio::test]
-async fn test_mock_rabbitmq_basic_functionality() {
-    // Test our mock RabbitMQ functionality
-    let mock = MockRabbitMQ::new();
-
-    // Test connection state
-    assert!(mock.is_connected(), "Mock should start connected");
-
-    // Test message operations
-    mock.add_message("test message".to_string());
-    let messages = mock.get_messages();
-    assert_eq!(messages.len(), 1);
-    assert_eq!(messages[0], "test message");
-
-    // Test disconnection
-    mock.set_connected(false);
-    assert!(!mock.is_connected(), "Mock should be disconnected");
-
-    // Test connection failure scenario
-    let failed_mock = MockRabbitMQ::with_connection_failure();
-    assert!(
-        !failed_mock.is_connected(),
-        "Failed mock should start disconnected"
-    );
-}
-
-#[tokio::test]
-async fn test_mock_connection_creation() {
-    // Test successful mock connection
-    let mock = MockRabbitMQ::new();
-    let connection_result = create_mock_connection(&mock).await;
-    assert!(connection_result.is_ok(), "Mock connection should succeed");
-
-    // Test failed mock connection
-    let failed_mock = MockRabbitMQ::with_connection_failure();
-    let failed_connection_result = create_mock_connection(&failed_mock).await;
-    assert!(
-        failed_connection_result.is_err(),
-        "Failed mock connection should fail"
-    );
-    assert!(failed_connection_result
-        .unwrap_err()
-        .to_string()
-        .contains("Connection failed"));
-}
-
-#[tokio::test]
-async fn test_mock_channel_operations() {
-    // Test mock channel creation and operations
-    let mock = MockRabbitMQ::new();
-    let connection = create_mock_connection(&mock).await.unwrap();
-
-    // Test channel creation
-    let channel_result = connection.create_channel().await;
-    assert!(
-        channel_result.is_ok(),
-        "Mock channel creation should succeed"
-    );
-
-    let channel = channel_result.unwrap();
-
-    // Test queue declaration
-    let queue_result = channel
-        .queue_declare(
-            TEST_QUEUE_NAME,
-            lapin::options::QueueDeclareOptions::default(),
-            lapin::types::FieldTable::default(),
-        )
-        .await;
-    assert!(
-        queue_result.is_ok(),
-        "Mock queue declaration should succeed"
-    );
-}
-
-/// Integration-style test for the actual module functions
-/// Note: This requires the actual functions to be public or have test-friendly wrappers
-#[cfg(test)]
-mod integration_tests {
-    // These would test the actual functions if they were public
-    // For now, they serve as documentation of what we want to test
-
-    #[tokio::test]
-    #[ignore] // Ignored until we can access the actual functions
-    async fn test_actual_create_connection() {
-        // This would test the actual create_connection() function
-        // let result = create_connection().await;
-        // We'd need either public functions or test-friendly wrappers
-    }
-
-    #[tokio::test]
-    #[ignore] // Ignored until we can access the actual functions
-    async fn test_actual_setup_channel_and_queue() {
-        // This would test the actual setup_channel_and_queue() function
-        // let conn = create_connection().await.unwrap();
-        // let result = setup_channel_and_queue(&conn).await;
-    }
-
-    #[tokio::test]
-    #[ignore] // Ignored until we can access the actual functions
-    async fn test_actual_close_connection_gracefully() {
-        // This would test the actual close_connection_gracefully() function
-        // let conn = create_connection().await.unwrap();
-        // close_connection_gracefully(conn, "test").await;
-    }
-}